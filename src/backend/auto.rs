@@ -0,0 +1,86 @@
+//! Backend that picks iwd, nmcli or nl80211 for each call, whichever owns
+//! the interface.
+
+use std::sync::{ Arc, Mutex };
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::{ self, BoxStream, StreamExt };
+
+use super::iwd::IwdBackend;
+use super::nl80211::Nl80211Backend;
+use super::nmcli::NmcliBackend;
+use super::{ Capabilities, Interface, ScanBackend, ScanError, ScanEvent, ScanRequest };
+
+pub const NAME: &str = "auto";
+
+/// Uses iwd when it manages the requested interface, nmcli otherwise, and
+/// the kernel's nl80211 interface when nmcli is not installed either. The
+/// choice is made again for every scan, so starting or stopping iwd takes
+/// effect without a restart.
+#[derive(Debug, Clone)]
+pub struct AutoBackend {
+    iwd: IwdBackend,
+    nmcli: NmcliBackend,
+    nl80211: Nl80211Backend,
+    /// Those of the backend selected last, shared between clones.
+    capabilities: Arc<Mutex<Capabilities>>,
+}
+
+impl Default for AutoBackend {
+    fn default() -> Self {
+        Self::with_backends(IwdBackend::default(), NmcliBackend::default())
+    }
+}
+
+impl AutoBackend {
+    pub(super) fn with_backends(iwd: IwdBackend, nmcli: NmcliBackend) -> Self {
+        let capabilities = Arc::new(Mutex::new(nmcli.capabilities()));
+        Self { iwd, nmcli, nl80211: Nl80211Backend, capabilities }
+    }
+
+    /// iwd before 2.11 cannot list BSSes, so the kernel is asked directly
+    /// for the interfaces it manages then.
+    pub(super) async fn select(&self, interface: Option<&str>) -> Box<dyn ScanBackend> {
+        let backend: Box<dyn ScanBackend> = if self.iwd.manages(interface).await {
+            if self.iwd.lists_bsses(interface).await {
+                Box::new(self.iwd.clone())
+            } else {
+                Box::new(self.nl80211.clone())
+            }
+        } else if self.nmcli.is_installed() {
+            Box::new(self.nmcli.clone())
+        } else {
+            Box::new(self.nl80211.clone())
+        };
+        *self.capabilities.lock().unwrap() = backend.capabilities();
+        backend
+    }
+}
+
+impl ScanBackend for AutoBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    /// Those of the backend selected for the latest call, nmcli's until
+    /// then.
+    fn capabilities(&self) -> Capabilities {
+        *self.capabilities.lock().unwrap()
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        let backend = self.clone();
+        async move {
+            backend.select(None).await.interfaces().await
+        }.boxed()
+    }
+
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        let backend = self.clone();
+        stream::once(async move {
+            backend.select(request.interface.as_deref()).await.scan(request)
+        })
+            .flatten()
+            .boxed()
+    }
+}
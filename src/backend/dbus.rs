@@ -0,0 +1,46 @@
+//! Connection handling shared by the D-Bus backends.
+
+use zbus::Connection;
+
+use super::ScanError;
+
+/// Which bus a service is reached on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bus {
+    System,
+    /// A specific bus address, used to test against a mock service.
+    #[cfg(test)]
+    Address(String),
+}
+
+pub async fn connect(bus: &Bus) -> Result<Connection, ScanError> {
+    let connection = match bus {
+        Bus::System => Connection::system().await,
+        #[cfg(test)]
+        Bus::Address(address) => {
+            match zbus::ConnectionBuilder::address(address.as_str()) {
+                Ok(builder) => builder.build().await,
+                Err(err) => Err(err),
+            }
+        }
+    };
+    connection.map_err(|_| ScanError::ToolMissing(String::from("D-Bus system bus")))
+}
+
+/// The D-Bus error name of a failed call. zbus turns the standard bus errors
+/// into [`zbus::fdo::Error`] variants, which are mapped back to their names.
+pub fn error_name(err: &zbus::Error) -> Option<String> {
+    match err {
+        zbus::Error::MethodError(name, _, _) => Some(name.as_str().to_string()),
+        zbus::Error::FDO(fdo) => {
+            match fdo.as_ref() {
+                zbus::fdo::Error::ServiceUnknown(_) | zbus::fdo::Error::NameHasNoOwner(_) => {
+                    Some(String::from("org.freedesktop.DBus.Error.ServiceUnknown"))
+                }
+                zbus::fdo::Error::AccessDenied(_) => Some(String::from("org.freedesktop.DBus.Error.AccessDenied")),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
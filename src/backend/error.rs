@@ -0,0 +1,56 @@
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+/// Why a scan (or interface listing) failed.
+///
+/// Errors are carried through iced messages, so they hold plain data rather
+/// than the underlying `io::Error`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+    /// The external tool or service the backend relies on is not installed
+    /// or not running.
+    ToolMissing(String),
+    /// The backend is not allowed to scan, usually because it needs root or
+    /// a polkit authorisation.
+    PermissionDenied(String),
+    /// The Wi-Fi radio is switched off (rfkill or NetworkManager).
+    RadioDisabled,
+    /// No wireless interface is present, or the requested one does not
+    /// exist.
+    NoInterface(Option<String>),
+    /// The backend answered but its output could not be understood.
+    Parse(String),
+    /// The backend did not answer within the allowed time.
+    Timeout(Duration),
+    /// Any other failure reported by the backend.
+    Failed(String),
+}
+
+impl ScanError {
+    /// Maps an error from spawning `tool` to the matching variant.
+    pub fn from_spawn(tool: &str, err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => ScanError::ToolMissing(tool.to_string()),
+            io::ErrorKind::PermissionDenied => ScanError::PermissionDenied(format!("cannot execute {}", tool)),
+            _ => ScanError::Failed(format!("{}: {}", tool, err)),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::ToolMissing(tool) => write!(f, "{} is not installed or not running", tool),
+            ScanError::PermissionDenied(detail) => write!(f, "Permission denied: {}", detail),
+            ScanError::RadioDisabled => f.write_str("The Wi-Fi radio is disabled"),
+            ScanError::NoInterface(Some(name)) => write!(f, "Wireless interface {} not found", name),
+            ScanError::NoInterface(None) => f.write_str("No wireless interface found"),
+            ScanError::Parse(detail) => write!(f, "Could not parse scan results: {}", detail),
+            ScanError::Timeout(after) => write!(f, "Scan timed out after {:.1} s", after.as_secs_f32()),
+            ScanError::Failed(detail) => write!(f, "Scan failed: {}", detail),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {}
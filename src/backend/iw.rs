@@ -0,0 +1,310 @@
+//! Backend that runs `iw dev <if> scan`, for systems without NetworkManager.
+
+mod parse;
+
+use std::time::{ Duration, SystemTime };
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::BoxStream;
+
+use super::process::{ self, ProcessLines };
+use super::{
+    scan_stream,
+    Capabilities,
+    Emitter,
+    Interface,
+    RescanPolicy,
+    ScanBackend,
+    ScanError,
+    ScanEvent,
+    ScanRequest,
+    AUTO_RESCAN_MAX_AGE,
+    DEFAULT_TIMEOUT,
+};
+use crate::ie::CAPABILITY_IBSS;
+use crate::network::{ dbm_to_percent, Mode, Network, Security, Ssid };
+
+pub const NAME: &str = "iw";
+
+#[derive(Debug, Clone)]
+pub struct IwBackend {
+    program: String,
+}
+
+impl Default for IwBackend {
+    fn default() -> Self {
+        Self { program: String::from("iw") }
+    }
+}
+
+/// Maps iw's stderr on a non-zero exit to a [`ScanError`]. iw reports
+/// netlink failures as `command failed: <strerror> (<-errno>)`.
+fn classify_failure(interface: Option<&str>, stderr: &str) -> ScanError {
+    let message = stderr.trim().to_string();
+
+    if message.contains("(-1)") || message.contains("(-13)") {
+        ScanError::PermissionDenied(message)
+    } else if message.contains("(-19)") {
+        ScanError::NoInterface(interface.map(str::to_string))
+    } else if message.contains("(-100)") || message.contains("(-132)") {
+        ScanError::RadioDisabled
+    } else if message.contains("nl80211 not found") {
+        ScanError::ToolMissing(String::from("nl80211"))
+    } else {
+        ScanError::Failed(message)
+    }
+}
+
+/// Turns a parsed BSS block into a network. Blocks without a BSSID are
+/// dropped. A hidden network is decloaked when iw prints a probe response
+/// with its SSID next to the beacon elements.
+fn network_from_bss(bss: parse::Bss) -> Option<Network> {
+    let bssid = bss.bssid?;
+    let ssid = Ssid::from_bytes(bss.ssid.unwrap_or_default());
+
+    let signal_dbm = bss.signal_dbm.map(|dbm| dbm.round() as i32);
+    let beacon_ssid = bss.beacon_ssid.map_or_else(|| ssid.clone(), Ssid::from_bytes);
+    let mut network = Network::new(beacon_ssid, bssid, signal_dbm.map_or(0, dbm_to_percent));
+    network.reveal_ssid(ssid);
+    network.signal_dbm = signal_dbm;
+    network.frequency_mhz = bss.frequency_mhz;
+    network.security = Security::from_elements(bss.capability, &bss.elements);
+    if bss.capability.is_some_and(|capability| capability & CAPABILITY_IBSS != 0) {
+        network.mode = Mode::AdHoc;
+    }
+    network.capability_info = bss.capability;
+    network.elements = bss.elements;
+    network.raw = Some(bss.raw);
+    network.derive_channel();
+    if let Some(last_seen) = bss.last_seen_ms {
+        let now = SystemTime::now();
+        network.last_seen = now.checked_sub(Duration::from_millis(last_seen)).unwrap_or(now);
+    }
+    Some(network)
+}
+
+/// Parses saved `iw dev <interface> scan` output, as used by recorded scans.
+pub(super) fn parse_networks(output: &str) -> Vec<Network> {
+    parse::parse_scan(output).into_iter().filter_map(network_from_bss).collect()
+}
+
+/// Runs `iw dev <interface> scan [dump]`, emitting networks as their blocks
+/// complete. Returns the age of the freshest BSS, if iw reported any.
+async fn run_scan(
+    program: &str,
+    interface: &str,
+    dump: bool,
+    timeout: Duration,
+    emit: Option<&Emitter>
+) -> Result<(Vec<Network>, Option<Duration>), ScanError> {
+    let mut args = vec!["dev", interface, "scan"];
+    if dump {
+        args.push("dump");
+    }
+    let mut process = ProcessLines::spawn(program, &args, timeout)?;
+    let mut parser = parse::ScanParser::default();
+    let mut networks = Vec::new();
+    let mut freshest: Option<u64> = None;
+
+    let mut handle = |bss: parse::Bss| {
+        if let Some(last_seen) = bss.last_seen_ms {
+            freshest = Some(freshest.map_or(last_seen, |freshest| freshest.min(last_seen)));
+        }
+        if let Some(network) = network_from_bss(bss) {
+            match emit {
+                Some(emit) => emit.network(network),
+                None => networks.push(network),
+            }
+        }
+    };
+    while let Some(line) = process.next_line().await? {
+        if let Some(bss) = parser.push_line(&line) {
+            handle(bss);
+        }
+    }
+    if let Some(bss) = parser.finish() {
+        handle(bss);
+    }
+    process.finish(|stderr| classify_failure(Some(interface), stderr)).await?;
+    Ok((networks, freshest.map(Duration::from_millis)))
+}
+
+/// Scans one interface according to `policy`. Triggering a scan needs
+/// `CAP_NET_ADMIN`; without it the cached `scan dump` results are used.
+async fn scan_interface(
+    program: &str,
+    interface: &str,
+    policy: RescanPolicy,
+    timeout: Duration,
+    emit: &Emitter
+) -> Result<(), ScanError> {
+    if policy != RescanPolicy::Force {
+        let (networks, age) = run_scan(program, interface, true, timeout, None).await?;
+        let stale = age.is_none_or(|age| age > AUTO_RESCAN_MAX_AGE);
+        if policy == RescanPolicy::Cached || !stale {
+            networks.into_iter().for_each(|network| emit.network(network));
+            if let Some(age) = age {
+                emit.cache_age(age);
+            }
+            return Ok(());
+        }
+    }
+
+    match run_scan(program, interface, false, timeout, Some(emit)).await {
+        Ok((_, age)) => {
+            emit.cache_age(age.unwrap_or_default());
+            Ok(())
+        }
+        Err(ScanError::PermissionDenied(_)) => {
+            let (_, age) = run_scan(program, interface, true, timeout, Some(emit)).await?;
+            if let Some(age) = age {
+                emit.cache_age(age);
+            }
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+impl ScanBackend for IwBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            trigger_scan: true,
+            signal_dbm: true,
+            frequency: true,
+            security: true,
+            information_elements: true,
+        }
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        let program = self.program.clone();
+        async move {
+            let stdout = process::output(&program, &["dev"], DEFAULT_TIMEOUT, |stderr| classify_failure(None, stderr)).await?;
+            Ok(parse::parse_dev(&stdout))
+        }.boxed()
+    }
+
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        let program = self.program.clone();
+        scan_stream(move |emit| async move {
+            let interfaces = match request.interface {
+                Some(interface) => vec![interface],
+                None => {
+                    let stdout = process::output(&program, &["dev"], request.timeout, |stderr| {
+                        classify_failure(None, stderr)
+                    }).await?;
+                    parse::parse_dev(&stdout)
+                        .into_iter()
+                        .map(|interface| interface.name)
+                        .collect()
+                }
+            };
+            if interfaces.is_empty() {
+                return Err(ScanError::NoInterface(None));
+            }
+            for interface in &interfaces {
+                scan_interface(&program, interface, request.rescan, request.timeout, &emit).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::HiddenSsid;
+
+    const SCAN: &str = include_str!("../../fixtures/iw/scan.txt");
+
+    fn networks() -> Vec<Network> {
+        parse_networks(SCAN)
+    }
+
+    #[test]
+    fn converts_fixture_to_networks() {
+        let networks = networks();
+        let ssids: Vec<_> = networks
+            .iter()
+            .map(|network| network.ssid.display())
+            .collect();
+        assert_eq!(ssids, ["HomeNet", "Office Net", "Cafe Free WiFi", "", "Corp", "Corp6"]);
+        assert_eq!(networks[3].hidden_ssid, Some(HiddenSsid::NullPadded { length: 6 }));
+        let raw = networks[0].raw.as_deref().unwrap();
+        assert!(raw.starts_with("BSS ") && raw.contains("\tSSID: HomeNet"));
+        assert!(!raw.contains("Office Net"));
+
+        let home = &networks[0];
+        assert_eq!(home.signal_dbm, Some(-48));
+        assert_eq!(home.signal_percent, 87);
+        assert_eq!(home.frequency_mhz, Some(2437));
+        assert_eq!(home.channel, Some(6));
+        assert_eq!(home.capability_info, Some(0x1411));
+        assert_eq!(networks[1].channel, Some(36));
+        assert_eq!(networks[5].channel, Some(37));
+    }
+
+    #[test]
+    fn derives_channel_width_from_operation_elements() {
+        let networks = networks();
+        assert_eq!(networks[0].channel_summary(), "2.4 GHz · ch 6 · 20 MHz");
+        assert_eq!(networks[1].channel_summary(), "5 GHz · ch 36 · 80 MHz");
+        assert_eq!(networks[1].center_frequency_mhz, Some(5210));
+        assert_eq!(networks[2].channel_summary(), "2.4 GHz · ch 1 · 20 MHz");
+        assert_eq!(networks[5].channel_summary(), "6 GHz · ch 37 · 160 MHz");
+        assert_eq!(networks[5].center_frequency_mhz, Some(6185));
+    }
+
+    #[test]
+    fn classifies_security() {
+        let security: Vec<_> = networks()
+            .iter()
+            .map(|network| network.security)
+            .collect();
+        assert_eq!(security, [
+            Security::Wpa2Psk,
+            Security::Wpa2Wpa3,
+            Security::Open,
+            Security::WpaPsk,
+            Security::EnterpriseSuiteB,
+            Security::Wpa3Sae,
+        ]);
+        assert_eq!(
+            Security::from_elements(Some(crate::ie::CAPABILITY_PRIVACY), &crate::ie::Elements::default()),
+            Security::Wep
+        );
+    }
+
+    #[test]
+    fn decloaks_hidden_networks_from_probe_responses() {
+        let output = "BSS 00:11:22:33:44:55(on wlan0)
+\tInformation elements from Probe Response frame:
+\tSSID: Backstage
+\tInformation elements from Beacon frame:
+\tSSID: 
+";
+        let network = &parse_networks(output)[0];
+        assert_eq!(network.ssid.display(), "Backstage");
+        assert_eq!(network.hidden_ssid, Some(HiddenSsid::ZeroLength));
+        assert!(network.is_decloaked());
+        assert_eq!(network.ssid_label(), "Backstage (decloaked)");
+    }
+
+    #[test]
+    fn classifies_failures() {
+        assert!(matches!(
+            classify_failure(Some("wlan0"), "command failed: Operation not permitted (-1)\n"),
+            ScanError::PermissionDenied(_)
+        ));
+        assert_eq!(
+            classify_failure(Some("wlan9"), "command failed: No such device (-19)\n"),
+            ScanError::NoInterface(Some(String::from("wlan9")))
+        );
+        assert_eq!(classify_failure(Some("wlan0"), "command failed: Network is down (-100)\n"), ScanError::RadioDisabled);
+    }
+}
@@ -0,0 +1,591 @@
+//! Parsing of `iw dev <if> scan` and `iw dev` output.
+//!
+//! iw prints one block per BSS, starting with an unindented `BSS <mac>`
+//! line. Attributes are indented by one tab; sections such as `RSN:` or
+//! `HT operation:` continue on lines indented by two or more tabs, usually
+//! as ` * key: value` items.
+//!
+//! When iw prints both the probe response and the beacon elements of a BSS,
+//! the beacon set follows an `Information elements from Beacon frame:`
+//! line. Only its SSID is kept, to tell hidden networks apart.
+
+use crate::backend::Interface;
+use crate::ie::{
+    Akm,
+    Cipher,
+    Country,
+    Elements,
+    HeOperation,
+    HtOperation,
+    Rate,
+    Rsn,
+    SecondaryChannelOffset,
+    SixGhzOperation,
+    VhtOperation,
+    IEEE_OUI,
+};
+use crate::network::MacAddress;
+
+/// One BSS block of `iw scan` output.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bss {
+    pub bssid: Option<MacAddress>,
+    pub associated: bool,
+    pub frequency_mhz: Option<u32>,
+    pub signal_dbm: Option<f32>,
+    pub last_seen_ms: Option<u64>,
+    pub capability: Option<u16>,
+    pub ssid: Option<Vec<u8>>,
+    /// The SSID in beacons, when iw prints it apart from the probe response.
+    pub beacon_ssid: Option<Vec<u8>>,
+    pub elements: Elements,
+    /// The lines of the block, as printed.
+    pub raw: String,
+}
+
+/// The multi-line section the parser is currently inside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    None,
+    Rsn,
+    Wpa,
+    HtOperation,
+    VhtOperation,
+    HeOperation,
+    /// The 6 GHz Operation Information block nested in `HE Operation`.
+    SixGhzOperation,
+    Other,
+}
+
+/// Incremental parser, fed one line at a time so BSSes can be reported while
+/// iw is still printing.
+#[derive(Debug)]
+pub struct ScanParser {
+    current: Option<Bss>,
+    section: Section,
+    /// Inside the beacon element set of the current BSS.
+    beacon_elements: bool,
+}
+
+impl Default for ScanParser {
+    fn default() -> Self {
+        Self { current: None, section: Section::None, beacon_elements: false }
+    }
+}
+
+impl ScanParser {
+    /// Feeds one line. Returns the previous BSS once the next one starts.
+    pub fn push_line(&mut self, line: &str) -> Option<Bss> {
+        if let Some(header) = line.strip_prefix("BSS ") {
+            let finished = self.current.take();
+            self.section = Section::None;
+            self.beacon_elements = false;
+            let mut bss = parse_header(header);
+            bss.raw = line.to_string();
+            self.current = Some(bss);
+            return finished;
+        }
+
+        let bss = self.current.as_mut()?;
+        bss.raw.push('\n');
+        bss.raw.push_str(line);
+        let depth = line.chars().take_while(|c| *c == '\t').count();
+        let content = line.trim();
+
+        if depth == 1 && content == "Information elements from Beacon frame:" {
+            self.beacon_elements = true;
+            self.section = Section::Other;
+        } else if depth == 1 && self.beacon_elements {
+            if let Some(ssid) = content.strip_prefix("SSID:") {
+                bss.beacon_ssid = Some(unescape_ssid(ssid.trim()));
+            }
+            self.section = Section::Other;
+        } else if depth == 1 {
+            self.section = parse_attribute(bss, content);
+        } else if depth > 1 {
+            parse_section_line(bss, &mut self.section, content);
+        }
+        None
+    }
+
+    /// Returns the last BSS once the output is exhausted.
+    pub fn finish(&mut self) -> Option<Bss> {
+        self.section = Section::None;
+        self.current.take()
+    }
+}
+
+/// Parses a complete `iw scan` output.
+pub fn parse_scan(output: &str) -> Vec<Bss> {
+    let mut parser = ScanParser::default();
+    let mut bsses: Vec<Bss> = output
+        .lines()
+        .filter_map(|line| parser.push_line(line))
+        .collect();
+    bsses.extend(parser.finish());
+    bsses
+}
+
+/// `aa:bb:cc:dd:ee:ff(on wlan0) -- associated`
+fn parse_header(header: &str) -> Bss {
+    let mac = header.split(['(', ' ']).next().unwrap_or_default();
+    Bss {
+        bssid: mac.parse().ok(),
+        associated: header.ends_with("-- associated"),
+        ..Bss::default()
+    }
+}
+
+/// Parses a one-tab attribute line and returns the section it opens.
+fn parse_attribute(bss: &mut Bss, content: &str) -> Section {
+    let Some((key, value)) = content.split_once(':') else {
+        return Section::Other;
+    };
+    let value = value.trim();
+
+    match key {
+        "freq" => {
+            bss.frequency_mhz = value.parse::<f32>().ok().map(|freq| freq.round() as u32);
+        }
+        "signal" => {
+            bss.signal_dbm = value.trim_end_matches("dBm").trim().parse().ok();
+        }
+        "last seen" => {
+            if let Some(ms) = value.strip_suffix("ms ago") {
+                bss.last_seen_ms = ms.trim().parse().ok();
+            }
+        }
+        "capability" => {
+            bss.capability = parenthesized_hex(value);
+        }
+        "SSID" => {
+            bss.ssid = Some(unescape_ssid(value));
+        }
+        "Supported rates" | "Extended supported rates" => {
+            bss.elements.supported_rates.extend(value.split_whitespace().filter_map(parse_rate));
+        }
+        "DS Parameter set" => {
+            bss.elements.ds_channel = value.strip_prefix("channel").and_then(|channel| channel.trim().parse().ok());
+        }
+        "Country" => {
+            bss.elements.country = Some(parse_country(value));
+        }
+        "RSN" => {
+            bss.elements.rsn = Some(empty_rsn());
+            parse_security_item(bss.elements.rsn.as_mut(), value);
+            return Section::Rsn;
+        }
+        "WPA" => {
+            bss.elements.wpa = Some(empty_rsn());
+            parse_security_item(bss.elements.wpa.as_mut(), value);
+            return Section::Wpa;
+        }
+        "HT operation" => {
+            bss.elements.ht_operation = Some(HtOperation {
+                primary_channel: 0,
+                secondary_channel_offset: SecondaryChannelOffset::None,
+                any_channel_width: false,
+            });
+            return Section::HtOperation;
+        }
+        "VHT operation" => {
+            bss.elements.vht_operation = Some(VhtOperation { channel_width: 0, center_segment0: 0, center_segment1: 0 });
+            return Section::VhtOperation;
+        }
+        "HE Operation" | "HE operation" => {
+            bss.elements.he_operation = Some(HeOperation::default());
+            return Section::HeOperation;
+        }
+        _ => {}
+    }
+    Section::Other
+}
+
+fn parse_section_line(bss: &mut Bss, section: &mut Section, content: &str) {
+    match *section {
+        Section::Rsn => parse_security_item(bss.elements.rsn.as_mut(), content),
+        Section::Wpa => parse_security_item(bss.elements.wpa.as_mut(), content),
+        Section::HtOperation => {
+            let (Some(ht), Some((key, value))) = (bss.elements.ht_operation.as_mut(), item(content)) else {
+                return;
+            };
+            match key {
+                "primary channel" => ht.primary_channel = value.parse().unwrap_or(0),
+                "secondary channel offset" => {
+                    ht.secondary_channel_offset = match value {
+                        "above" => SecondaryChannelOffset::Above,
+                        "below" => SecondaryChannelOffset::Below,
+                        _ => SecondaryChannelOffset::None,
+                    };
+                }
+                "STA channel width" => ht.any_channel_width = value == "any",
+                _ => {}
+            }
+        }
+        Section::VhtOperation => {
+            let (Some(vht), Some((key, value))) = (bss.elements.vht_operation.as_mut(), item(content)) else {
+                return;
+            };
+            let number = leading_number(value);
+            match key {
+                "channel width" => vht.channel_width = number,
+                "center freq segment 1" => vht.center_segment0 = number,
+                "center freq segment 2" => vht.center_segment1 = number,
+                _ => {}
+            }
+        }
+        Section::HeOperation => {
+            let (Some(he), Some((key, value))) = (bss.elements.he_operation.as_mut(), item(content)) else {
+                return;
+            };
+            match key {
+                "BSS Color" => he.bss_color = value.parse().ok(),
+                "6 GHz Operation Information" => {
+                    he.six_ghz = Some(SixGhzOperation {
+                        primary_channel: 0,
+                        channel_width: 0,
+                        center_segment0: 0,
+                        center_segment1: 0,
+                    });
+                    *section = Section::SixGhzOperation;
+                }
+                _ => {}
+            }
+        }
+        Section::SixGhzOperation => {
+            let (Some(six_ghz), Some((key, value))) = (
+                bss.elements.he_operation.as_mut().and_then(|he| he.six_ghz.as_mut()),
+                item(content),
+            ) else {
+                return;
+            };
+            match key {
+                "Primary Channel" => six_ghz.primary_channel = leading_number(value),
+                "Channel Width" => {
+                    six_ghz.channel_width = match leading_number(value) {
+                        40 => 1,
+                        80 => 2,
+                        160 => 3,
+                        _ => 0,
+                    };
+                }
+                "Channel Center Frequency Segment 0" => six_ghz.center_segment0 = leading_number(value),
+                "Channel Center Frequency Segment 1" => six_ghz.center_segment1 = leading_number(value),
+                _ => {}
+            }
+        }
+        Section::None | Section::Other => {}
+    }
+}
+
+/// Splits ` * key: value` (or `key: value`) into its parts.
+fn item(content: &str) -> Option<(&str, &str)> {
+    let content = content.trim_start_matches('*').trim();
+    let (key, value) = content.split_once(':')?;
+    Some((key.trim(), value.trim()))
+}
+
+fn leading_number(value: &str) -> u8 {
+    let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().unwrap_or(0)
+}
+
+fn empty_rsn() -> Rsn {
+    Rsn {
+        version: 1,
+        group_cipher: None,
+        pairwise_ciphers: Vec::new(),
+        akm_suites: Vec::new(),
+        capabilities: None,
+        group_management_cipher: None,
+    }
+}
+
+fn parse_security_item(rsn: Option<&mut Rsn>, content: &str) {
+    let (Some(rsn), Some((key, value))) = (rsn, item(content)) else {
+        return;
+    };
+    match key {
+        "Version" => rsn.version = value.parse().unwrap_or(1),
+        "Group cipher" => rsn.group_cipher = parse_cipher(value),
+        "Pairwise ciphers" => rsn.pairwise_ciphers = value.split_whitespace().filter_map(parse_cipher).collect(),
+        "Authentication suites" => rsn.akm_suites = parse_akms(value),
+        "Capabilities" => rsn.capabilities = parenthesized_hex(value),
+        _ => {}
+    }
+}
+
+/// Cipher names as printed by iw, or `xx-xx-xx:n` for unknown suites.
+fn parse_cipher(name: &str) -> Option<Cipher> {
+    let kind = match name {
+        "Use group cipher suite" => 0,
+        "WEP-40" => 1,
+        "TKIP" => 2,
+        "CCMP" | "CCMP-128" => 4,
+        "WEP-104" => 5,
+        "AES-128-CMAC" | "BIP-CMAC-128" => 6,
+        "NO-GROUP" => 7,
+        "GCMP" | "GCMP-128" => 8,
+        "GCMP-256" => 9,
+        "CCMP-256" => 10,
+        "BIP-GMAC-128" => 11,
+        "BIP-GMAC-256" => 12,
+        "BIP-CMAC-256" => 13,
+        _ => {
+            let (oui, kind) = parse_selector(name)?;
+            return Some(Cipher::from_selector(oui, kind));
+        }
+    };
+    Some(Cipher::from_selector(IEEE_OUI, kind))
+}
+
+/// AKM names as printed by iw. Several contain spaces ("IEEE 802.1X"), so
+/// the list is split on known names rather than on whitespace.
+fn parse_akms(list: &str) -> Vec<Akm> {
+    const NAMES: &[(&str, u8)] = &[
+        ("FT/IEEE 802.1X/SHA-384", 13),
+        ("IEEE 802.1X/SUITE-B-192", 12),
+        ("IEEE 802.1X/SUITE-B", 11),
+        ("IEEE 802.1X/SHA-256", 5),
+        ("FT/IEEE 802.1X", 3),
+        ("IEEE 802.1X", 1),
+        ("FT/FILS/SHA-256", 16),
+        ("FT/FILS/SHA-384", 17),
+        ("FILS/SHA-256", 14),
+        ("FILS/SHA-384", 15),
+        ("FT/PSK/SHA-384", 19),
+        ("PSK/SHA-384", 20),
+        ("PSK/SHA-256", 6),
+        ("FT/PSK", 4),
+        ("PSK", 2),
+        ("TDLS/TPK", 7),
+        ("FT/SAE-EXT-KEY", 25),
+        ("SAE-EXT-KEY", 24),
+        ("FT/SAE", 9),
+        ("SAE", 8),
+        ("OWE", 18),
+    ];
+
+    let mut akms = Vec::new();
+    let mut rest = list.trim();
+    'outer: while !rest.is_empty() {
+        for (name, kind) in NAMES {
+            if let Some(after) = rest.strip_prefix(name)
+                && (after.is_empty() || after.starts_with(' '))
+            {
+                akms.push(Akm::from_selector(IEEE_OUI, *kind));
+                rest = after.trim_start();
+                continue 'outer;
+            }
+        }
+        let (token, after) = rest.split_once(' ').unwrap_or((rest, ""));
+        if let Some((oui, kind)) = parse_selector(token) {
+            akms.push(Akm::from_selector(oui, kind));
+        }
+        rest = after.trim_start();
+    }
+    akms
+}
+
+/// `00-0f-ac:24`
+fn parse_selector(text: &str) -> Option<([u8; 3], u8)> {
+    let (oui, kind) = text.split_once(':')?;
+    let mut octets = oui.split('-').map(|octet| u8::from_str_radix(octet, 16));
+    let oui = [octets.next()?.ok()?, octets.next()?.ok()?, octets.next()?.ok()?];
+    if octets.next().is_some() {
+        return None;
+    }
+    Some((oui, kind.parse().ok()?))
+}
+
+/// `5.5*` is 5.5 Mbit/s in the basic rate set.
+fn parse_rate(text: &str) -> Option<Rate> {
+    let basic = text.ends_with('*');
+    let mbps = text.trim_end_matches('*').parse::<f32>().ok()?;
+    Some(Rate { half_mbps: (mbps * 2.0).round() as u8, basic })
+}
+
+/// `DE\tEnvironment: Indoor/Outdoor`
+fn parse_country(value: &str) -> Country {
+    let (code, environment) = match value.split_once("Environment:") {
+        Some((code, environment)) => (code.trim(), Some(environment.trim().to_string())),
+        None => (value.trim(), None),
+    };
+    Country { code: code.to_string(), environment }
+}
+
+/// Extracts the hex value from `... (0x1411)`.
+fn parenthesized_hex(value: &str) -> Option<u16> {
+    let start = value.rfind("(0x")?;
+    let hex = value[start + 3..].trim_end_matches(')');
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// iw prints unprintable SSID bytes, backslashes and leading or trailing
+/// spaces as `\xNN`.
+fn unescape_ssid(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut ssid = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'\\'
+            && bytes.get(index + 1) == Some(&b'x')
+            && let Some(value) = text.get(index + 2..index + 4).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            ssid.push(value);
+            index += 4;
+            continue;
+        }
+        ssid.push(bytes[index]);
+        index += 1;
+    }
+    ssid
+}
+
+/// Parses `iw dev` output into the wireless interfaces it lists.
+pub fn parse_dev(output: &str) -> Vec<Interface> {
+    let mut interfaces: Vec<Interface> = Vec::new();
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Interface ") {
+            interfaces.push(Interface { name: name.to_string(), mac_address: None });
+        } else if let (Some(mac), Some(interface)) = (line.strip_prefix("addr "), interfaces.last_mut()) {
+            interface.mac_address = mac.parse().ok();
+        }
+    }
+    interfaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCAN: &str = include_str!("../../../fixtures/iw/scan.txt");
+    const DEV: &str = include_str!("../../../fixtures/iw/dev.txt");
+
+    #[test]
+    fn splits_fixture_into_bss_blocks() {
+        let bsses = parse_scan(SCAN);
+        assert_eq!(bsses.len(), 6);
+        assert!(bsses[0].associated);
+        assert!(!bsses[1].associated);
+    }
+
+    #[test]
+    fn parses_basic_attributes() {
+        let bss = &parse_scan(SCAN)[0];
+        assert_eq!(bss.bssid, Some("aa:bb:cc:dd:ee:01".parse().unwrap()));
+        assert_eq!(bss.frequency_mhz, Some(2437));
+        assert_eq!(bss.signal_dbm, Some(-48.0));
+        assert_eq!(bss.last_seen_ms, Some(120));
+        assert_eq!(bss.capability, Some(0x1411));
+        assert_eq!(bss.ssid.as_deref(), Some(&b"HomeNet"[..]));
+        assert_eq!(bss.elements.ds_channel, Some(6));
+        assert_eq!(bss.elements.supported_rates.len(), 12);
+        assert_eq!(bss.elements.supported_rates[2], Rate { half_mbps: 11, basic: true });
+        assert_eq!(bss.elements.country, Some(Country {
+            code: String::from("DE"),
+            environment: Some(String::from("Indoor/Outdoor")),
+        }));
+    }
+
+    #[test]
+    fn parses_rsn_section() {
+        let rsn = parse_scan(SCAN)[1].elements.rsn.clone().unwrap();
+        assert_eq!(rsn.version, 1);
+        assert_eq!(rsn.group_cipher, Some(Cipher::Ccmp128));
+        assert_eq!(rsn.pairwise_ciphers, [Cipher::Ccmp128]);
+        assert_eq!(rsn.akm_suites, [Akm::Psk, Akm::Sae]);
+        assert_eq!(rsn.capabilities, Some(0x008c));
+    }
+
+    #[test]
+    fn parses_akm_names_with_spaces_and_raw_selectors() {
+        let bsses = parse_scan(SCAN);
+        assert_eq!(bsses[4].elements.rsn.as_ref().unwrap().akm_suites, [Akm::SuiteB192]);
+        assert_eq!(bsses[4].elements.rsn.as_ref().unwrap().group_cipher, Some(Cipher::Gcmp256));
+        assert_eq!(bsses[5].elements.rsn.as_ref().unwrap().akm_suites, [Akm::Sae, Akm::SaeExtKey]);
+        assert_eq!(parse_akms("IEEE 802.1X FT/IEEE 802.1X"), [Akm::Ieee8021x, Akm::FtIeee8021x]);
+    }
+
+    #[test]
+    fn parses_wpa_section() {
+        let bss = &parse_scan(SCAN)[3];
+        let wpa = bss.elements.wpa.as_ref().unwrap();
+        assert_eq!(wpa.group_cipher, Some(Cipher::Tkip));
+        assert_eq!(wpa.pairwise_ciphers, [Cipher::Tkip, Cipher::Ccmp128]);
+        assert_eq!(wpa.akm_suites, [Akm::Psk]);
+        assert!(bss.elements.rsn.is_none());
+    }
+
+    #[test]
+    fn parses_ht_and_vht_operation() {
+        let bsses = parse_scan(SCAN);
+        assert_eq!(bsses[0].elements.ht_operation, Some(HtOperation {
+            primary_channel: 6,
+            secondary_channel_offset: SecondaryChannelOffset::None,
+            any_channel_width: false,
+        }));
+        assert_eq!(bsses[1].elements.ht_operation.unwrap().secondary_channel_offset, SecondaryChannelOffset::Above);
+        assert_eq!(bsses[1].elements.vht_operation, Some(VhtOperation {
+            channel_width: 1,
+            center_segment0: 42,
+            center_segment1: 0,
+        }));
+    }
+
+    #[test]
+    fn parses_he_operation_with_six_ghz_information() {
+        let he = parse_scan(SCAN)[5].elements.he_operation.unwrap();
+        assert_eq!(he.bss_color, Some(17));
+        assert_eq!(he.six_ghz, Some(SixGhzOperation {
+            primary_channel: 37,
+            channel_width: 3,
+            center_segment0: 39,
+            center_segment1: 47,
+        }));
+    }
+
+    #[test]
+    fn unescapes_ssid_bytes() {
+        assert_eq!(parse_scan(SCAN)[3].ssid.as_deref(), Some(&[0u8; 6][..]));
+        assert_eq!(unescape_ssid(r"\x20Lobby\x5c"), b" Lobby\\");
+        assert_eq!(unescape_ssid(r"odd\x"), b"odd\\x");
+    }
+
+    #[test]
+    fn keeps_only_the_ssid_of_beacon_elements() {
+        let output = "BSS 00:11:22:33:44:55(on wlan0)
+\tInformation elements from Probe Response frame:
+\tSSID: Backstage
+\tSupported rates: 6.0* 12.0 24.0
+\tInformation elements from Beacon frame:
+\tSSID: \\x00\\x00\\x00\\x00\\x00\\x00\\x00\\x00\\x00
+\tSupported rates: 6.0* 12.0 24.0
+";
+        let bss = &parse_scan(output)[0];
+        assert_eq!(bss.ssid.as_deref(), Some(&b"Backstage"[..]));
+        assert_eq!(bss.beacon_ssid.as_deref(), Some(&[0u8; 9][..]));
+        assert_eq!(bss.elements.supported_rates.len(), 3);
+    }
+
+    #[test]
+    fn incremental_parser_reports_blocks_as_they_end() {
+        let mut parser = ScanParser::default();
+        assert_eq!(parser.push_line("BSS 00:11:22:33:44:55(on wlan0)"), None);
+        assert_eq!(parser.push_line("\tSSID: A"), None);
+        let first = parser.push_line("BSS 00:11:22:33:44:56(on wlan0)").unwrap();
+        assert_eq!(first.ssid.as_deref(), Some(&b"A"[..]));
+        assert!(parser.finish().is_some());
+        assert!(parser.finish().is_none());
+    }
+
+    #[test]
+    fn parses_dev_listing() {
+        let interfaces = parse_dev(DEV);
+        assert_eq!(interfaces, [
+            Interface { name: String::from("wlan1"), mac_address: Some("02:00:00:00:01:00".parse().unwrap()) },
+            Interface { name: String::from("wlan0"), mac_address: Some("02:00:00:00:00:00".parse().unwrap()) },
+        ]);
+    }
+}
@@ -0,0 +1,684 @@
+//! Backend that talks to iwd over the system D-Bus.
+//!
+//! iwd groups BSSes into networks: each `Network` object below a station is
+//! one SSID and security type, with its BSSes listed in `ExtendedServiceSet`
+//! (iwd 2.11 and later). Signal strength is only reported per network, by
+//! `Station.GetOrderedNetworks`, so every BSS of a network gets the same one.
+//! Hidden BSSes belong to no network and are listed apart, by
+//! `Station.GetHiddenAccessPoints`.
+
+use std::time::Duration;
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::BoxStream;
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{ dbus_proxy, CacheProperties, Connection };
+
+use super::dbus::{ self, connect, Bus };
+use super::{
+    scan_stream,
+    Capabilities,
+    Emitter,
+    Interface,
+    RescanPolicy,
+    ScanBackend,
+    ScanError,
+    ScanEvent,
+    ScanRequest,
+    DEFAULT_TIMEOUT,
+};
+use crate::network::{ dbm_to_percent, HiddenSsid, MacAddress, Network, Security, Ssid };
+
+pub const NAME: &str = "iwd";
+
+const SERVICE: &str = "net.connman.iwd";
+const DEVICE_INTERFACE: &str = "net.connman.iwd.Device";
+const STATION_INTERFACE: &str = "net.connman.iwd.Station";
+const SCANNING_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Bound for checking whether iwd is running at all.
+const DETECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[dbus_proxy(interface = "net.connman.iwd.Device", default_service = "net.connman.iwd", gen_blocking = false)]
+trait Device {
+    #[dbus_proxy(property)]
+    fn name(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn address(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn powered(&self) -> zbus::Result<bool>;
+}
+
+#[dbus_proxy(interface = "net.connman.iwd.Station", default_service = "net.connman.iwd", gen_blocking = false)]
+trait Station {
+    fn scan(&self) -> zbus::Result<()>;
+
+    /// Networks in iwd's order of preference, with their signal strength in
+    /// hundredths of a dBm.
+    fn get_ordered_networks(&self) -> zbus::Result<Vec<(OwnedObjectPath, i16)>>;
+
+    /// Hidden BSSes with their address, signal strength in hundredths of a
+    /// dBm and network type.
+    fn get_hidden_access_points(&self) -> zbus::Result<Vec<(String, i16, String)>>;
+
+    #[dbus_proxy(property)]
+    fn scanning(&self) -> zbus::Result<bool>;
+}
+
+#[dbus_proxy(interface = "net.connman.iwd.Network", default_service = "net.connman.iwd", gen_blocking = false)]
+trait Network {
+    #[dbus_proxy(property)]
+    fn name(&self) -> zbus::Result<String>;
+
+    /// `open`, `wep`, `psk` or `8021x`.
+    #[dbus_proxy(property, name = "Type")]
+    fn network_type(&self) -> zbus::Result<String>;
+
+    /// Only present when a profile for the network is saved.
+    #[dbus_proxy(property)]
+    fn known_network(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[dbus_proxy(property)]
+    fn extended_service_set(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[dbus_proxy(interface = "net.connman.iwd.KnownNetwork", default_service = "net.connman.iwd", gen_blocking = false)]
+trait KnownNetwork {
+    #[dbus_proxy(property)]
+    fn name(&self) -> zbus::Result<String>;
+}
+
+#[dbus_proxy(interface = "net.connman.iwd.BasicServiceSet", default_service = "net.connman.iwd", gen_blocking = false)]
+trait BasicServiceSet {
+    #[dbus_proxy(property)]
+    fn address(&self) -> zbus::Result<String>;
+}
+
+#[derive(Debug, Clone)]
+pub struct IwdBackend {
+    bus: Bus,
+}
+
+impl Default for IwdBackend {
+    fn default() -> Self {
+        Self { bus: Bus::System }
+    }
+}
+
+impl IwdBackend {
+    #[cfg(test)]
+    pub(super) fn with_address(address: &str) -> Self {
+        Self { bus: Bus::Address(address.to_string()) }
+    }
+
+    /// Whether iwd is running and manages `interface`, or any interface when
+    /// `None`. Every failure counts as no.
+    pub async fn manages(&self, interface: Option<&str>) -> bool {
+        let check = async {
+            let connection = connect(&self.bus).await.ok()?;
+            let devices = devices(&connection).await.ok()?;
+            Some(devices.iter().any(|device| interface.is_none_or(|interface| device.name == interface)))
+        };
+        matches!(tokio::time::timeout(DETECT_TIMEOUT, check).await, Ok(Some(true)))
+    }
+
+    /// Whether the networks iwd knows on `interface`, or on any interface
+    /// when `None`, list their BSSes, as they do from iwd 2.11 on. Without
+    /// any network to check, nothing will fail to list either.
+    pub async fn lists_bsses(&self, interface: Option<&str>) -> bool {
+        let check = async {
+            let connection = connect(&self.bus).await.ok()?;
+            for station in stations(&connection, interface).await.ok()? {
+                let Some((path, _)) = station.get_ordered_networks().await.ok()?.into_iter().next() else {
+                    continue;
+                };
+                let network = NetworkProxy::builder(&connection).path(path).ok()?.build().await.ok()?;
+                return Some(network.extended_service_set().await.is_ok());
+            }
+            Some(true)
+        };
+        matches!(tokio::time::timeout(DETECT_TIMEOUT, check).await, Ok(Some(true)))
+    }
+}
+
+/// Maps a D-Bus failure to a [`ScanError`].
+fn dbus_error(err: zbus::Error) -> ScanError {
+    match dbus::error_name(&err).as_deref() {
+        Some("org.freedesktop.DBus.Error.ServiceUnknown") => ScanError::ToolMissing(String::from(NAME)),
+        Some("org.freedesktop.DBus.Error.AccessDenied" | "net.connman.iwd.PermissionDenied") => {
+            ScanError::PermissionDenied(err.to_string())
+        }
+        Some("net.connman.iwd.NotAvailable") => ScanError::RadioDisabled,
+        _ => ScanError::Failed(err.to_string()),
+    }
+}
+
+fn security_from_type(network_type: &str) -> Security {
+    match network_type {
+        "open" => Security::Open,
+        "wep" => Security::Wep,
+        // iwd does not tell WPA2-Personal and WPA3-Personal apart here.
+        "psk" => Security::Wpa2Psk,
+        "8021x" => Security::Enterprise,
+        _ => Security::Unknown,
+    }
+}
+
+/// A device iwd manages, and whether it is currently in station mode.
+struct ManagedDevice {
+    path: OwnedObjectPath,
+    name: String,
+    station: bool,
+}
+
+async fn devices(connection: &Connection) -> Result<Vec<ManagedDevice>, ScanError> {
+    let manager = ObjectManagerProxy::builder(connection)
+        .destination(SERVICE)
+        .map_err(dbus_error)?
+        .path("/")
+        .map_err(dbus_error)?
+        .build().await
+        .map_err(dbus_error)?;
+
+    let mut devices = Vec::new();
+    for (path, interfaces) in manager.get_managed_objects().await.map_err(|err| dbus_error(err.into()))? {
+        let Some(properties) = interfaces.get(DEVICE_INTERFACE) else {
+            continue;
+        };
+        let Some(Ok(name)) = properties.get("Name").cloned().map(String::try_from) else {
+            continue;
+        };
+        let station = interfaces.contains_key(STATION_INTERFACE);
+        devices.push(ManagedDevice { path, name, station });
+    }
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+/// Stations to scan on, optionally restricted to the one named `interface`.
+/// iwd drops the station interface of devices that are powered off.
+async fn stations<'a>(
+    connection: &'a Connection,
+    interface: Option<&str>
+) -> Result<Vec<StationProxy<'a>>, ScanError> {
+    let devices: Vec<_> = devices(connection)
+        .await?
+        .into_iter()
+        .filter(|device| interface.is_none_or(|interface| device.name == interface))
+        .collect();
+
+    let mut stations = Vec::new();
+    let mut powered_off = false;
+    for device in devices {
+        if !device.station {
+            let proxy = DeviceProxy::builder(connection).path(device.path).map_err(dbus_error)?.build().await.map_err(dbus_error)?;
+            powered_off |= !proxy.powered().await.map_err(dbus_error)?;
+            continue;
+        }
+        let station = StationProxy::builder(connection)
+            .path(device.path)
+            .map_err(dbus_error)?
+            .cache_properties(CacheProperties::No)
+            .build().await
+            .map_err(dbus_error)?;
+        stations.push(station);
+    }
+
+    if stations.is_empty() {
+        return Err(if powered_off {
+            ScanError::RadioDisabled
+        } else {
+            ScanError::NoInterface(interface.map(str::to_string))
+        });
+    }
+    Ok(stations)
+}
+
+/// Triggers a scan if `policy` calls for it and waits for it to finish.
+/// iwd scans periodically on its own and keeps no scan timestamp, so `Auto`
+/// only scans when the station knows no networks at all. Returns whether a
+/// scan completed.
+async fn refresh(station: &StationProxy<'_>, policy: RescanPolicy) -> Result<bool, ScanError> {
+    let stale = match policy {
+        RescanPolicy::Cached => false,
+        RescanPolicy::Auto => station.get_ordered_networks().await.map_err(dbus_error)?.is_empty(),
+        RescanPolicy::Force => true,
+    };
+    if !stale {
+        return Ok(false);
+    }
+
+    if let Err(err) = station.scan().await {
+        // A scan iwd is already running will do just as well.
+        if !matches!(dbus::error_name(&err).as_deref(), Some("net.connman.iwd.Busy" | "net.connman.iwd.InProgress")) {
+            return Err(dbus_error(err));
+        }
+    }
+    while station.scanning().await.map_err(dbus_error)? {
+        tokio::time::sleep(SCANNING_POLL_INTERVAL).await;
+    }
+    Ok(true)
+}
+
+fn network_with_signal(ssid: Ssid, bssid: MacAddress, signal: i16) -> Network {
+    let signal_dbm = i32::from(signal) / 100;
+    let mut network = Network::new(ssid, bssid, dbm_to_percent(signal_dbm));
+    network.signal_dbm = Some(signal_dbm);
+    network
+}
+
+/// Hidden BSSes, which iwd reports with an address but no SSID. iwd does
+/// not tell how their beacons hide it.
+async fn hidden_networks(station: &StationProxy<'_>) -> Result<Vec<Network>, ScanError> {
+    let access_points = station.get_hidden_access_points().await.map_err(dbus_error)?;
+    Ok(access_points
+        .into_iter()
+        .filter_map(|(address, signal, network_type)| {
+            let mut network = network_with_signal(Ssid::from(""), address.parse().ok()?, signal);
+            network.hidden_ssid = Some(HiddenSsid::Unknown);
+            network.security = security_from_type(&network_type);
+            Some(network)
+        })
+        .collect())
+}
+
+/// The records for one iwd network, one per BSS. iwd before 2.11 does not
+/// expose BSSes, and without their addresses the records could not be told
+/// apart, so the scan fails instead.
+async fn networks(connection: &Connection, path: OwnedObjectPath, signal: i16) -> Result<Vec<Network>, ScanError> {
+    let proxy = NetworkProxy::builder(connection).path(path).map_err(dbus_error)?.build().await.map_err(dbus_error)?;
+    let bss_paths = proxy
+        .extended_service_set().await
+        .map_err(|_| ScanError::Failed(String::from("iwd 2.11 or later is needed to list BSSes")))?;
+    let ssid = Ssid::from(proxy.name().await.map_err(dbus_error)?.as_str());
+    let security = security_from_type(&proxy.network_type().await.map_err(dbus_error)?);
+    let mut known = false;
+    if let Ok(path) = proxy.known_network().await {
+        let known_network = KnownNetworkProxy::builder(connection).path(path).map_err(dbus_error)?.build().await.map_err(dbus_error)?;
+        known = known_network.name().await.is_ok();
+    }
+
+    let mut networks = Vec::new();
+    for bss_path in bss_paths {
+        let bss = BasicServiceSetProxy::builder(connection).path(bss_path).map_err(dbus_error)?.build().await.map_err(dbus_error)?;
+        if let Ok(bssid) = bss.address().await.map_err(dbus_error)?.parse() {
+            let mut network = network_with_signal(ssid.clone(), bssid, signal);
+            network.security = security;
+            network.known = known;
+            networks.push(network);
+        }
+    }
+    Ok(networks)
+}
+
+async fn scan(bus: Bus, request: ScanRequest, emit: Emitter) -> Result<(), ScanError> {
+    let connection = connect(&bus).await?;
+    let mut all_refreshed = true;
+    for station in stations(&connection, request.interface.as_deref()).await? {
+        all_refreshed &= refresh(&station, request.rescan).await?;
+        for (path, signal) in station.get_ordered_networks().await.map_err(dbus_error)? {
+            networks(&connection, path, signal).await?.into_iter().for_each(|network| emit.network(network));
+        }
+        hidden_networks(&station).await?.into_iter().for_each(|network| emit.network(network));
+    }
+    if all_refreshed {
+        emit.cache_age(Duration::ZERO);
+    }
+    Ok(())
+}
+
+impl ScanBackend for IwdBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            trigger_scan: true,
+            signal_dbm: true,
+            security: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        let bus = self.bus.clone();
+        let list = async move {
+            let connection = connect(&bus).await?;
+            let mut interfaces = Vec::new();
+            for device in devices(&connection).await? {
+                let proxy = DeviceProxy::builder(&connection).path(device.path).map_err(dbus_error)?.build().await.map_err(dbus_error)?;
+                let mac_address = proxy.address().await.ok().and_then(|mac| mac.parse().ok());
+                interfaces.push(Interface { name: device.name, mac_address });
+            }
+            Ok(interfaces)
+        };
+        async move {
+            tokio::time::timeout(DEFAULT_TIMEOUT, list).await.unwrap_or(Err(ScanError::Timeout(DEFAULT_TIMEOUT)))
+        }.boxed()
+    }
+
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        let bus = self.bus.clone();
+        scan_stream(move |emit| async move {
+            let timeout = request.timeout;
+            tokio::time::timeout(timeout, scan(bus, request, emit)).await.unwrap_or(Err(ScanError::Timeout(timeout)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::auto::AutoBackend;
+    use crate::backend::nl80211::{ self, Nl80211Backend };
+    use crate::backend::nmcli::{ self, NmcliBackend };
+    use crate::backend::test_bus::TestBus;
+    use iced::futures::StreamExt;
+    use zbus::{ dbus_interface, ConnectionBuilder };
+
+    const WLAN0: &str = "/net/connman/iwd/0/4";
+    const WLAN1: &str = "/net/connman/iwd/1/5";
+    const HOME: &str = "/net/connman/iwd/0/4/486f6d654e6574_psk";
+    const CAFE: &str = "/net/connman/iwd/0/4/43616665_open";
+    const KNOWN_HOME: &str = "/net/connman/iwd/486f6d654e6574_psk";
+
+    struct MockDevice {
+        name: &'static str,
+        address: &'static str,
+        powered: bool,
+    }
+
+    #[dbus_interface(name = "net.connman.iwd.Device")]
+    impl MockDevice {
+        #[dbus_interface(property)]
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        #[dbus_interface(property)]
+        fn address(&self) -> String {
+            self.address.to_string()
+        }
+
+        #[dbus_interface(property)]
+        fn powered(&self) -> bool {
+            self.powered
+        }
+    }
+
+    struct MockStation {
+        networks: Vec<(OwnedObjectPath, i16)>,
+        scans: u32,
+    }
+
+    #[dbus_interface(name = "net.connman.iwd.Station")]
+    impl MockStation {
+        fn scan(&mut self) {
+            self.scans += 1;
+        }
+
+        fn get_ordered_networks(&self) -> Vec<(OwnedObjectPath, i16)> {
+            self.networks.clone()
+        }
+
+        fn get_hidden_access_points(&self) -> Vec<(String, i16, String)> {
+            vec![(String::from("AA:BB:CC:DD:EE:04"), -8000, String::from("psk"))]
+        }
+
+        #[dbus_interface(property)]
+        fn scanning(&self) -> bool {
+            false
+        }
+    }
+
+    struct MockNetwork {
+        name: &'static str,
+        network_type: &'static str,
+        known_network: Option<&'static str>,
+        /// `None` mimics iwd before 2.11, which lacks the property.
+        bss: Option<Vec<OwnedObjectPath>>,
+    }
+
+    #[dbus_interface(name = "net.connman.iwd.Network")]
+    impl MockNetwork {
+        #[dbus_interface(property)]
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        #[dbus_interface(property, name = "Type")]
+        fn network_type(&self) -> String {
+            self.network_type.to_string()
+        }
+
+        #[dbus_interface(property)]
+        fn known_network(&self) -> zbus::fdo::Result<OwnedObjectPath> {
+            self.known_network
+                .map(path)
+                .ok_or_else(|| zbus::fdo::Error::UnknownProperty(String::from("KnownNetwork")))
+        }
+
+        #[dbus_interface(property)]
+        fn extended_service_set(&self) -> zbus::fdo::Result<Vec<OwnedObjectPath>> {
+            self.bss
+                .clone()
+                .ok_or_else(|| zbus::fdo::Error::UnknownProperty(String::from("ExtendedServiceSet")))
+        }
+    }
+
+    struct MockKnownNetwork;
+
+    #[dbus_interface(name = "net.connman.iwd.KnownNetwork")]
+    impl MockKnownNetwork {
+        #[dbus_interface(property)]
+        fn name(&self) -> String {
+            String::from("HomeNet")
+        }
+    }
+
+    struct MockBss {
+        address: &'static str,
+    }
+
+    #[dbus_interface(name = "net.connman.iwd.BasicServiceSet")]
+    impl MockBss {
+        #[dbus_interface(property)]
+        fn address(&self) -> String {
+            self.address.to_string()
+        }
+    }
+
+    fn path(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(path).unwrap()
+    }
+
+    /// Publishes iwd with a station `wlan0` that sees a saved WPA network
+    /// with two BSSes, an open one and a hidden BSS, and a powered-off `wlan1`.
+    /// Without `extended_service_set`, networks do not list their BSSes.
+    async fn serve_mock(bus: &TestBus, extended_service_set: bool) -> Connection {
+        let home_bss = ["/net/connman/iwd/0/4/486f6d654e6574_psk/aabbccddee01", "/net/connman/iwd/0/4/486f6d654e6574_psk/aabbccddee02"];
+        let cafe_bss = "/net/connman/iwd/0/4/43616665_open/aabbccddee03";
+
+        let connection = ConnectionBuilder::address(bus.address()).unwrap()
+            .name(SERVICE).unwrap()
+            .serve_at(WLAN0, MockDevice { name: "wlan0", address: "02:00:00:00:00:01", powered: true }).unwrap()
+            .serve_at(WLAN0, MockStation { networks: vec![(path(HOME), -4800), (path(CAFE), -7200)], scans: 0 }).unwrap()
+            .serve_at(WLAN1, MockDevice { name: "wlan1", address: "02:00:00:00:00:02", powered: false }).unwrap()
+            .serve_at(HOME, MockNetwork {
+                name: "HomeNet",
+                network_type: "psk",
+                known_network: Some(KNOWN_HOME),
+                bss: extended_service_set.then(|| home_bss.iter().map(|bss| path(bss)).collect()),
+            }).unwrap()
+            .serve_at(home_bss[0], MockBss { address: "AA:BB:CC:DD:EE:01" }).unwrap()
+            .serve_at(home_bss[1], MockBss { address: "AA:BB:CC:DD:EE:02" }).unwrap()
+            .serve_at(CAFE, MockNetwork { name: "Cafe", network_type: "open", known_network: None, bss: extended_service_set.then(|| vec![path(cafe_bss)]) }).unwrap()
+            .serve_at(cafe_bss, MockBss { address: "AA:BB:CC:DD:EE:03" }).unwrap()
+            .serve_at(KNOWN_HOME, MockKnownNetwork).unwrap()
+            .build().await
+            .unwrap();
+        connection.object_server().at("/", zbus::fdo::ObjectManager).await.unwrap();
+        connection
+    }
+
+    async fn scans(service: &Connection) -> u32 {
+        let station = service.object_server().interface::<_, MockStation>(WLAN0).await.unwrap();
+        station.get().await.scans
+    }
+
+    fn wlan0(rescan: RescanPolicy) -> ScanRequest {
+        ScanRequest { interface: Some(String::from("wlan0")), rescan, ..ScanRequest::default() }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scans_mock_iwd() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let service = serve_mock(&bus, true).await;
+        let backend = IwdBackend::with_address(bus.address());
+
+        let events: Vec<_> = backend.scan(wlan0(RescanPolicy::Force)).collect().await;
+        let networks: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ScanEvent::Network(network) => Some(network.as_ref()),
+                _ => None,
+            })
+            .collect();
+
+        let bssids: Vec<_> = networks
+            .iter()
+            .map(|network| network.bssid.to_string())
+            .collect();
+        assert_eq!(bssids, ["AA:BB:CC:DD:EE:01", "AA:BB:CC:DD:EE:02", "AA:BB:CC:DD:EE:03", "AA:BB:CC:DD:EE:04"]);
+        assert_eq!(networks[0].ssid.display(), "HomeNet");
+        assert_eq!(networks[0].signal_dbm, Some(-48));
+        assert_eq!(networks[0].signal_percent, 87);
+        assert_eq!(networks[0].security, Security::Wpa2Psk);
+        assert!(networks[1].known);
+        assert_eq!(networks[2].security, Security::Open);
+        assert!(!networks[2].known);
+        assert_eq!(networks[3].hidden_ssid, Some(HiddenSsid::Unknown));
+        assert_eq!(networks[3].signal_dbm, Some(-80));
+        assert_eq!(networks[3].security, Security::Wpa2Psk);
+        assert!(events.contains(&ScanEvent::CacheAge(Duration::ZERO)));
+        assert_eq!(events.last(), Some(&ScanEvent::Finished(Ok(()))));
+        assert_eq!(scans(&service).await, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn auto_policy_uses_known_networks() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let service = serve_mock(&bus, true).await;
+        let backend = IwdBackend::with_address(bus.address());
+
+        let events: Vec<_> = backend.scan(wlan0(RescanPolicy::Auto)).collect().await;
+        assert_eq!(events.len(), 5);
+        assert_eq!(scans(&service).await, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn requires_bss_objects() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, false).await;
+        let backend = IwdBackend::with_address(bus.address());
+
+        let events: Vec<_> = backend.scan(wlan0(RescanPolicy::Cached)).collect().await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::Failed(String::from("iwd 2.11 or later is needed to list BSSes"))))]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reports_powered_off_and_missing_devices() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, true).await;
+        let backend = IwdBackend::with_address(bus.address());
+
+        let request = ScanRequest { interface: Some(String::from("wlan1")), ..ScanRequest::default() };
+        let events: Vec<_> = backend.scan(request).collect().await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::RadioDisabled))]);
+
+        let request = ScanRequest { interface: Some(String::from("wlan9")), ..ScanRequest::default() };
+        let events: Vec<_> = backend.scan(request).collect().await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::NoInterface(Some(String::from("wlan9")))))]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lists_managed_interfaces() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, true).await;
+        let backend = IwdBackend::with_address(bus.address());
+
+        let names: Vec<_> = backend
+            .interfaces().await
+            .unwrap()
+            .into_iter()
+            .map(|interface| interface.name)
+            .collect();
+        assert_eq!(names, ["wlan0", "wlan1"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn auto_backend_picks_iwd_only_for_its_interfaces() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let iwd = IwdBackend::with_address(bus.address());
+        // Any installed program stands in for nmcli.
+        let backend = AutoBackend::with_backends(iwd.clone(), NmcliBackend::with_program("sh"));
+        assert_eq!(backend.select(None).await.name(), nmcli::NAME);
+
+        let _service = serve_mock(&bus, true).await;
+        assert_eq!(backend.select(None).await.name(), NAME);
+        assert_eq!(backend.select(Some("wlan0")).await.name(), NAME);
+        assert_eq!(backend.select(Some("wlan9")).await.name(), nmcli::NAME);
+
+        let backend = AutoBackend::with_backends(iwd, NmcliBackend::with_program("definitely-not-a-real-tool"));
+        assert_eq!(backend.select(Some("wlan9")).await.name(), nl80211::NAME);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn auto_backend_reports_capabilities_of_its_pick() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, true).await;
+        let iwd = IwdBackend::with_address(bus.address());
+        let nmcli = NmcliBackend::with_program("sh");
+        let backend = AutoBackend::with_backends(iwd.clone(), nmcli.clone());
+        assert_eq!(backend.capabilities(), nmcli.capabilities());
+
+        backend.select(Some("wlan0")).await;
+        assert_eq!(backend.capabilities(), iwd.capabilities());
+        // Clones, like the one a scan runs on, share what was picked.
+        assert_eq!(backend.clone().capabilities(), iwd.capabilities());
+        backend.select(Some("wlan9")).await;
+        assert_eq!(backend.capabilities(), nmcli.capabilities());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn auto_backend_skips_iwd_that_cannot_list_bsses() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, false).await;
+        let iwd = IwdBackend::with_address(bus.address());
+        assert!(iwd.manages(Some("wlan0")).await);
+        assert!(!iwd.lists_bsses(Some("wlan0")).await);
+
+        let backend = AutoBackend::with_backends(iwd, NmcliBackend::with_program("sh"));
+        assert_eq!(backend.select(Some("wlan0")).await.name(), nl80211::NAME);
+        assert_eq!(backend.capabilities(), Nl80211Backend.capabilities());
+    }
+}
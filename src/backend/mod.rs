@@ -0,0 +1,266 @@
+//! Scan backends.
+//!
+//! Each backend knows how to enumerate wireless interfaces and turn a scan
+//! into [`Network`] records. The GUI only talks to the [`ScanBackend`] trait,
+//! so backends can be swapped at runtime and tests can inject a fake one.
+
+pub mod auto;
+mod dbus;
+mod error;
+pub mod iw;
+pub mod iwd;
+pub mod nl80211;
+pub mod nm_dbus;
+pub mod nmcli;
+mod process;
+pub mod replay;
+pub mod wpa_supplicant;
+#[cfg(test)]
+mod test_bus;
+
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::futures::channel::mpsc;
+use iced::futures::future::BoxFuture;
+use iced::futures::stream::{ self, BoxStream, StreamExt };
+
+pub use error::ScanError;
+
+use crate::network::{ MacAddress, Network };
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a backend is able to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities {
+    /// The backend can ask the driver for a fresh scan instead of returning
+    /// cached results.
+    pub trigger_scan: bool,
+    /// Signal strength is reported in dBm rather than only as a percentage.
+    pub signal_dbm: bool,
+    /// Frequency and channel are reported.
+    pub frequency: bool,
+    /// Security information (flags or RSN/WPA elements) is reported.
+    pub security: bool,
+    /// Raw information elements are available.
+    pub information_elements: bool,
+}
+
+/// A wireless interface a backend can scan on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub name: String,
+    pub mac_address: Option<MacAddress>,
+}
+
+/// Whether a scan should ask the driver for fresh results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RescanPolicy {
+    /// Only read the results the system already has, however old.
+    Cached,
+    /// Let the backend decide; most rescan when their results are older
+    /// than [`AUTO_RESCAN_MAX_AGE`].
+    #[default]
+    Auto,
+    /// Always trigger a fresh scan first. Slower, and may need privileges.
+    Force,
+}
+
+/// With [`RescanPolicy::Auto`], results older than this are stale. This is
+/// NetworkManager's own threshold, which nmcli applies.
+pub(super) const AUTO_RESCAN_MAX_AGE: Duration = Duration::from_secs(30);
+
+impl RescanPolicy {
+    pub const ALL: [RescanPolicy; 3] = [RescanPolicy::Cached, RescanPolicy::Auto, RescanPolicy::Force];
+}
+
+impl fmt::Display for RescanPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RescanPolicy::Cached => "Cached",
+            RescanPolicy::Auto => "Auto",
+            RescanPolicy::Force => "Force rescan",
+        })
+    }
+}
+
+impl FromStr for RescanPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cached" => Ok(RescanPolicy::Cached),
+            "auto" => Ok(RescanPolicy::Auto),
+            "force" => Ok(RescanPolicy::Force),
+            _ => Err(format!("unknown rescan policy {:?}, expected cached, auto or force", s)),
+        }
+    }
+}
+
+/// Parameters for a single scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanRequest {
+    /// Interface to scan on, or every interface the backend manages when
+    /// `None`.
+    pub interface: Option<String>,
+    /// Upper bound for the whole scan, including any external tool.
+    pub timeout: Duration,
+    pub rescan: RescanPolicy,
+}
+
+impl Default for ScanRequest {
+    fn default() -> Self {
+        Self { interface: None, timeout: DEFAULT_TIMEOUT, rescan: RescanPolicy::default() }
+    }
+}
+
+/// Progress reported by a running scan.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanEvent {
+    /// A network parsed so far. Networks are streamed as soon as they are
+    /// known, before the scan has finished. Boxed, since networks carry
+    /// their decoded information elements.
+    Network(Box<Network>),
+    /// How old the results are at most, as far as the backend can tell.
+    /// Not sent when the age is unknown.
+    CacheAge(Duration),
+    /// The scan ended. No further events follow.
+    Finished(Result<(), ScanError>),
+}
+
+pub trait ScanBackend: Send + Sync + std::fmt::Debug {
+    /// Short identifier, also used to select the backend on the command line.
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>>;
+
+    /// Starts a scan. The scan runs while the stream is polled and is
+    /// abandoned, including any child process, when the stream is dropped.
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent>;
+}
+
+/// Sends networks from a running scan to its stream.
+#[derive(Debug, Clone)]
+pub struct Emitter(mpsc::UnboundedSender<ScanEvent>);
+
+impl Emitter {
+    pub fn network(&self, network: Network) {
+        let _ = self.0.unbounded_send(ScanEvent::Network(Box::new(network)));
+    }
+
+    pub fn cache_age(&self, age: Duration) {
+        let _ = self.0.unbounded_send(ScanEvent::CacheAge(age));
+    }
+}
+
+/// Turns an async scan routine into a scan stream. Networks passed to the
+/// [`Emitter`] are yielded as they arrive, followed by a single
+/// [`ScanEvent::Finished`] carrying the routine's result.
+pub fn scan_stream<F, Fut>(scan: F) -> BoxStream<'static, ScanEvent>
+    where F: FnOnce(Emitter) -> Fut, Fut: Future<Output = Result<(), ScanError>> + Send + 'static
+{
+    let (sender, receiver) = mpsc::unbounded();
+    let finished = sender.clone();
+    let routine = scan(Emitter(sender));
+    let driver = stream::once(async move {
+        let result = routine.await;
+        let _ = finished.unbounded_send(ScanEvent::Finished(result));
+    }).filter_map(|()| async { None });
+
+    stream::select(receiver, driver).boxed()
+}
+
+/// Names of all backends compiled into this build, default first.
+pub const BACKEND_NAMES: &[&str] = &[
+    auto::NAME,
+    nmcli::NAME,
+    nm_dbus::NAME,
+    iwd::NAME,
+    iw::NAME,
+    nl80211::NAME,
+    wpa_supplicant::NAME,
+];
+
+/// Looks up a backend by its [`ScanBackend::name`].
+pub fn by_name(name: &str) -> Option<Arc<dyn ScanBackend>> {
+    match name {
+        auto::NAME => Some(Arc::new(auto::AutoBackend::default())),
+        nmcli::NAME => Some(Arc::new(nmcli::NmcliBackend::default())),
+        nm_dbus::NAME => Some(Arc::new(nm_dbus::NmDbusBackend::default())),
+        iwd::NAME => Some(Arc::new(iwd::IwdBackend::default())),
+        iw::NAME => Some(Arc::new(iw::IwBackend::default())),
+        nl80211::NAME => Some(Arc::new(nl80211::Nl80211Backend)),
+        wpa_supplicant::NAME => Some(Arc::new(wpa_supplicant::WpaSupplicantBackend::default())),
+        _ => None,
+    }
+}
+
+pub fn default_backend() -> Arc<dyn ScanBackend> {
+    Arc::new(auto::AutoBackend::default())
+}
+
+/// Formats a binary reply for display, 16 bytes per line after the offset.
+fn hex_dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let hex: Vec<_> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("{:04x}  {}", index * 16, hex.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_listed_backend_resolves() {
+        for name in BACKEND_NAMES {
+            let backend = by_name(name).unwrap();
+            assert_eq!(backend.name(), *name);
+        }
+        assert!(by_name("does-not-exist").is_none());
+    }
+
+    #[tokio::test]
+    async fn scan_stream_yields_networks_then_finished() {
+        let network = Network::new("Test".into(), "00:11:22:33:44:55".parse().unwrap(), 50);
+        let emitted = network.clone();
+        let events: Vec<_> = scan_stream(move |emit| async move {
+            emit.network(emitted);
+            Err(ScanError::RadioDisabled)
+        }).collect().await;
+
+        assert_eq!(events, [ScanEvent::Network(Box::new(network)), ScanEvent::Finished(Err(ScanError::RadioDisabled))]);
+    }
+
+    #[test]
+    fn parses_rescan_policy() {
+        assert_eq!("force".parse(), Ok(RescanPolicy::Force));
+        assert!("yes".parse::<RescanPolicy>().is_err());
+    }
+
+    #[test]
+    fn dumps_bytes_as_hex() {
+        let bytes: Vec<u8> = (0..18).collect();
+        assert_eq!(
+            hex_dump(&bytes),
+            "0000  00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n0010  10 11"
+        );
+        assert_eq!(hex_dump(&[]), "");
+    }
+
+    #[test]
+    fn default_backend_is_listed_first() {
+        assert_eq!(default_backend().name(), BACKEND_NAMES[0]);
+    }
+}
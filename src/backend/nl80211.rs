@@ -0,0 +1,494 @@
+//! Backend that scans through nl80211 over generic netlink, the interface
+//! iw itself uses, so no external tool is needed.
+//!
+//! Reading results (`NL80211_CMD_GET_SCAN`) is allowed for everyone;
+//! triggering a scan (`NL80211_CMD_TRIGGER_SCAN`) needs `CAP_NET_ADMIN`.
+//! Completion is announced on the `scan` multicast group.
+
+mod netlink;
+
+use std::io;
+use std::time::{ Duration, SystemTime };
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::BoxStream;
+
+use netlink::{ Attributes, DecodeError, Family, Request, Socket, NLM_F_ACK, NLM_F_DUMP };
+use super::{
+    scan_stream,
+    Capabilities,
+    Emitter,
+    Interface,
+    RescanPolicy,
+    ScanBackend,
+    ScanError,
+    ScanEvent,
+    ScanRequest,
+    AUTO_RESCAN_MAX_AGE,
+    DEFAULT_TIMEOUT,
+};
+use crate::ie::{ Elements, CAPABILITY_IBSS };
+use crate::network::{ dbm_to_percent, MacAddress, Mode, Network, Security, Ssid };
+
+pub const NAME: &str = "nl80211";
+
+const FAMILY_NAME: &str = "nl80211";
+const SCAN_GROUP: &str = "scan";
+
+const CMD_GET_INTERFACE: u8 = 5;
+const CMD_GET_SCAN: u8 = 32;
+const CMD_TRIGGER_SCAN: u8 = 33;
+const CMD_NEW_SCAN_RESULTS: u8 = 34;
+const CMD_SCAN_ABORTED: u8 = 35;
+
+const ATTR_IFINDEX: u16 = 3;
+const ATTR_IFNAME: u16 = 4;
+const ATTR_MAC: u16 = 6;
+const ATTR_BSS: u16 = 47;
+
+const BSS_BSSID: u16 = 1;
+const BSS_FREQUENCY: u16 = 2;
+const BSS_CAPABILITY: u16 = 5;
+const BSS_INFORMATION_ELEMENTS: u16 = 6;
+const BSS_SIGNAL_MBM: u16 = 7;
+const BSS_STATUS: u16 = 9;
+const BSS_SEEN_MS_AGO: u16 = 10;
+const BSS_BEACON_IES: u16 = 11;
+
+/// `NL80211_BSS_STATUS_ASSOCIATED`.
+const BSS_STATUS_ASSOCIATED: u32 = 1;
+
+#[derive(Debug, Clone, Default)]
+pub struct Nl80211Backend;
+
+/// A wireless interface as reported by `NL80211_CMD_GET_INTERFACE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WirelessInterface {
+    index: u32,
+    name: String,
+    mac_address: Option<MacAddress>,
+}
+
+/// The `NL80211_ATTR_BSS` of one scan result.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Bss {
+    bssid: Option<MacAddress>,
+    frequency_mhz: Option<u32>,
+    capability: Option<u16>,
+    /// Elements of the last probe response, or of the last beacon when no
+    /// probe response was received.
+    elements: Vec<u8>,
+    /// The SSID in the last beacon, when the kernel kept beacon elements.
+    beacon_ssid: Option<Vec<u8>>,
+    signal_mbm: Option<i32>,
+    associated: bool,
+    seen_ms_ago: Option<u32>,
+    /// The attribute payload, kept for display.
+    payload: Vec<u8>,
+}
+
+fn decode_interface(attributes: Attributes<'_>) -> Result<Option<WirelessInterface>, DecodeError> {
+    let (mut index, mut name, mut mac_address) = (None, None, None);
+    for attribute in attributes {
+        let attribute = attribute?;
+        match attribute.kind {
+            ATTR_IFINDEX => index = Some(attribute.u32()?),
+            ATTR_IFNAME => name = Some(attribute.string()?.to_string()),
+            ATTR_MAC => mac_address = mac(attribute.payload),
+            _ => {}
+        }
+    }
+    // P2P and NAN devices have no netdev, hence no index or name.
+    Ok(index.zip(name).map(|(index, name)| WirelessInterface { index, name, mac_address }))
+}
+
+fn decode_bss(attributes: Attributes<'_>) -> Result<Option<Bss>, DecodeError> {
+    for attribute in attributes {
+        let attribute = attribute?;
+        if attribute.kind != ATTR_BSS {
+            continue;
+        }
+        let mut bss = Bss { payload: attribute.payload.to_vec(), ..Bss::default() };
+        let mut beacon_elements = None;
+        for field in attribute.nested() {
+            let field = field?;
+            match field.kind {
+                BSS_BSSID => bss.bssid = mac(field.payload),
+                BSS_FREQUENCY => bss.frequency_mhz = Some(field.u32()?),
+                BSS_CAPABILITY => bss.capability = Some(field.u16()?),
+                BSS_INFORMATION_ELEMENTS => bss.elements = field.payload.to_vec(),
+                BSS_BEACON_IES => beacon_elements = Some(field.payload.to_vec()),
+                BSS_SIGNAL_MBM => bss.signal_mbm = Some(field.i32()?),
+                BSS_STATUS => bss.associated = field.u32()? == BSS_STATUS_ASSOCIATED,
+                BSS_SEEN_MS_AGO => bss.seen_ms_ago = Some(field.u32()?),
+                _ => {}
+            }
+        }
+        if let Some(beacon_elements) = beacon_elements {
+            bss.beacon_ssid = Elements::decode(&beacon_elements).ssid;
+            if bss.elements.is_empty() {
+                bss.elements = beacon_elements;
+            }
+        }
+        return Ok(Some(bss));
+    }
+    Ok(None)
+}
+
+fn mac(payload: &[u8]) -> Option<MacAddress> {
+    payload.try_into().ok().map(MacAddress::new)
+}
+
+/// Turns a scan result into a network. Results without a BSSID or SSID
+/// element are dropped. A hidden network is decloaked when the last probe
+/// response carried its SSID while the beacons hide it.
+fn network_from_bss(bss: Bss) -> Option<Network> {
+    let bssid = bss.bssid?;
+    let elements = Elements::decode(&bss.elements);
+    let ssid = Ssid::from_bytes(elements.ssid.clone()?);
+
+    let signal_dbm = bss.signal_mbm.map(|mbm| mbm / 100);
+    let beacon_ssid = bss.beacon_ssid.map_or_else(|| ssid.clone(), Ssid::from_bytes);
+    let mut network = Network::new(beacon_ssid, bssid, signal_dbm.map_or(0, dbm_to_percent));
+    network.reveal_ssid(ssid);
+    network.signal_dbm = signal_dbm;
+    network.frequency_mhz = bss.frequency_mhz;
+    network.security = Security::from_elements(bss.capability, &elements);
+    if bss.capability.is_some_and(|capability| capability & CAPABILITY_IBSS != 0) {
+        network.mode = Mode::AdHoc;
+    }
+    network.capability_info = bss.capability;
+    network.elements = elements;
+    network.derive_channel();
+    network.raw = Some(super::hex_dump(&bss.payload));
+    if let Some(seen_ms_ago) = bss.seen_ms_ago {
+        let now = SystemTime::now();
+        network.last_seen = now.checked_sub(Duration::from_millis(u64::from(seen_ms_ago))).unwrap_or(now);
+    }
+    Some(network)
+}
+
+/// Maps a negated errno from the kernel to a [`ScanError`].
+fn errno_error(errno: i32, interface: Option<&str>) -> ScanError {
+    match -errno {
+        libc::EPERM | libc::EACCES => {
+            ScanError::PermissionDenied(String::from("triggering a scan needs CAP_NET_ADMIN"))
+        }
+        libc::ENODEV => ScanError::NoInterface(interface.map(str::to_string)),
+        libc::ENETDOWN | libc::ERFKILL => ScanError::RadioDisabled,
+        errno => ScanError::Failed(format!("{}: {}", NAME, io::Error::from_raw_os_error(errno))),
+    }
+}
+
+fn netlink_error(err: netlink::Error, interface: Option<&str>) -> ScanError {
+    match err {
+        netlink::Error::Errno(errno) => errno_error(errno, interface),
+        netlink::Error::Io(err) => socket_error(err),
+        netlink::Error::Decode(err) => ScanError::Parse(err.to_string()),
+    }
+}
+
+fn socket_error(err: io::Error) -> ScanError {
+    match err.raw_os_error() {
+        Some(libc::EAFNOSUPPORT | libc::EPROTONOSUPPORT) => ScanError::ToolMissing(String::from("netlink")),
+        _ => ScanError::Failed(format!("{}: {}", NAME, err)),
+    }
+}
+
+/// Looks up the nl80211 family, which is missing when no wireless driver
+/// is loaded.
+async fn resolve_family(socket: &mut Socket) -> Result<Family, ScanError> {
+    let request = Request::new(netlink::GENL_ID_CTRL, 0, netlink::CTRL_CMD_GETFAMILY)
+        .string_attribute(netlink::CTRL_ATTR_FAMILY_NAME, FAMILY_NAME);
+    let payloads = match socket.request(request).await {
+        Err(netlink::Error::Errno(errno)) if -errno == libc::ENOENT => {
+            return Err(ScanError::ToolMissing(String::from(FAMILY_NAME)));
+        }
+        result => result.map_err(|err| netlink_error(err, None))?,
+    };
+    let payload = payloads.first().ok_or_else(|| ScanError::Parse(String::from("empty family reply")))?;
+    netlink::decode_family(payload).map_err(|err| ScanError::Parse(err.to_string()))
+}
+
+async fn wireless_interfaces(socket: &mut Socket, family: &Family) -> Result<Vec<WirelessInterface>, ScanError> {
+    let payloads = socket
+        .request(Request::new(family.id, NLM_F_DUMP, CMD_GET_INTERFACE)).await
+        .map_err(|err| netlink_error(err, None))?;
+    let mut interfaces = Vec::new();
+    for payload in payloads {
+        let (_, attributes) = generic(&payload)?;
+        interfaces.extend(decode_interface(attributes).map_err(|err| ScanError::Parse(err.to_string()))?);
+    }
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(interfaces)
+}
+
+fn generic(payload: &[u8]) -> Result<(u8, Attributes<'_>), ScanError> {
+    netlink::Message { kind: 0, flags: 0, seq: 0, payload }
+        .generic()
+        .map_err(|err| ScanError::Parse(err.to_string()))
+}
+
+async fn get_scan(socket: &mut Socket, family: &Family, interface: &WirelessInterface) -> Result<Vec<Bss>, ScanError> {
+    let request = Request::new(family.id, NLM_F_DUMP, CMD_GET_SCAN).u32_attribute(ATTR_IFINDEX, interface.index);
+    let payloads = socket.request(request).await.map_err(|err| netlink_error(err, Some(&interface.name)))?;
+    let mut results = Vec::new();
+    for payload in payloads {
+        let (_, attributes) = generic(&payload)?;
+        results.extend(decode_bss(attributes).map_err(|err| ScanError::Parse(err.to_string()))?);
+    }
+    Ok(results)
+}
+
+/// Triggers a scan on `interface` and waits for the kernel to announce its
+/// results.
+async fn trigger_scan(socket: &mut Socket, family: &Family, interface: &WirelessInterface) -> Result<(), ScanError> {
+    let group = family.multicast_groups
+        .iter()
+        .find(|(name, _)| name == SCAN_GROUP)
+        .map(|(_, group)| *group)
+        .ok_or_else(|| ScanError::Failed(String::from("nl80211 has no scan multicast group")))?;
+    // Subscribe before triggering so the completion cannot be missed.
+    let events = Socket::open().map_err(socket_error)?;
+    events.add_membership(group).map_err(socket_error)?;
+
+    let request = Request::new(family.id, NLM_F_ACK, CMD_TRIGGER_SCAN).u32_attribute(ATTR_IFINDEX, interface.index);
+    match socket.request(request).await {
+        Ok(_) => {}
+        // A scan is already running; its results will do.
+        Err(netlink::Error::Errno(errno)) if -errno == libc::EBUSY => {}
+        Err(err) => return Err(netlink_error(err, Some(&interface.name))),
+    }
+
+    loop {
+        let datagram = events.receive().await.map_err(socket_error)?;
+        let messages = netlink::messages(&datagram).map_err(|err| ScanError::Parse(err.to_string()))?;
+        for message in messages {
+            if message.kind != family.id {
+                continue;
+            }
+            let (command, attributes) = generic(message.payload)?;
+            let ours = attributes
+                .filter_map(Result::ok)
+                .any(|attribute| attribute.kind == ATTR_IFINDEX && attribute.u32() == Ok(interface.index));
+            match command {
+                CMD_NEW_SCAN_RESULTS if ours => return Ok(()),
+                CMD_SCAN_ABORTED if ours => return Err(ScanError::Failed(String::from("the scan was aborted"))),
+                _ => {}
+            }
+        }
+    }
+}
+
+async fn scan_interface(
+    socket: &mut Socket,
+    family: &Family,
+    interface: &WirelessInterface,
+    policy: RescanPolicy,
+    emit: &Emitter
+) -> Result<(), ScanError> {
+    let mut results = get_scan(socket, family, interface).await?;
+    let freshest = |results: &[Bss]| results.iter().filter_map(|bss| bss.seen_ms_ago).min();
+
+    let stale = freshest(&results).is_none_or(|age| Duration::from_millis(u64::from(age)) > AUTO_RESCAN_MAX_AGE);
+    let rescan = match policy {
+        RescanPolicy::Cached => false,
+        RescanPolicy::Auto => stale,
+        RescanPolicy::Force => true,
+    };
+    if rescan {
+        match trigger_scan(socket, family, interface).await {
+            Ok(()) => results = get_scan(socket, family, interface).await?,
+            // Without the capability, Auto makes do with the cached results.
+            Err(ScanError::PermissionDenied(_)) if policy == RescanPolicy::Auto => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    let age = freshest(&results);
+    results
+        .into_iter()
+        .filter_map(network_from_bss)
+        .for_each(|network| emit.network(network));
+    if let Some(age) = age {
+        emit.cache_age(Duration::from_millis(u64::from(age)));
+    }
+    Ok(())
+}
+
+async fn scan(request: ScanRequest, emit: Emitter) -> Result<(), ScanError> {
+    let mut socket = Socket::open().map_err(socket_error)?;
+    let family = resolve_family(&mut socket).await?;
+    let interfaces: Vec<_> = wireless_interfaces(&mut socket, &family)
+        .await?
+        .into_iter()
+        .filter(|interface| request.interface.as_ref().is_none_or(|name| *name == interface.name))
+        .collect();
+    if interfaces.is_empty() {
+        return Err(ScanError::NoInterface(request.interface));
+    }
+    for interface in &interfaces {
+        scan_interface(&mut socket, &family, interface, request.rescan, &emit).await?;
+    }
+    Ok(())
+}
+
+impl ScanBackend for Nl80211Backend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            trigger_scan: true,
+            signal_dbm: true,
+            frequency: true,
+            security: true,
+            information_elements: true,
+        }
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        let list = async {
+            let mut socket = Socket::open().map_err(socket_error)?;
+            let family = resolve_family(&mut socket).await?;
+            let interfaces = wireless_interfaces(&mut socket, &family).await?;
+            Ok(interfaces
+                .into_iter()
+                .map(|interface| Interface { name: interface.name, mac_address: interface.mac_address })
+                .collect())
+        };
+        async move {
+            tokio::time::timeout(DEFAULT_TIMEOUT, list).await.unwrap_or(Err(ScanError::Timeout(DEFAULT_TIMEOUT)))
+        }.boxed()
+    }
+
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        scan_stream(move |emit| async move {
+            let timeout = request.timeout;
+            tokio::time::timeout(timeout, scan(request, emit)).await.unwrap_or(Err(ScanError::Timeout(timeout)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::HiddenSsid;
+
+    const FAMILY: &str = include_str!("../../fixtures/nl80211/get_family.hex");
+    const INTERFACES: &str = include_str!("../../fixtures/nl80211/get_interface.hex");
+    const SCAN: &str = include_str!("../../fixtures/nl80211/get_scan.hex");
+    const TRIGGER_EPERM: &str = include_str!("../../fixtures/nl80211/trigger_eperm.hex");
+
+    /// Reads a captured datagram: hex bytes separated by whitespace, with
+    /// `#` comment lines. Captures are little-endian, like the hosts the
+    /// tests run on.
+    fn captured(hex: &str) -> Vec<u8> {
+        hex.lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(str::split_whitespace)
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect()
+    }
+
+    fn payloads(datagram: &[u8]) -> Vec<Vec<u8>> {
+        netlink::messages(datagram)
+            .unwrap()
+            .into_iter()
+            .filter(|message| message.kind >= netlink::GENL_ID_CTRL)
+            .map(|message| message.payload.to_vec())
+            .collect()
+    }
+
+    fn scan_results() -> Vec<Bss> {
+        payloads(&captured(SCAN))
+            .iter()
+            .filter_map(|payload| decode_bss(generic(payload).unwrap().1).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_family() {
+        let family = netlink::decode_family(&payloads(&captured(FAMILY))[0]).unwrap();
+        assert_eq!(family.id, 0x1c);
+        assert!(family.multicast_groups.contains(&(String::from("scan"), 6)));
+    }
+
+    #[test]
+    fn decodes_interfaces_and_skips_devices_without_netdev() {
+        let interfaces: Vec<_> = payloads(&captured(INTERFACES))
+            .iter()
+            .filter_map(|payload| decode_interface(generic(payload).unwrap().1).unwrap())
+            .collect();
+        assert_eq!(interfaces, [WirelessInterface {
+            index: 3,
+            name: String::from("wlan0"),
+            mac_address: Some("02:11:22:33:44:55".parse().unwrap()),
+        }]);
+    }
+
+    #[test]
+    fn decodes_bss_attributes() {
+        let results = scan_results();
+        assert_eq!(results.len(), 4);
+        let home = &results[0];
+        assert_eq!(home.bssid, Some("00:11:22:33:44:55".parse().unwrap()));
+        assert_eq!(home.frequency_mhz, Some(2437));
+        assert_eq!(home.capability, Some(0x0411));
+        assert_eq!(home.signal_mbm, Some(-4800));
+        assert_eq!(home.seen_ms_ago, Some(120));
+        assert!(home.associated);
+        assert!(!results[1].associated);
+        assert_eq!(&home.elements[..9], b"\x00\x07HomeNet");
+        // Beacon elements stand in when there was no probe response.
+        assert_eq!(&results[3].elements[2..16], b"Cafe Free WiFi");
+    }
+
+    #[test]
+    fn converts_results_to_networks() {
+        let networks: Vec<_> = scan_results().into_iter().filter_map(network_from_bss).collect();
+        let ssids: Vec<_> = networks
+            .iter()
+            .map(|network| network.ssid.display())
+            .collect();
+        assert_eq!(ssids, ["HomeNet", "Office Net", "", "Cafe Free WiFi"]);
+        assert!(networks[2].hidden_ssid.is_some());
+        assert_eq!(networks[0].signal_dbm, Some(-48));
+        assert_eq!(networks[0].signal_percent, 87);
+        assert_eq!(networks[0].channel, Some(6));
+        assert_eq!(networks[0].security, Security::Wpa2Psk);
+        assert_eq!(networks[1].security, Security::Wpa2Wpa3);
+        assert_eq!(networks[3].security, Security::Open);
+    }
+
+    #[test]
+    fn decloaks_hidden_networks_from_probe_responses() {
+        let network = network_from_bss(Bss {
+            bssid: Some("00:11:22:33:44:66".parse().unwrap()),
+            elements: b"\x00\x09Backstage".to_vec(),
+            beacon_ssid: Some(vec![0; 9]),
+            ..Bss::default()
+        }).unwrap();
+        assert_eq!(network.ssid.display(), "Backstage");
+        assert_eq!(network.hidden_ssid, Some(HiddenSsid::NullPadded { length: 9 }));
+        assert!(network.is_decloaked());
+    }
+
+    #[test]
+    fn reports_missing_privileges() {
+        let datagram = captured(TRIGGER_EPERM);
+        let messages = netlink::messages(&datagram).unwrap();
+        let errno = messages[0].error().unwrap();
+        assert!(matches!(errno_error(errno, Some("wlan0")), ScanError::PermissionDenied(_)));
+        assert_eq!(errno_error(-libc::ENODEV, Some("wlan9")), ScanError::NoInterface(Some(String::from("wlan9"))));
+        assert_eq!(errno_error(-libc::ERFKILL, None), ScanError::RadioDisabled);
+    }
+
+    #[test]
+    fn rejects_truncated_bss() {
+        let mut payload = payloads(&captured(SCAN)).remove(0);
+        payload.truncate(payload.len() - 3);
+        assert!(decode_bss(generic(&payload).unwrap().1).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+//! Just enough generic netlink to talk to nl80211: message framing,
+//! attributes and an async socket.
+//!
+//! Encoding and decoding work on plain byte slices so they can be tested
+//! against captured messages without a kernel.
+
+use std::io;
+use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd };
+
+use tokio::io::unix::AsyncFd;
+
+pub const NLMSG_ERROR: u16 = 2;
+pub const NLMSG_DONE: u16 = 3;
+
+pub const NLM_F_REQUEST: u16 = 0x1;
+pub const NLM_F_MULTI: u16 = 0x2;
+pub const NLM_F_ACK: u16 = 0x4;
+pub const NLM_F_DUMP: u16 = 0x300;
+
+/// Family id of the generic netlink controller.
+pub const GENL_ID_CTRL: u16 = 0x10;
+pub const CTRL_CMD_GETFAMILY: u8 = 3;
+pub const CTRL_ATTR_FAMILY_ID: u16 = 1;
+pub const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+pub const CTRL_ATTR_MCAST_GROUPS: u16 = 7;
+pub const CTRL_ATTR_MCAST_GRP_NAME: u16 = 1;
+pub const CTRL_ATTR_MCAST_GRP_ID: u16 = 2;
+
+const NLMSG_HEADER_LEN: usize = 16;
+const GENL_HEADER_LEN: usize = 4;
+const NLA_HEADER_LEN: usize = 4;
+/// Attribute type bits; the top two carry the nested and byte order flags.
+const NLA_TYPE_MASK: u16 = 0x3fff;
+const RECEIVE_BUFFER_SIZE: usize = 64 * 1024;
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Malformed netlink data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError(pub String);
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "malformed netlink message: {}", self.0)
+    }
+}
+
+/// Builds one generic netlink request.
+pub struct Request {
+    buffer: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(family: u16, flags: u16, command: u8) -> Self {
+        let mut buffer = vec![0; NLMSG_HEADER_LEN];
+        buffer[4..6].copy_from_slice(&family.to_ne_bytes());
+        buffer[6..8].copy_from_slice(&(NLM_F_REQUEST | flags).to_ne_bytes());
+        buffer.extend_from_slice(&[command, 1, 0, 0]);
+        Self { buffer }
+    }
+
+    pub fn attribute(mut self, kind: u16, payload: &[u8]) -> Self {
+        let len = NLA_HEADER_LEN + payload.len();
+        self.buffer.extend_from_slice(&(len as u16).to_ne_bytes());
+        self.buffer.extend_from_slice(&kind.to_ne_bytes());
+        self.buffer.extend_from_slice(payload);
+        self.buffer.resize(align(self.buffer.len()), 0);
+        self
+    }
+
+    pub fn u32_attribute(self, kind: u16, value: u32) -> Self {
+        self.attribute(kind, &value.to_ne_bytes())
+    }
+
+    /// Adds a NUL-terminated string attribute.
+    pub fn string_attribute(self, kind: u16, value: &str) -> Self {
+        let mut payload = value.as_bytes().to_vec();
+        payload.push(0);
+        self.attribute(kind, &payload)
+    }
+
+    /// Fills in length and sequence number and returns the wire bytes.
+    pub fn finish(mut self, seq: u32) -> Vec<u8> {
+        let len = self.buffer.len() as u32;
+        self.buffer[0..4].copy_from_slice(&len.to_ne_bytes());
+        self.buffer[8..12].copy_from_slice(&seq.to_ne_bytes());
+        self.buffer
+    }
+}
+
+/// One netlink message out of a datagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message<'a> {
+    pub kind: u16,
+    pub flags: u16,
+    pub seq: u32,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    /// For `NLMSG_ERROR`, the negated errno, 0 for an acknowledgement.
+    pub fn error(&self) -> Option<i32> {
+        if self.kind != NLMSG_ERROR {
+            return None;
+        }
+        Some(i32::from_ne_bytes(self.payload.get(..4)?.try_into().ok()?))
+    }
+
+    /// The generic netlink command and the attributes after the header.
+    pub fn generic(&self) -> Result<(u8, Attributes<'a>), DecodeError> {
+        if self.payload.len() < GENL_HEADER_LEN {
+            return Err(DecodeError(String::from("generic netlink header truncated")));
+        }
+        Ok((self.payload[0], Attributes::new(&self.payload[GENL_HEADER_LEN..])))
+    }
+}
+
+/// Splits a received datagram into its messages.
+pub fn messages(datagram: &[u8]) -> Result<Vec<Message<'_>>, DecodeError> {
+    let mut messages = Vec::new();
+    let mut rest = datagram;
+    while !rest.is_empty() {
+        if rest.len() < NLMSG_HEADER_LEN {
+            return Err(DecodeError(format!("{} trailing bytes", rest.len())));
+        }
+        let len = u32::from_ne_bytes(rest[0..4].try_into().unwrap()) as usize;
+        if len < NLMSG_HEADER_LEN || len > rest.len() {
+            return Err(DecodeError(format!("message length {} out of bounds", len)));
+        }
+        messages.push(Message {
+            kind: u16::from_ne_bytes(rest[4..6].try_into().unwrap()),
+            flags: u16::from_ne_bytes(rest[6..8].try_into().unwrap()),
+            seq: u32::from_ne_bytes(rest[8..12].try_into().unwrap()),
+            payload: &rest[NLMSG_HEADER_LEN..len],
+        });
+        rest = &rest[align(len).min(rest.len())..];
+    }
+    Ok(messages)
+}
+
+/// A netlink attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribute<'a> {
+    pub kind: u16,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Attribute<'a> {
+    fn fixed<const N: usize>(&self) -> Result<[u8; N], DecodeError> {
+        self.payload
+            .get(..N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| DecodeError(format!("attribute {} shorter than {} bytes", self.kind, N)))
+    }
+
+    pub fn u16(&self) -> Result<u16, DecodeError> {
+        self.fixed().map(u16::from_ne_bytes)
+    }
+
+    pub fn u32(&self) -> Result<u32, DecodeError> {
+        self.fixed().map(u32::from_ne_bytes)
+    }
+
+    pub fn i32(&self) -> Result<i32, DecodeError> {
+        self.fixed().map(i32::from_ne_bytes)
+    }
+
+    /// A string attribute, without its NUL terminator.
+    pub fn string(&self) -> Result<&'a str, DecodeError> {
+        let bytes = self.payload.split(|byte| *byte == 0).next().unwrap_or_default();
+        std::str::from_utf8(bytes).map_err(|_| DecodeError(format!("attribute {} is not UTF-8", self.kind)))
+    }
+
+    pub fn nested(&self) -> Attributes<'a> {
+        Attributes::new(self.payload)
+    }
+}
+
+/// Iterates over a run of attributes.
+#[derive(Debug, Clone)]
+pub struct Attributes<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Attributes<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { rest: bytes }
+    }
+}
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = Result<Attribute<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let header = self.rest.get(..NLA_HEADER_LEN);
+        let len = header.map_or(0, |header| u16::from_ne_bytes([header[0], header[1]]) as usize);
+        if header.is_none() || len < NLA_HEADER_LEN || len > self.rest.len() {
+            self.rest = &[];
+            return Some(Err(DecodeError(String::from("attribute length out of bounds"))));
+        }
+        let attribute = Attribute {
+            kind: u16::from_ne_bytes([self.rest[2], self.rest[3]]) & NLA_TYPE_MASK,
+            payload: &self.rest[NLA_HEADER_LEN..len],
+        };
+        self.rest = &self.rest[align(len).min(self.rest.len())..];
+        Some(Ok(attribute))
+    }
+}
+
+/// A failed netlink exchange.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The kernel answered with a negated errno.
+    Errno(i32),
+    Decode(DecodeError),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Self {
+        Error::Decode(err)
+    }
+}
+
+/// A non-blocking `NETLINK_GENERIC` socket.
+pub struct Socket {
+    fd: AsyncFd<OwnedFd>,
+    seq: u32,
+}
+
+impl Socket {
+    pub fn open() -> io::Result<Self> {
+        // SAFETY: plain socket(2) call; the result is checked before use.
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                libc::NETLINK_GENERIC
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `fd` is a freshly created descriptor nothing else owns.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        Ok(Self { fd: AsyncFd::new(fd)?, seq: 0 })
+    }
+
+    /// Subscribes to a multicast group, such as nl80211's `scan` group.
+    pub fn add_membership(&self, group: u32) -> io::Result<()> {
+        // SAFETY: `group` outlives the call and its size is passed along.
+        let result = unsafe {
+            libc::setsockopt(
+                self.fd.as_raw_fd(),
+                libc::SOL_NETLINK,
+                libc::NETLINK_ADD_MEMBERSHIP,
+                (&group as *const u32).cast(),
+                std::mem::size_of::<u32>() as libc::socklen_t
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Sends `request` to the kernel and returns its sequence number.
+    pub async fn send(&mut self, request: Request) -> io::Result<u32> {
+        self.seq = self.seq.wrapping_add(1);
+        let seq = self.seq;
+        let bytes = request.finish(seq);
+        loop {
+            let mut guard = self.fd.writable().await?;
+            // SAFETY: `bytes` is valid for its length; the kernel is the
+            // default destination of an unconnected netlink socket.
+            let result = guard.try_io(|fd| {
+                let sent = unsafe { libc::send(fd.as_raw_fd(), bytes.as_ptr().cast(), bytes.len(), 0) };
+                if sent < 0 { Err(io::Error::last_os_error()) } else { Ok(()) }
+            });
+            match result {
+                Ok(result) => return result.map(|()| seq),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Receives one datagram, which may hold several messages.
+    pub async fn receive(&self) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; RECEIVE_BUFFER_SIZE];
+        loop {
+            let mut guard = self.fd.readable().await?;
+            // SAFETY: `buffer` is valid for writes of its full length.
+            let result = guard.try_io(|fd| {
+                let received = unsafe { libc::recv(fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len(), 0) };
+                if received < 0 { Err(io::Error::last_os_error()) } else { Ok(received as usize) }
+            });
+            match result {
+                Ok(received) => {
+                    buffer.truncate(received?);
+                    return Ok(buffer);
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Sends `request` and collects the generic netlink payloads of all reply
+    /// messages, until the acknowledgement or the end of a dump.
+    pub async fn request(&mut self, request: Request) -> Result<Vec<Vec<u8>>, Error> {
+        let seq = self.send(request).await?;
+        let mut payloads = Vec::new();
+        loop {
+            let datagram = self.receive().await?;
+            for message in messages(&datagram)? {
+                if message.seq != seq {
+                    continue;
+                }
+                match message.kind {
+                    NLMSG_DONE => return Ok(payloads),
+                    NLMSG_ERROR => {
+                        return match message.error() {
+                            Some(0) => Ok(payloads),
+                            Some(errno) => Err(Error::Errno(errno)),
+                            None => Err(Error::Decode(DecodeError(String::from("error message truncated")))),
+                        };
+                    }
+                    _ => {
+                        payloads.push(message.payload.to_vec());
+                        if message.flags & NLM_F_MULTI == 0 {
+                            return Ok(payloads);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A resolved generic netlink family.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Family {
+    pub id: u16,
+    pub multicast_groups: Vec<(String, u32)>,
+}
+
+/// Decodes the controller's reply to `CTRL_CMD_GETFAMILY`.
+pub fn decode_family(payload: &[u8]) -> Result<Family, DecodeError> {
+    let message = Message { kind: GENL_ID_CTRL, flags: 0, seq: 0, payload };
+    let (_, attributes) = message.generic()?;
+    let mut id = None;
+    let mut multicast_groups = Vec::new();
+    for attribute in attributes {
+        let attribute = attribute?;
+        match attribute.kind {
+            CTRL_ATTR_FAMILY_ID => id = Some(attribute.u16()?),
+            CTRL_ATTR_MCAST_GROUPS => {
+                for group in attribute.nested() {
+                    let (mut name, mut group_id) = (None, None);
+                    for field in group?.nested() {
+                        let field = field?;
+                        match field.kind {
+                            CTRL_ATTR_MCAST_GRP_NAME => name = Some(field.string()?.to_string()),
+                            CTRL_ATTR_MCAST_GRP_ID => group_id = Some(field.u32()?),
+                            _ => {}
+                        }
+                    }
+                    if let (Some(name), Some(group_id)) = (name, group_id) {
+                        multicast_groups.push((name, group_id));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let id = id.ok_or_else(|| DecodeError(String::from("family id missing")))?;
+    Ok(Family { id, multicast_groups })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_round_trips_through_decoder() {
+        let bytes = Request::new(GENL_ID_CTRL, 0, CTRL_CMD_GETFAMILY)
+            .string_attribute(CTRL_ATTR_FAMILY_NAME, "nl80211")
+            .finish(7);
+        assert_eq!(bytes.len(), 16 + 4 + 12);
+
+        let messages = messages(&bytes).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].kind, GENL_ID_CTRL);
+        assert_eq!(messages[0].seq, 7);
+        let (command, attributes) = messages[0].generic().unwrap();
+        assert_eq!(command, CTRL_CMD_GETFAMILY);
+        let attributes: Vec<_> = attributes.collect::<Result<_, _>>().unwrap();
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0].string(), Ok("nl80211"));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let bytes = Request::new(GENL_ID_CTRL, 0, CTRL_CMD_GETFAMILY).u32_attribute(1, 5).finish(1);
+        assert!(messages(&bytes[..bytes.len() - 2]).is_err());
+
+        let attribute = [8, 0, 1, 0, 0, 0];
+        assert!(Attributes::new(&attribute).next().unwrap().is_err());
+    }
+}
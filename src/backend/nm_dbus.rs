@@ -0,0 +1,706 @@
+//! Backend that talks to NetworkManager directly over the system D-Bus.
+//!
+//! Produces the same records as the nmcli backend, but without spawning a
+//! process or parsing text, and with the extra access point properties
+//! (flags, max bitrate, last seen) that nmcli does not expose in terse mode.
+
+use std::time::{ Duration, SystemTime };
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::BoxStream;
+use zbus::zvariant::{ OwnedObjectPath, Value };
+use zbus::{ dbus_proxy, CacheProperties, Connection };
+
+use super::dbus::{ self, connect, Bus };
+use super::{
+    scan_stream,
+    Capabilities,
+    Emitter,
+    Interface,
+    RescanPolicy,
+    ScanBackend,
+    ScanError,
+    ScanEvent,
+    ScanRequest,
+    AUTO_RESCAN_MAX_AGE,
+    DEFAULT_TIMEOUT,
+};
+use crate::channel::ChannelWidth;
+use crate::ie::{ Akm, Cipher, Rsn, CAPABILITY_PRIVACY };
+use crate::network::{ HiddenSsid, MacAddress, Mode, Network, Security, Ssid };
+
+pub const NAME: &str = "networkmanager";
+
+/// `NM_DEVICE_TYPE_WIFI`.
+const DEVICE_TYPE_WIFI: u32 = 2;
+const LAST_SCAN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// `NM80211ApFlags`
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+/// `NM80211ApSecurityFlags`
+pub(super) const AP_SEC_PAIR_WEP40: u32 = 0x1;
+pub(super) const AP_SEC_PAIR_WEP104: u32 = 0x2;
+pub(super) const AP_SEC_PAIR_TKIP: u32 = 0x4;
+pub(super) const AP_SEC_PAIR_CCMP: u32 = 0x8;
+pub(super) const AP_SEC_GROUP_WEP40: u32 = 0x10;
+pub(super) const AP_SEC_GROUP_WEP104: u32 = 0x20;
+pub(super) const AP_SEC_GROUP_TKIP: u32 = 0x40;
+pub(super) const AP_SEC_GROUP_CCMP: u32 = 0x80;
+pub(super) const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+pub(super) const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+pub(super) const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+pub(super) const AP_SEC_KEY_MGMT_OWE: u32 = 0x800;
+/// Set on the open BSS of an OWE transition pair, which has no RSN element.
+pub(super) const AP_SEC_KEY_MGMT_OWE_TM: u32 = 0x1000;
+pub(super) const AP_SEC_KEY_MGMT_EAP_SUITE_B_192: u32 = 0x2000;
+
+const PAIRWISE_FLAGS: &[(u32, Cipher)] = &[
+    (AP_SEC_PAIR_WEP40, Cipher::Wep40),
+    (AP_SEC_PAIR_WEP104, Cipher::Wep104),
+    (AP_SEC_PAIR_TKIP, Cipher::Tkip),
+    (AP_SEC_PAIR_CCMP, Cipher::Ccmp128),
+];
+const GROUP_FLAGS: &[(u32, Cipher)] = &[
+    (AP_SEC_GROUP_WEP40, Cipher::Wep40),
+    (AP_SEC_GROUP_WEP104, Cipher::Wep104),
+    (AP_SEC_GROUP_TKIP, Cipher::Tkip),
+    (AP_SEC_GROUP_CCMP, Cipher::Ccmp128),
+];
+const KEY_MGMT_FLAGS: &[(u32, Akm)] = &[
+    (AP_SEC_KEY_MGMT_PSK, Akm::Psk),
+    (AP_SEC_KEY_MGMT_802_1X, Akm::Ieee8021x),
+    (AP_SEC_KEY_MGMT_SAE, Akm::Sae),
+    (AP_SEC_KEY_MGMT_OWE, Akm::Owe),
+    (AP_SEC_KEY_MGMT_EAP_SUITE_B_192, Akm::SuiteB192),
+];
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager",
+    gen_blocking = false
+)]
+trait NetworkManager {
+    fn get_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    #[dbus_proxy(property)]
+    fn wireless_enabled(&self) -> zbus::Result<bool>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager",
+    gen_blocking = false
+)]
+trait Device {
+    #[dbus_proxy(property)]
+    fn device_type(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn interface(&self) -> zbus::Result<String>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Wireless",
+    default_service = "org.freedesktop.NetworkManager",
+    gen_blocking = false
+)]
+trait Wireless {
+    fn request_scan(&self, options: std::collections::HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn get_all_access_points(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    #[dbus_proxy(property)]
+    fn hw_address(&self) -> zbus::Result<String>;
+
+    /// `CLOCK_BOOTTIME` milliseconds of the last completed scan, or -1.
+    #[dbus_proxy(property)]
+    fn last_scan(&self) -> zbus::Result<i64>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager",
+    gen_blocking = false
+)]
+trait AccessPoint {
+    #[dbus_proxy(property)]
+    fn flags(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn wpa_flags(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn rsn_flags(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn ssid(&self) -> zbus::Result<Vec<u8>>;
+
+    #[dbus_proxy(property)]
+    fn frequency(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn hw_address(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn mode(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn max_bitrate(&self) -> zbus::Result<u32>;
+
+    /// Channel width in MHz. Added in NetworkManager 1.46.
+    #[dbus_proxy(property)]
+    fn bandwidth(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn strength(&self) -> zbus::Result<u8>;
+
+    /// `CLOCK_BOOTTIME` seconds when the AP was last seen, or -1.
+    #[dbus_proxy(property)]
+    fn last_seen(&self) -> zbus::Result<i32>;
+}
+
+#[derive(Debug, Clone)]
+pub struct NmDbusBackend {
+    bus: Bus,
+}
+
+impl Default for NmDbusBackend {
+    fn default() -> Self {
+        Self { bus: Bus::System }
+    }
+}
+
+impl NmDbusBackend {
+    #[cfg(test)]
+    fn with_address(address: &str) -> Self {
+        Self { bus: Bus::Address(address.to_string()) }
+    }
+}
+
+/// Maps a D-Bus failure to a [`ScanError`].
+fn dbus_error(err: zbus::Error) -> ScanError {
+    match dbus::error_name(&err).as_deref() {
+        Some("org.freedesktop.DBus.Error.ServiceUnknown" | "org.freedesktop.DBus.Error.NameHasNoOwner") => {
+            ScanError::ToolMissing(String::from("NetworkManager"))
+        }
+        Some(
+            "org.freedesktop.DBus.Error.AccessDenied" |
+            "org.freedesktop.NetworkManager.PermissionDenied" |
+            "org.freedesktop.NetworkManager.Device.NotAllowed",
+        ) => ScanError::PermissionDenied(err.to_string()),
+        _ => ScanError::Failed(err.to_string()),
+    }
+}
+
+/// Current `CLOCK_BOOTTIME`, which NetworkManager timestamps are relative to.
+/// `/proc/uptime` counts suspended time as well, so it tracks the same clock.
+fn boot_time_now() -> Option<Duration> {
+    let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+    let seconds = uptime.split_whitespace().next()?.parse::<f64>().ok()?;
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Converts a `CLOCK_BOOTTIME` timestamp to wall-clock time.
+fn boot_time_to_system_time(timestamp: Duration, boot_time_now: Duration) -> SystemTime {
+    let now = SystemTime::now();
+    now.checked_sub(boot_time_now.saturating_sub(timestamp)).unwrap_or(now)
+}
+
+/// Rebuilds the suites of an RSN or WPA element from one of
+/// NetworkManager's security flag words, so they can be classified like
+/// decoded elements. NetworkManager does not report PMF, so the capabilities
+/// stay unknown.
+pub(super) fn suites_from_flags(flags: u32) -> Option<Rsn> {
+    let matching = |table: &[(u32, Cipher)]| -> Vec<Cipher> {
+        table
+            .iter()
+            .filter(|(flag, _)| flags & flag != 0)
+            .map(|(_, cipher)| *cipher)
+            .collect()
+    };
+    let pairwise_ciphers = matching(PAIRWISE_FLAGS);
+    let group_cipher = matching(GROUP_FLAGS).first().copied();
+    let akm_suites: Vec<_> = KEY_MGMT_FLAGS
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, akm)| *akm)
+        .collect();
+    if pairwise_ciphers.is_empty() && group_cipher.is_none() && akm_suites.is_empty() {
+        return None;
+    }
+    Some(Rsn {
+        version: 1,
+        group_cipher,
+        pairwise_ciphers,
+        akm_suites,
+        capabilities: None,
+        group_management_cipher: None,
+    })
+}
+
+fn mode_from_nm(mode: u32) -> Mode {
+    match mode {
+        1 => Mode::AdHoc,
+        4 => Mode::Mesh,
+        _ => Mode::Infrastructure,
+    }
+}
+
+/// Wireless devices, optionally restricted to the one named `interface`.
+async fn wireless_devices<'a>(
+    connection: &'a Connection,
+    interface: Option<&str>
+) -> Result<Vec<(String, WirelessProxy<'a>)>, ScanError> {
+    let manager = NetworkManagerProxy::new(connection).await.map_err(dbus_error)?;
+    let mut devices = Vec::new();
+
+    for path in manager.get_devices().await.map_err(dbus_error)? {
+        let device = DeviceProxy::builder(connection).path(path.clone()).map_err(dbus_error)?.build().await.map_err(dbus_error)?;
+        if device.device_type().await.map_err(dbus_error)? != DEVICE_TYPE_WIFI {
+            continue;
+        }
+        let name = device.interface().await.map_err(dbus_error)?;
+        if interface.is_some_and(|interface| interface != name) {
+            continue;
+        }
+        let wireless = WirelessProxy::builder(connection)
+            .path(path)
+            .map_err(dbus_error)?
+            .cache_properties(CacheProperties::No)
+            .build().await
+            .map_err(dbus_error)?;
+        devices.push((name, wireless));
+    }
+
+    if devices.is_empty() {
+        return Err(ScanError::NoInterface(interface.map(str::to_string)));
+    }
+    Ok(devices)
+}
+
+/// Requests a scan if `policy` calls for it and waits until NetworkManager
+/// reports it finished, then returns the device's `LastScan`.
+async fn refresh(wireless: &WirelessProxy<'_>, policy: RescanPolicy) -> Result<i64, ScanError> {
+    let last_scan = wireless.last_scan().await.map_err(dbus_error)?;
+    let stale = match (policy, boot_time_now()) {
+        (RescanPolicy::Cached, _) => false,
+        (RescanPolicy::Force, _) => true,
+        (RescanPolicy::Auto, Some(now)) => {
+            last_scan < 0 || now.saturating_sub(Duration::from_millis(last_scan as u64)) > AUTO_RESCAN_MAX_AGE
+        }
+        (RescanPolicy::Auto, None) => true,
+    };
+    if !stale {
+        return Ok(last_scan);
+    }
+
+    wireless.request_scan(Default::default()).await.map_err(dbus_error)?;
+    loop {
+        let current = wireless.last_scan().await.map_err(dbus_error)?;
+        if current > last_scan {
+            return Ok(current);
+        }
+        tokio::time::sleep(LAST_SCAN_POLL_INTERVAL).await;
+    }
+}
+
+async fn access_point(connection: &Connection, path: OwnedObjectPath) -> Result<Option<Network>, ScanError> {
+    let ap = AccessPointProxy::builder(connection).path(path).map_err(dbus_error)?.build().await.map_err(dbus_error)?;
+
+    // Empty for hidden networks; NetworkManager does not tell how the
+    // beacon hides it.
+    let ssid = ap.ssid().await.map_err(dbus_error)?;
+    let Ok(bssid) = ap.hw_address().await.map_err(dbus_error)?.parse::<MacAddress>() else {
+        return Ok(None);
+    };
+
+    let mut network = Network::new(Ssid::from_bytes(ssid), bssid, ap.strength().await.map_err(dbus_error)?);
+    if network.hidden_ssid.is_some() {
+        network.hidden_ssid = Some(HiddenSsid::Unknown);
+    }
+    let frequency = ap.frequency().await.map_err(dbus_error)?;
+    network.frequency_mhz = (frequency > 0).then_some(frequency);
+    let max_bitrate = ap.max_bitrate().await.map_err(dbus_error)?;
+    network.max_bitrate_kbps = (max_bitrate > 0).then_some(max_bitrate);
+    network.mode = mode_from_nm(ap.mode().await.map_err(dbus_error)?);
+    // Older NetworkManager versions lack the property altogether.
+    network.channel_width = ap.bandwidth().await.ok().and_then(ChannelWidth::from_mhz);
+    network.elements.wpa = suites_from_flags(ap.wpa_flags().await.map_err(dbus_error)?);
+    network.elements.rsn = suites_from_flags(ap.rsn_flags().await.map_err(dbus_error)?);
+    let privacy = if ap.flags().await.map_err(dbus_error)? & AP_FLAGS_PRIVACY != 0 { CAPABILITY_PRIVACY } else { 0 };
+    network.security = Security::from_elements(Some(privacy), &network.elements);
+    network.derive_channel();
+    let last_seen = ap.last_seen().await.map_err(dbus_error)?;
+    if let (Ok(last_seen), Some(now)) = (u64::try_from(last_seen), boot_time_now()) {
+        network.last_seen = boot_time_to_system_time(Duration::from_secs(last_seen), now);
+    }
+    Ok(Some(network))
+}
+
+async fn scan(bus: Bus, request: ScanRequest, emit: Emitter) -> Result<(), ScanError> {
+    let connection = connect(&bus).await?;
+    let manager = NetworkManagerProxy::new(&connection).await.map_err(dbus_error)?;
+    if !manager.wireless_enabled().await.map_err(dbus_error)? {
+        return Err(ScanError::RadioDisabled);
+    }
+
+    let mut oldest_scan = None;
+    for (_, wireless) in wireless_devices(&connection, request.interface.as_deref()).await? {
+        let last_scan = refresh(&wireless, request.rescan).await?;
+        oldest_scan = Some(oldest_scan.map_or(last_scan, |oldest: i64| oldest.min(last_scan)));
+
+        for path in wireless.get_all_access_points().await.map_err(dbus_error)? {
+            if let Some(network) = access_point(&connection, path).await? {
+                emit.network(network);
+            }
+        }
+    }
+
+    if let (Some(Ok(last_scan)), Some(now)) = (oldest_scan.map(u64::try_from), boot_time_now()) {
+        emit.cache_age(now.saturating_sub(Duration::from_millis(last_scan)));
+    }
+    Ok(())
+}
+
+impl ScanBackend for NmDbusBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            trigger_scan: true,
+            frequency: true,
+            security: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        let bus = self.bus.clone();
+        let list = async move {
+            let connection = connect(&bus).await?;
+            let mut interfaces = Vec::new();
+            for (name, wireless) in wireless_devices(&connection, None).await? {
+                let mac_address = wireless.hw_address().await.ok().and_then(|mac| mac.parse().ok());
+                interfaces.push(Interface { name, mac_address });
+            }
+            Ok(interfaces)
+        };
+        async move {
+            tokio::time::timeout(DEFAULT_TIMEOUT, list).await.unwrap_or(Err(ScanError::Timeout(DEFAULT_TIMEOUT)))
+        }.boxed()
+    }
+
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        let bus = self.bus.clone();
+        scan_stream(move |emit| async move {
+            let timeout = request.timeout;
+            tokio::time::timeout(timeout, scan(bus, request, emit)).await.unwrap_or(Err(ScanError::Timeout(timeout)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_bus::TestBus;
+    use crate::ie::Elements;
+    use iced::futures::StreamExt;
+    use zbus::{ dbus_interface, ConnectionBuilder };
+
+    struct MockManager {
+        devices: Vec<OwnedObjectPath>,
+        wireless_enabled: bool,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.NetworkManager")]
+    impl MockManager {
+        fn get_devices(&self) -> Vec<OwnedObjectPath> {
+            self.devices.clone()
+        }
+
+        #[dbus_interface(property)]
+        fn wireless_enabled(&self) -> bool {
+            self.wireless_enabled
+        }
+    }
+
+    struct MockDevice {
+        interface: String,
+        device_type: u32,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.NetworkManager.Device")]
+    impl MockDevice {
+        #[dbus_interface(property)]
+        fn device_type(&self) -> u32 {
+            self.device_type
+        }
+
+        #[dbus_interface(property)]
+        fn interface(&self) -> String {
+            self.interface.clone()
+        }
+    }
+
+    struct MockWireless {
+        access_points: Vec<OwnedObjectPath>,
+        last_scan: i64,
+        scan_requests: u32,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+    impl MockWireless {
+        fn request_scan(&mut self, _options: std::collections::HashMap<String, zbus::zvariant::OwnedValue>) {
+            self.scan_requests += 1;
+            self.last_scan += 1;
+        }
+
+        fn get_all_access_points(&self) -> Vec<OwnedObjectPath> {
+            self.access_points.clone()
+        }
+
+        #[dbus_interface(property)]
+        fn hw_address(&self) -> String {
+            String::from("02:00:00:00:00:01")
+        }
+
+        #[dbus_interface(property)]
+        fn last_scan(&self) -> i64 {
+            self.last_scan
+        }
+    }
+
+    struct MockAccessPoint {
+        ssid: &'static [u8],
+        hw_address: &'static str,
+        strength: u8,
+        frequency: u32,
+        bandwidth: u32,
+        flags: u32,
+        wpa_flags: u32,
+        rsn_flags: u32,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+    impl MockAccessPoint {
+        #[dbus_interface(property)]
+        fn flags(&self) -> u32 {
+            self.flags
+        }
+
+        #[dbus_interface(property)]
+        fn wpa_flags(&self) -> u32 {
+            self.wpa_flags
+        }
+
+        #[dbus_interface(property)]
+        fn rsn_flags(&self) -> u32 {
+            self.rsn_flags
+        }
+
+        #[dbus_interface(property)]
+        fn ssid(&self) -> Vec<u8> {
+            self.ssid.to_vec()
+        }
+
+        #[dbus_interface(property)]
+        fn frequency(&self) -> u32 {
+            self.frequency
+        }
+
+        #[dbus_interface(property)]
+        fn bandwidth(&self) -> u32 {
+            self.bandwidth
+        }
+
+        #[dbus_interface(property)]
+        fn hw_address(&self) -> String {
+            self.hw_address.to_string()
+        }
+
+        #[dbus_interface(property)]
+        fn mode(&self) -> u32 {
+            2
+        }
+
+        #[dbus_interface(property)]
+        fn max_bitrate(&self) -> u32 {
+            270_000
+        }
+
+        #[dbus_interface(property)]
+        fn strength(&self) -> u8 {
+            self.strength
+        }
+
+        #[dbus_interface(property)]
+        fn last_seen(&self) -> i32 {
+            -1
+        }
+    }
+
+    fn path(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(path).unwrap()
+    }
+
+    /// Publishes a NetworkManager with one wifi device (`wlan0`), one
+    /// ethernet device and three access points, one of them hidden, on `bus`.
+    async fn serve_mock(bus: &TestBus, wireless_enabled: bool) -> Connection {
+        let wifi = "/org/freedesktop/NetworkManager/Devices/1";
+        let ethernet = "/org/freedesktop/NetworkManager/Devices/2";
+        let home = "/org/freedesktop/NetworkManager/AccessPoint/1";
+        let office = "/org/freedesktop/NetworkManager/AccessPoint/2";
+        let hidden = "/org/freedesktop/NetworkManager/AccessPoint/3";
+
+        ConnectionBuilder::address(bus.address()).unwrap()
+            .name("org.freedesktop.NetworkManager").unwrap()
+            .serve_at("/org/freedesktop/NetworkManager", MockManager {
+                devices: vec![path(wifi), path(ethernet)],
+                wireless_enabled,
+            }).unwrap()
+            .serve_at(wifi, MockDevice { interface: String::from("wlan0"), device_type: DEVICE_TYPE_WIFI }).unwrap()
+            .serve_at(wifi, MockWireless { access_points: vec![path(home), path(office), path(hidden)], last_scan: 1, scan_requests: 0 }).unwrap()
+            .serve_at(ethernet, MockDevice { interface: String::from("eth0"), device_type: 1 }).unwrap()
+            .serve_at(home, MockAccessPoint {
+                ssid: b"HomeNet",
+                hw_address: "AA:BB:CC:DD:EE:01",
+                strength: 82,
+                frequency: 2437,
+                bandwidth: 20,
+                flags: AP_FLAGS_PRIVACY,
+                wpa_flags: 0,
+                rsn_flags: AP_SEC_PAIR_CCMP | AP_SEC_GROUP_CCMP | AP_SEC_KEY_MGMT_PSK,
+            }).unwrap()
+            .serve_at(office, MockAccessPoint {
+                ssid: b"Office",
+                hw_address: "AA:BB:CC:DD:EE:02",
+                strength: 47,
+                frequency: 5180,
+                bandwidth: 80,
+                flags: AP_FLAGS_PRIVACY,
+                wpa_flags: 0,
+                rsn_flags: AP_SEC_KEY_MGMT_802_1X,
+            }).unwrap()
+            .serve_at(hidden, MockAccessPoint {
+                ssid: b"",
+                hw_address: "AA:BB:CC:DD:EE:03",
+                strength: 30,
+                frequency: 2412,
+                bandwidth: 20,
+                flags: AP_FLAGS_PRIVACY,
+                wpa_flags: 0,
+                rsn_flags: AP_SEC_PAIR_CCMP | AP_SEC_GROUP_CCMP | AP_SEC_KEY_MGMT_PSK,
+            }).unwrap()
+            .build().await
+            .unwrap()
+    }
+
+    async fn collect(backend: &NmDbusBackend, request: ScanRequest) -> Vec<ScanEvent> {
+        backend.scan(request).collect().await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scans_mock_network_manager() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, true).await;
+        let backend = NmDbusBackend::with_address(bus.address());
+
+        let request = ScanRequest { rescan: RescanPolicy::Force, ..ScanRequest::default() };
+        let events = collect(&backend, request).await;
+        let networks: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ScanEvent::Network(network) => Some(network.as_ref()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(networks.len(), 3);
+        assert_eq!(networks[0].ssid.display(), "HomeNet");
+        assert_eq!(networks[0].bssid, "AA:BB:CC:DD:EE:01".parse().unwrap());
+        assert_eq!(networks[0].signal_percent, 82);
+        assert_eq!(networks[0].frequency_mhz, Some(2437));
+        assert_eq!(networks[0].channel_summary(), "2.4 GHz · ch 6 · 20 MHz");
+        assert_eq!(networks[0].center_frequency_mhz, Some(2437));
+        assert_eq!(networks[1].channel_summary(), "5 GHz · ch 36 · 80 MHz");
+        assert_eq!(networks[0].security, Security::Wpa2Psk);
+        assert_eq!(networks[0].security_summary(), "WPA2-PSK · CCMP");
+        assert_eq!(networks[0].max_bitrate_kbps, Some(270_000));
+        assert_eq!(networks[1].security, Security::Enterprise);
+        assert_eq!(networks[2].hidden_ssid, Some(HiddenSsid::Unknown));
+        assert_eq!(networks[2].ssid_label(), "<hidden>");
+        assert_eq!(events.last(), Some(&ScanEvent::Finished(Ok(()))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lists_only_wifi_interfaces() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, true).await;
+        let backend = NmDbusBackend::with_address(bus.address());
+
+        let interfaces = backend.interfaces().await.unwrap();
+        assert_eq!(interfaces, [Interface {
+            name: String::from("wlan0"),
+            mac_address: Some("02:00:00:00:00:01".parse().unwrap()),
+        }]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reports_missing_service_and_interface() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let backend = NmDbusBackend::with_address(bus.address());
+        let events = collect(&backend, ScanRequest::default()).await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::ToolMissing(String::from("NetworkManager"))))]);
+
+        let _service = serve_mock(&bus, true).await;
+        let request = ScanRequest { interface: Some(String::from("wlan9")), ..ScanRequest::default() };
+        let events = collect(&backend, request).await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::NoInterface(Some(String::from("wlan9")))))]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reports_disabled_radio() {
+        let Some(bus) = TestBus::start().await else {
+            return;
+        };
+        let _service = serve_mock(&bus, false).await;
+        let backend = NmDbusBackend::with_address(bus.address());
+        let events = collect(&backend, ScanRequest::default()).await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::RadioDisabled))]);
+    }
+
+    #[test]
+    fn classifies_security_flags() {
+        let classify = |flags: u32, wpa_flags: u32, rsn_flags: u32| {
+            let elements = Elements {
+                wpa: suites_from_flags(wpa_flags),
+                rsn: suites_from_flags(rsn_flags),
+                ..Elements::default()
+            };
+            let privacy = if flags & AP_FLAGS_PRIVACY != 0 { CAPABILITY_PRIVACY } else { 0 };
+            Security::from_elements(Some(privacy), &elements)
+        };
+        assert_eq!(classify(0, 0, 0), Security::Open);
+        assert_eq!(classify(0, 0, AP_SEC_KEY_MGMT_OWE_TM), Security::Open);
+        assert_eq!(classify(0, 0, AP_SEC_PAIR_CCMP | AP_SEC_KEY_MGMT_OWE), Security::Owe);
+        assert_eq!(classify(AP_FLAGS_PRIVACY, 0, 0), Security::Wep);
+        assert_eq!(classify(AP_FLAGS_PRIVACY, AP_SEC_KEY_MGMT_PSK, 0), Security::WpaPsk);
+        assert_eq!(classify(AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_SAE), Security::Wpa3Sae);
+        assert_eq!(classify(AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_PSK | AP_SEC_KEY_MGMT_SAE), Security::Wpa2Wpa3);
+        assert_eq!(classify(AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_EAP_SUITE_B_192), Security::EnterpriseSuiteB);
+
+        let suites = suites_from_flags(AP_SEC_PAIR_TKIP | AP_SEC_PAIR_CCMP | AP_SEC_GROUP_TKIP | AP_SEC_KEY_MGMT_PSK).unwrap();
+        assert_eq!(suites.pairwise_ciphers, [Cipher::Tkip, Cipher::Ccmp128]);
+        assert_eq!(suites.group_cipher, Some(Cipher::Tkip));
+        assert_eq!(suites.akm_suites, [Akm::Psk]);
+    }
+}
@@ -0,0 +1,453 @@
+//! Backend that shells out to NetworkManager's `nmcli`.
+
+mod terse;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant, SystemTime };
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::BoxStream;
+
+use super::nm_dbus::{
+    self,
+    AP_SEC_GROUP_CCMP,
+    AP_SEC_GROUP_TKIP,
+    AP_SEC_GROUP_WEP104,
+    AP_SEC_GROUP_WEP40,
+    AP_SEC_KEY_MGMT_802_1X,
+    AP_SEC_KEY_MGMT_EAP_SUITE_B_192,
+    AP_SEC_KEY_MGMT_OWE,
+    AP_SEC_KEY_MGMT_OWE_TM,
+    AP_SEC_KEY_MGMT_PSK,
+    AP_SEC_KEY_MGMT_SAE,
+    AP_SEC_PAIR_CCMP,
+    AP_SEC_PAIR_TKIP,
+    AP_SEC_PAIR_WEP104,
+    AP_SEC_PAIR_WEP40,
+};
+use super::process::{ self, ProcessLines };
+use super::{
+    scan_stream,
+    Capabilities,
+    Interface,
+    RescanPolicy,
+    ScanBackend,
+    ScanError,
+    ScanEvent,
+    ScanRequest,
+    AUTO_RESCAN_MAX_AGE,
+    DEFAULT_TIMEOUT,
+};
+use crate::channel::ChannelWidth;
+use crate::ie::CAPABILITY_PRIVACY;
+use crate::network::{ HiddenSsid, MacAddress, Network, Security, Ssid };
+
+pub const NAME: &str = "nmcli";
+
+/// Fields requested from `nmcli device wifi list`. New fields go at the
+/// end, so scans saved with an older list are a prefix of this one.
+const WIFI_FIELDS: &[&str] = &[
+    "SSID",
+    "BSSID",
+    "SIGNAL",
+    "SECURITY",
+    "WPA-FLAGS",
+    "RSN-FLAGS",
+    "CHAN",
+    "FREQ",
+    "BANDWIDTH",
+];
+/// The fields of the oldest saved scans.
+const BASIC_WIFI_FIELD_COUNT: usize = 3;
+const DEVICE_FIELDS: &[&str] = &["DEVICE", "TYPE"];
+
+/// Names nmcli prints in `WPA-FLAGS` and `RSN-FLAGS`, with the
+/// NetworkManager flag each stands for.
+const SECURITY_FLAG_NAMES: &[(&str, u32)] = &[
+    ("pair_wep40", AP_SEC_PAIR_WEP40),
+    ("pair_wep104", AP_SEC_PAIR_WEP104),
+    ("pair_tkip", AP_SEC_PAIR_TKIP),
+    ("pair_ccmp", AP_SEC_PAIR_CCMP),
+    ("group_wep40", AP_SEC_GROUP_WEP40),
+    ("group_wep104", AP_SEC_GROUP_WEP104),
+    ("group_tkip", AP_SEC_GROUP_TKIP),
+    ("group_ccmp", AP_SEC_GROUP_CCMP),
+    ("psk", AP_SEC_KEY_MGMT_PSK),
+    ("802.1X", AP_SEC_KEY_MGMT_802_1X),
+    ("sae", AP_SEC_KEY_MGMT_SAE),
+    ("owe", AP_SEC_KEY_MGMT_OWE),
+    ("owe_tm", AP_SEC_KEY_MGMT_OWE_TM),
+    ("eap_suite_b_192", AP_SEC_KEY_MGMT_EAP_SUITE_B_192),
+];
+
+#[derive(Debug, Clone)]
+pub struct NmcliBackend {
+    program: String,
+    /// Latest point in time NetworkManager's list is known to have been at
+    /// least as fresh as, shared between scans.
+    fresh_since: Arc<Mutex<Option<Instant>>>,
+    /// The row each BSSID had in the previous listing and when it was first
+    /// listed like that. nmcli reports no time per BSS, so an unchanged row
+    /// read back from NetworkManager's list keeps its time.
+    rows: Arc<Mutex<HashMap<MacAddress, (String, SystemTime)>>>,
+}
+
+impl Default for NmcliBackend {
+    fn default() -> Self {
+        Self {
+            program: String::from("nmcli"),
+            fresh_since: Arc::new(Mutex::new(None)),
+            rows: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl NmcliBackend {
+    #[cfg(test)]
+    pub(super) fn with_program(program: &str) -> Self {
+        Self { program: program.to_string(), ..Self::default() }
+    }
+
+    /// Whether the program can be found, looking it up on `PATH` unless it
+    /// is given as a path.
+    pub(super) fn is_installed(&self) -> bool {
+        let program = Path::new(&self.program);
+        if program.components().count() > 1 {
+            return program.is_file();
+        }
+        std::env::var_os("PATH").is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+    }
+}
+
+/// Updates `fresh_since` after a successful scan with `policy` that ended at
+/// `now`.
+fn advance_freshness(fresh_since: Option<Instant>, policy: RescanPolicy, now: Instant) -> Option<Instant> {
+    let guaranteed = match policy {
+        RescanPolicy::Cached => None,
+        RescanPolicy::Auto => now.checked_sub(AUTO_RESCAN_MAX_AGE),
+        RescanPolicy::Force => Some(now),
+    };
+    fresh_since.max(guaranteed)
+}
+
+fn rescan_arg(policy: RescanPolicy) -> &'static str {
+    match policy {
+        RescanPolicy::Cached => "no",
+        RescanPolicy::Auto => "auto",
+        RescanPolicy::Force => "yes",
+    }
+}
+
+/// Builds `-t -f <fields> <args>`.
+fn terse_args(fields: &[&str], args: &[&str]) -> Vec<String> {
+    let mut terse = vec![String::from("-t"), String::from("-f"), fields.join(",")];
+    terse.extend(args.iter().map(|arg| arg.to_string()));
+    terse
+}
+
+/// nmcli lists nothing rather than failing when the radio is off, so an
+/// empty result is double-checked against `nmcli radio wifi`.
+async fn radio_enabled(program: &str, timeout: Duration) -> Result<bool, ScanError> {
+    let stdout = process::output(program, &["-t", "radio", "wifi"], timeout, classify_failure).await?;
+    Ok(stdout.trim() != "disabled")
+}
+
+impl ScanBackend for NmcliBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { trigger_scan: true, frequency: true, security: true, ..Capabilities::default() }
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        let program = self.program.clone();
+        async move {
+            let args = terse_args(DEVICE_FIELDS, &["device"]);
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            let stdout = process::output(&program, &args, DEFAULT_TIMEOUT, classify_failure).await?;
+            let records = terse::parse_output(&stdout, DEVICE_FIELDS)
+                .map_err(|err| ScanError::Parse(err.to_string()))?;
+            Ok(records
+                .iter()
+                .filter(|record| record.get("TYPE") == Some("wifi"))
+                .filter_map(|record| record.get("DEVICE"))
+                .map(|name| Interface { name: name.to_string(), mac_address: None })
+                .collect())
+        }.boxed()
+    }
+
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        let program = self.program.clone();
+        let fresh_since = Arc::clone(&self.fresh_since);
+        let rows = Arc::clone(&self.rows);
+        scan_stream(move |emit| async move {
+            let mut args = vec!["device", "wifi", "list"];
+            if let Some(interface) = &request.interface {
+                args.extend(["ifname", interface]);
+            }
+            args.extend(["--rescan", rescan_arg(request.rescan)]);
+            let args = terse_args(WIFI_FIELDS, &args);
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+            let previous_rows = rows.lock().unwrap().clone();
+            let mut current_rows = HashMap::new();
+            let mut process = ProcessLines::spawn(&program, &args, request.timeout)?;
+            let mut line_number = 0;
+            let mut found = 0;
+            while let Some(line) = process.next_line().await? {
+                line_number += 1;
+                if line.is_empty() {
+                    continue;
+                }
+                let record = terse::parse_line(&line, line_number, WIFI_FIELDS)
+                    .map_err(|err| ScanError::Parse(err.to_string()))?;
+                if let Some(mut network) = network_from_record(&record) {
+                    found += 1;
+                    // A forced rescan sees every BSS anew, even unchanged.
+                    if request.rescan != RescanPolicy::Force
+                        && let Some((row, seen)) = previous_rows.get(&network.bssid)
+                        && *row == line
+                    {
+                        network.last_seen = *seen;
+                    }
+                    current_rows.insert(network.bssid, (line.clone(), network.last_seen));
+                    emit.network(network);
+                }
+            }
+            process.finish(classify_failure).await?;
+            *rows.lock().unwrap() = current_rows;
+
+            let now = Instant::now();
+            let fresh = {
+                let mut fresh_since = fresh_since.lock().unwrap();
+                *fresh_since = advance_freshness(*fresh_since, request.rescan, now);
+                *fresh_since
+            };
+            if let Some(fresh) = fresh {
+                emit.cache_age(now.saturating_duration_since(fresh));
+            }
+
+            if found == 0 && !radio_enabled(&program, request.timeout).await? {
+                return Err(ScanError::RadioDisabled);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Maps nmcli's stderr on a non-zero exit to a [`ScanError`].
+fn classify_failure(stderr: &str) -> ScanError {
+    let message = stderr.trim().trim_start_matches("Error: ").to_string();
+    let lower = message.to_lowercase();
+
+    if lower.contains("networkmanager is not running") {
+        ScanError::ToolMissing(String::from("NetworkManager"))
+    } else if lower.contains("not authorized") || lower.contains("insufficient privileges") {
+        ScanError::PermissionDenied(message)
+    } else if lower.contains("no wi-fi device found") {
+        ScanError::NoInterface(None)
+    } else if lower.starts_with("device '") && lower.contains("not found") {
+        let name = message.split('\'').nth(1).map(str::to_string);
+        ScanError::NoInterface(name)
+    } else if lower.contains("wi-fi radio") && lower.contains("disabled") {
+        ScanError::RadioDisabled
+    } else {
+        ScanError::Failed(message)
+    }
+}
+
+/// Parses saved `nmcli -t -f <WIFI_FIELDS> device wifi list` output, as used
+/// by recorded scans. Saves with only the leading fields, down to
+/// `SSID,BSSID,SIGNAL`, are accepted as well.
+pub(super) fn parse_networks(output: &str) -> Result<Vec<Network>, ScanError> {
+    let first_line = output.lines().find(|line| !line.is_empty()).unwrap_or_default();
+    let count = terse::split_fields(first_line).len();
+    let fields = if (BASIC_WIFI_FIELD_COUNT..WIFI_FIELDS.len()).contains(&count) {
+        &WIFI_FIELDS[..count]
+    } else {
+        WIFI_FIELDS
+    };
+    let records = terse::parse_output(output, fields).map_err(|err| ScanError::Parse(err.to_string()))?;
+    Ok(records.iter().filter_map(network_from_record).collect())
+}
+
+/// Turns a `WPA-FLAGS` or `RSN-FLAGS` value, such as
+/// `pair_ccmp group_ccmp psk sae` or `(none)`, into element suites.
+fn suites_from_names(names: &str) -> Option<crate::ie::Rsn> {
+    let flags = names
+        .split_whitespace()
+        .filter_map(|name| SECURITY_FLAG_NAMES.iter().find(|(known, _)| *known == name))
+        .fold(0, |flags, (_, flag)| flags | flag);
+    nm_dbus::suites_from_flags(flags)
+}
+
+/// Turns one `SSID,BSSID,SIGNAL,...` record into a network. Rows with an
+/// unparseable BSSID or signal are skipped. Hidden networks have an empty
+/// SSID; nmcli does not tell how the beacon hides it.
+fn network_from_record(record: &terse::Record<'_>) -> Option<Network> {
+    let name = record.get("SSID")?;
+    let bssid = record.get("BSSID")?.parse::<MacAddress>().ok()?;
+    let strength = record.get("SIGNAL")?.parse::<u8>().ok()?;
+    let mut network = Network::new(Ssid::from(name), bssid, strength);
+    if network.hidden_ssid.is_some() {
+        network.hidden_ssid = Some(HiddenSsid::Unknown);
+    }
+    if let (Some(security), Some(wpa_flags), Some(rsn_flags)) =
+        (record.get("SECURITY"), record.get("WPA-FLAGS"), record.get("RSN-FLAGS"))
+    {
+        network.elements.wpa = suites_from_names(wpa_flags);
+        network.elements.rsn = suites_from_names(rsn_flags);
+        // SECURITY is empty (or `--` outside terse mode) for open networks
+        // and names WEP when there are no WPA or RSN flags.
+        let privacy = if matches!(security.trim(), "" | "--") { 0 } else { CAPABILITY_PRIVACY };
+        network.security = Security::from_elements(Some(privacy), &network.elements);
+    }
+    // `FREQ` and `BANDWIDTH` carry a ` MHz` suffix.
+    let megahertz = |field| record.get(field)?.trim_end_matches("MHz").trim().parse::<u32>().ok();
+    network.frequency_mhz = megahertz("FREQ").filter(|mhz| *mhz > 0);
+    network.channel = record.get("CHAN").and_then(|channel| channel.parse().ok());
+    network.channel_width = megahertz("BANDWIDTH").and_then(ChannelWidth::from_mhz);
+    network.derive_channel();
+    network.raw = Some(record.line().to_string());
+    Some(network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::History;
+    use iced::futures::StreamExt;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn parses_basic_fixture_into_networks() {
+        let networks = parse_networks(include_str!("../../fixtures/nmcli/dev_wifi_basic.txt")).unwrap();
+        let ssids: Vec<_> = networks
+            .iter()
+            .map(|network| network.ssid.display())
+            .collect();
+        assert_eq!(ssids, ["HomeNet", "HomeNet", "", "Cafe: Free WiFi", r"back\\slash", "DIRECT-4F-HP OfficeJet"]);
+        assert_eq!(networks[1].bssid, "AA:BB:CC:DD:EE:02".parse().unwrap());
+        assert_eq!(networks[2].hidden_ssid, Some(HiddenSsid::Unknown));
+        assert_eq!(networks[5].signal_percent, 100);
+        assert_eq!(networks[0].raw.as_deref(), Some(r"HomeNet:AA\:BB\:CC\:DD\:EE\:01:82"));
+    }
+
+    #[test]
+    fn classifies_security_fields() {
+        let networks = parse_networks(include_str!("../../fixtures/nmcli/dev_wifi_security.txt")).unwrap();
+        let security: Vec<_> = networks
+            .iter()
+            .map(|network| network.security)
+            .collect();
+        assert_eq!(security, [
+            Security::Wpa2Psk,
+            Security::Wpa2Wpa3,
+            Security::Wpa3Sae,
+            Security::Open,
+            Security::Owe,
+            Security::Wep,
+            Security::WpaPsk,
+            Security::Wpa2Psk,
+            Security::Enterprise,
+            Security::EnterpriseSuiteB,
+        ]);
+        assert_eq!(networks[0].security_summary(), "WPA2-PSK · CCMP");
+        assert_eq!(networks[7].security_summary(), "WPA2-PSK · TKIP/CCMP · group TKIP");
+        let weak: Vec<_> = networks
+            .iter()
+            .map(Network::has_weak_security)
+            .collect();
+        assert_eq!(weak, [false, false, false, true, false, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn reads_channel_fields() {
+        let networks = parse_networks(include_str!("../../fixtures/nmcli/dev_wifi_security.txt")).unwrap();
+        let channels: Vec<_> = networks
+            .iter()
+            .map(Network::channel_summary)
+            .collect();
+        assert_eq!(channels[..4], [
+            "2.4 GHz · ch 6 · 20 MHz",
+            "5 GHz · ch 36 · 80 MHz",
+            "6 GHz · ch 37 · 160 MHz",
+            "2.4 GHz · ch 1 · 20 MHz",
+        ]);
+        assert_eq!(networks[2].frequency_mhz, Some(6135));
+        assert_eq!(networks[0].center_frequency_mhz, Some(2437));
+        // Without a bandwidth, as from nmcli before 1.46, the width is unknown.
+        assert_eq!(networks[5].channel_summary(), "2.4 GHz · ch 11");
+    }
+
+    #[tokio::test]
+    async fn cached_rereads_keep_their_time() {
+        let dir = std::env::temp_dir().join(format!("nmcli-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fixture = dir.join("dev_wifi_security.txt");
+        std::fs::write(&fixture, include_str!("../../fixtures/nmcli/dev_wifi_security.txt")).unwrap();
+        let program = dir.join("nmcli");
+        std::fs::write(&program, format!("#!/bin/sh\nexec cat '{}'\n", fixture.display())).unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let backend = NmcliBackend::with_program(program.to_str().unwrap());
+
+        let mut scans = Vec::new();
+        for rescan in [RescanPolicy::Cached, RescanPolicy::Cached, RescanPolicy::Force] {
+            let request = ScanRequest { rescan, ..ScanRequest::default() };
+            let networks: Vec<_> = backend
+                .scan(request)
+                .filter_map(|event| async move {
+                    match event {
+                        ScanEvent::Network(network) => Some(*network),
+                        _ => None,
+                    }
+                })
+                .collect().await;
+            scans.push(networks);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut history = History::default();
+        history.record(&scans[0]);
+        history.record(&scans[1]);
+        assert!(scans[0].iter().all(|network| history.get(network.bssid).unwrap().scans == 1));
+        assert_eq!(scans[0][0].last_seen, scans[1][0].last_seen);
+        // A forced rescan sees the same rows anew.
+        assert!(scans[2][0].last_seen > scans[1][0].last_seen);
+    }
+
+    #[test]
+    fn freshness_follows_rescan_policy() {
+        let now = Instant::now() + Duration::from_secs(120);
+        let earlier = now - Duration::from_secs(90);
+
+        assert_eq!(advance_freshness(None, RescanPolicy::Cached, now), None);
+        assert_eq!(advance_freshness(Some(earlier), RescanPolicy::Cached, now), Some(earlier));
+        assert_eq!(advance_freshness(Some(earlier), RescanPolicy::Auto, now), Some(now - AUTO_RESCAN_MAX_AGE));
+        assert_eq!(advance_freshness(Some(now), RescanPolicy::Auto, now), Some(now));
+        assert_eq!(advance_freshness(Some(earlier), RescanPolicy::Force, now), Some(now));
+    }
+
+    #[test]
+    fn classifies_common_failures() {
+        assert_eq!(
+            classify_failure("Error: NetworkManager is not running.\n"),
+            ScanError::ToolMissing(String::from("NetworkManager"))
+        );
+        assert_eq!(
+            classify_failure("Error: Device 'wlan7' not found.\n"),
+            ScanError::NoInterface(Some(String::from("wlan7")))
+        );
+        assert_eq!(classify_failure("Error: No Wi-Fi device found.\n"), ScanError::NoInterface(None));
+        assert!(matches!(
+            classify_failure("Error: Scanning not allowed: not authorized.\n"),
+            ScanError::PermissionDenied(_)
+        ));
+        assert_eq!(classify_failure("Error: boom\n"), ScanError::Failed(String::from("boom")));
+    }
+}
@@ -0,0 +1,163 @@
+//! Parsing of nmcli's terse (`-t`) output format.
+//!
+//! In terse mode nmcli separates fields with `:` and escapes any literal `:`
+//! or `\` inside a field as `\:` and `\\`. BSSIDs are themselves
+//! colon-separated, so splitting on `:` without honouring escapes mangles
+//! every line.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerseError {
+    pub line: usize,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl fmt::Display for TerseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "nmcli output line {}: expected {} fields, found {}",
+            self.line,
+            self.expected,
+            self.found
+        )
+    }
+}
+
+impl std::error::Error for TerseError {}
+
+/// Splits one line of terse output into its unescaped fields.
+///
+/// Empty fields are preserved, so `a::b` yields `["a", "", "b"]`. A trailing
+/// lone backslash is kept as-is.
+pub fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                match chars.next() {
+                    Some(escaped) => current.push(escaped),
+                    None => current.push('\\'),
+                }
+            }
+            ':' => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+/// One line of terse output, addressable by the field names that were
+/// requested with `nmcli -f`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<'a> {
+    fields: &'a [&'a str],
+    values: Vec<String>,
+    line: String,
+}
+
+impl<'a> Record<'a> {
+    /// The line as nmcli printed it.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .position(|name| *name == field)
+            .map(|index| self.values[index].as_str())
+    }
+}
+
+/// Parses a single line of terse output. `line_number` is only used for
+/// error reporting.
+pub fn parse_line<'a>(line: &str, line_number: usize, fields: &'a [&'a str]) -> Result<Record<'a>, TerseError> {
+    let values = split_fields(line);
+    if values.len() != fields.len() {
+        return Err(TerseError {
+            line: line_number,
+            expected: fields.len(),
+            found: values.len(),
+        });
+    }
+    Ok(Record { fields, values, line: line.to_string() })
+}
+
+/// Parses the whole output of an `nmcli -t -f <fields> ...` invocation.
+///
+/// Blank lines are skipped. Every other line must contain exactly one value
+/// per requested field.
+pub fn parse_output<'a>(output: &str, fields: &'a [&'a str]) -> Result<Vec<Record<'a>>, TerseError> {
+    output
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| parse_line(line, index + 1, fields))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASIC: &str = include_str!("../../../fixtures/nmcli/dev_wifi_basic.txt");
+    const BASIC_FIELDS: &[&str] = &["SSID", "BSSID", "SIGNAL"];
+
+    #[test]
+    fn splits_unescaped_fields() {
+        assert_eq!(split_fields("a:b:c"), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn honours_escaped_colons_and_backslashes() {
+        assert_eq!(split_fields(r"a\:b:c\\d"), ["a:b", r"c\d"]);
+        assert_eq!(split_fields(r"AA\:BB\:CC\:DD\:EE\:FF"), ["AA:BB:CC:DD:EE:FF"]);
+    }
+
+    #[test]
+    fn keeps_empty_fields() {
+        assert_eq!(split_fields(""), [""]);
+        assert_eq!(split_fields("::"), ["", "", ""]);
+        assert_eq!(split_fields(":x:"), ["", "x", ""]);
+    }
+
+    #[test]
+    fn keeps_trailing_backslash() {
+        assert_eq!(split_fields(r"abc\"), [r"abc\"]);
+    }
+
+    #[test]
+    fn parses_basic_fixture() {
+        let records = parse_output(BASIC, BASIC_FIELDS).unwrap();
+        assert_eq!(records.len(), 6);
+
+        assert_eq!(records[0].get("SSID"), Some("HomeNet"));
+        assert_eq!(records[0].get("BSSID"), Some("AA:BB:CC:DD:EE:01"));
+        assert_eq!(records[0].get("SIGNAL"), Some("82"));
+
+        assert_eq!(records[2].get("SSID"), Some(""));
+        assert_eq!(records[3].get("SSID"), Some("Cafe: Free WiFi"));
+        assert_eq!(records[3].get("BSSID"), Some("12:34:56:78:9A:BC"));
+        assert_eq!(records[4].get("SSID"), Some(r"back\slash"));
+        assert_eq!(records[5].get("SIGNAL"), Some("100"));
+    }
+
+    #[test]
+    fn unknown_field_is_none() {
+        let records = parse_output(BASIC, BASIC_FIELDS).unwrap();
+        assert_eq!(records[0].get("CHAN"), None);
+    }
+
+    #[test]
+    fn rejects_wrong_field_count() {
+        let err = parse_output("a:b\n\nc\n", &["ONE", "TWO"]).unwrap_err();
+        assert_eq!(err, TerseError { line: 3, expected: 2, found: 1 });
+    }
+}
@@ -0,0 +1,171 @@
+//! Helpers for backends that run an external tool.
+//!
+//! Children are spawned with `kill_on_drop`, so dropping a scan stream (for
+//! example when the user cancels) also terminates the tool.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::{ AsyncBufReadExt, AsyncReadExt, BufReader };
+use tokio::process::{ Child, ChildStdout, Command };
+use tokio::task::JoinHandle;
+use tokio::time::{ timeout_at, Instant };
+
+use super::ScanError;
+
+/// A running tool whose stdout is consumed line by line before a shared
+/// deadline. Stderr is collected alongside, so a tool writing a lot of it
+/// does not block on a full pipe.
+pub struct ProcessLines {
+    program: String,
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+    stderr: JoinHandle<Vec<u8>>,
+    deadline: Instant,
+    timeout: Duration,
+}
+
+impl ProcessLines {
+    pub fn spawn(program: &str, args: &[&str], timeout: Duration) -> Result<Self, ScanError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| ScanError::from_spawn(program, &err))?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let stderr = tokio::spawn(async move {
+            let mut bytes = Vec::new();
+            // A read error only loses the tail of the message.
+            let _ = stderr.read_to_end(&mut bytes).await;
+            bytes
+        });
+
+        Ok(Self {
+            program: program.to_string(),
+            child,
+            stdout: BufReader::new(stdout),
+            stderr,
+            deadline: Instant::now() + timeout,
+            timeout,
+        })
+    }
+
+    /// Returns the next line of stdout, or `None` once the tool closed it.
+    /// Bytes that are not UTF-8, such as raw SSIDs some tools print, are
+    /// replaced.
+    pub async fn next_line(&mut self) -> Result<Option<String>, ScanError> {
+        let mut line = Vec::new();
+        match timeout_at(self.deadline, self.stdout.read_until(b'\n', &mut line)).await {
+            Ok(Ok(0)) => Ok(None),
+            Ok(Ok(_)) => {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                    if line.last() == Some(&b'\r') {
+                        line.pop();
+                    }
+                }
+                Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+            }
+            Ok(Err(err)) => Err(ScanError::Failed(format!("reading from {}: {}", self.program, err))),
+            Err(_) => Err(ScanError::Timeout(self.timeout)),
+        }
+    }
+
+    /// Waits for the tool to exit. A non-zero exit status is turned into an
+    /// error by passing its stderr to `classify`.
+    pub async fn finish(mut self, classify: impl FnOnce(&str) -> ScanError) -> Result<(), ScanError> {
+        let exit = async {
+            // Unread stdout is discarded so the tool does not block writing it.
+            tokio::io::copy(&mut self.stdout, &mut tokio::io::sink()).await?;
+            let status = self.child.wait().await?;
+            let stderr = self.stderr.await.unwrap_or_default();
+            Ok::<_, std::io::Error>((status, stderr))
+        };
+        let (status, stderr) = match timeout_at(self.deadline, exit).await {
+            Ok(Ok(exit)) => exit,
+            Ok(Err(err)) => return Err(ScanError::Failed(format!("waiting for {}: {}", self.program, err))),
+            Err(_) => return Err(ScanError::Timeout(self.timeout)),
+        };
+        if status.success() {
+            Ok(())
+        } else {
+            Err(classify(&String::from_utf8_lossy(&stderr)))
+        }
+    }
+}
+
+/// Runs a tool to completion and returns its stdout.
+pub async fn output(
+    program: &str,
+    args: &[&str],
+    timeout: Duration,
+    classify: impl FnOnce(&str) -> ScanError
+) -> Result<String, ScanError> {
+    let mut process = ProcessLines::spawn(program, args, timeout)?;
+    let mut stdout = String::new();
+    while let Some(line) = process.next_line().await? {
+        stdout.push_str(&line);
+        stdout.push('\n');
+    }
+    process.finish(classify).await?;
+    Ok(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str, timeout: Duration) -> ProcessLines {
+        ProcessLines::spawn("sh", &["-c", script], timeout).unwrap()
+    }
+
+    #[tokio::test]
+    async fn yields_lines_then_none() {
+        let mut process = sh("printf 'a\\nb\\n'", Duration::from_secs(5));
+        assert_eq!(process.next_line().await, Ok(Some(String::from("a"))));
+        assert_eq!(process.next_line().await, Ok(Some(String::from("b"))));
+        assert_eq!(process.next_line().await, Ok(None));
+        assert_eq!(process.finish(|_| unreachable!()).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn classifies_failure_from_stderr() {
+        let result = output("sh", &["-c", "echo oops >&2; exit 3"], Duration::from_secs(5), |stderr| {
+            ScanError::Failed(stderr.trim().to_string())
+        }).await;
+        assert_eq!(result, Err(ScanError::Failed(String::from("oops"))));
+    }
+
+    #[tokio::test]
+    async fn replaces_bytes_that_are_not_utf8() {
+        let mut process = sh("printf 'SSID: caf\\351\\r\\n'", Duration::from_secs(5));
+        assert_eq!(process.next_line().await, Ok(Some(String::from("SSID: caf\u{fffd}"))));
+        assert_eq!(process.next_line().await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn drains_stderr_while_reading_stdout() {
+        // Far more stderr than a pipe buffers, written before any stdout.
+        let script = "head -c 1000000 /dev/zero >&2; echo done";
+        let mut process = sh(script, Duration::from_secs(5));
+        assert_eq!(process.next_line().await, Ok(Some(String::from("done"))));
+        assert_eq!(process.finish(|_| unreachable!()).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn times_out_on_slow_tool() {
+        let timeout = Duration::from_millis(100);
+        let mut process = sh("sleep 5", timeout);
+        assert_eq!(process.next_line().await, Err(ScanError::Timeout(timeout)));
+    }
+
+    #[tokio::test]
+    async fn missing_tool_is_reported() {
+        let result = ProcessLines::spawn("definitely-not-a-real-tool", &[], Duration::from_secs(1));
+        assert!(matches!(result, Err(ScanError::ToolMissing(_))));
+    }
+}
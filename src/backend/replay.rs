@@ -0,0 +1,348 @@
+//! Backend that replays recorded scans from disk, for development and demos
+//! on machines without wireless hardware.
+//!
+//! A recording is either a single saved scan, as printed by
+//! `nmcli -t -f SSID,BSSID,SIGNAL,SECURITY,WPA-FLAGS,RSN-FLAGS device wifi list`
+//! (or with just the first three fields) or `iw dev <if> scan`,
+//! or a JSON file with a timed sequence of frames:
+//!
+//! ```json
+//! {
+//!   "loop_ms": 20000,
+//!   "scan_duration_ms": 1500,
+//!   "frames": [
+//!     { "at_ms": 0, "networks": [{ "ssid": "HomeNet", "bssid": "AA:BB:CC:DD:EE:01", "signal_dbm": -45 }] },
+//!     { "at_ms": 5000, "file": "office.txt" },
+//!     { "at_ms": 15000, "error": "radio_disabled" }
+//!   ]
+//! }
+//! ```
+//!
+//! Each scan returns the frame current at the time elapsed since the
+//! recording was loaded, wrapping around after `loop_ms` if given. Frame
+//! files are resolved relative to the JSON file.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::BoxStream;
+use serde::Deserialize;
+
+use super::{ iw, nmcli, scan_stream, Capabilities, Interface, ScanBackend, ScanError, ScanEvent, ScanRequest };
+use crate::channel::ChannelWidth;
+use crate::network::{ dbm_to_percent, Mode, Network, Security, Ssid };
+
+pub const NAME: &str = "replay";
+
+/// What a frame replays: a list of networks or a failed scan.
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Networks(Vec<Network>),
+    Error(ScanError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    at: Duration,
+    outcome: Outcome,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayBackend {
+    frames: Arc<Vec<Frame>>,
+    loop_after: Option<Duration>,
+    /// Time over which each scan's networks are spread out, to exercise
+    /// streaming in the GUI.
+    scan_duration: Duration,
+    started: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecordingFile {
+    loop_ms: Option<u64>,
+    #[serde(default)]
+    scan_duration_ms: u64,
+    frames: Vec<FrameFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrameFile {
+    #[serde(default)]
+    at_ms: u64,
+    networks: Option<Vec<NetworkFile>>,
+    file: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NetworkFile {
+    ssid: String,
+    bssid: String,
+    signal_percent: Option<u8>,
+    signal_dbm: Option<i32>,
+    frequency_mhz: Option<u32>,
+    channel: Option<u32>,
+    width_mhz: Option<u32>,
+    security: Option<String>,
+    mode: Option<String>,
+    #[serde(default)]
+    known: bool,
+}
+
+fn parse_error(path: &Path, detail: impl std::fmt::Display) -> ScanError {
+    ScanError::Parse(format!("{}: {}", path.display(), detail))
+}
+
+impl NetworkFile {
+    fn into_network(self, path: &Path) -> Result<Network, ScanError> {
+        let bssid = self.bssid.parse().map_err(|err| parse_error(path, err))?;
+        let percent = self.signal_percent
+            .or(self.signal_dbm.map(dbm_to_percent))
+            .unwrap_or(0);
+        let mut network = Network::new(Ssid::from(self.ssid.as_str()), bssid, percent);
+        network.signal_dbm = self.signal_dbm;
+        network.frequency_mhz = self.frequency_mhz;
+        network.channel = self.channel;
+        network.channel_width = self.width_mhz.and_then(ChannelWidth::from_mhz);
+        network.known = self.known;
+        network.security = match self.security.as_deref() {
+            None => Security::Unknown,
+            Some("open") => Security::Open,
+            Some("owe") => Security::Owe,
+            Some("wep") => Security::Wep,
+            Some("wpa") => Security::WpaPsk,
+            Some("wpa2") => Security::Wpa2Psk,
+            Some("wpa3") => Security::Wpa3Sae,
+            Some("wpa2/wpa3") => Security::Wpa2Wpa3,
+            Some("enterprise") => Security::Enterprise,
+            Some("suite-b") => Security::EnterpriseSuiteB,
+            Some(other) => return Err(parse_error(path, format_args!("unknown security {:?}", other))),
+        };
+        network.mode = match self.mode.as_deref() {
+            None | Some("infrastructure") => Mode::Infrastructure,
+            Some("adhoc") => Mode::AdHoc,
+            Some("mesh") => Mode::Mesh,
+            Some(other) => return Err(parse_error(path, format_args!("unknown mode {:?}", other))),
+        };
+        network.derive_channel();
+        Ok(network)
+    }
+}
+
+/// Parses a saved nmcli or iw scan, telling them apart by iw's `BSS` lines.
+fn parse_saved_scan(path: &Path, text: &str) -> Result<Vec<Network>, ScanError> {
+    if text.trim_start().starts_with("BSS ") {
+        Ok(iw::parse_networks(text))
+    } else {
+        nmcli::parse_networks(text).map_err(|err| parse_error(path, err))
+    }
+}
+
+fn read(path: &Path) -> Result<String, ScanError> {
+    std::fs::read_to_string(path).map_err(|err| ScanError::Failed(format!("cannot read {}: {}", path.display(), err)))
+}
+
+fn replayed_error(path: &Path, name: &str) -> Result<ScanError, ScanError> {
+    Ok(match name {
+        "tool_missing" => ScanError::ToolMissing(String::from(NAME)),
+        "permission_denied" => ScanError::PermissionDenied(String::from("replayed")),
+        "radio_disabled" => ScanError::RadioDisabled,
+        "no_interface" => ScanError::NoInterface(None),
+        "timeout" => ScanError::Timeout(super::DEFAULT_TIMEOUT),
+        other => return Err(parse_error(path, format_args!("unknown error {:?}", other))),
+    })
+}
+
+impl FrameFile {
+    fn into_frame(self, path: &Path) -> Result<Frame, ScanError> {
+        let outcome = match (self.networks, self.file, self.error) {
+            (Some(networks), None, None) => {
+                let networks = networks
+                    .into_iter()
+                    .map(|network| network.into_network(path))
+                    .collect::<Result<_, _>>()?;
+                Outcome::Networks(networks)
+            }
+            (None, Some(file), None) => {
+                let file = path.parent().unwrap_or(Path::new(".")).join(file);
+                Outcome::Networks(parse_saved_scan(&file, &read(&file)?)?)
+            }
+            (None, None, Some(error)) => Outcome::Error(replayed_error(path, &error)?),
+            _ => return Err(parse_error(path, "each frame needs exactly one of networks, file or error")),
+        };
+        Ok(Frame { at: Duration::from_millis(self.at_ms), outcome })
+    }
+}
+
+impl ReplayBackend {
+    /// Loads a recording, starting its clock now.
+    pub fn load(path: &Path) -> Result<Self, ScanError> {
+        let text = read(path)?;
+        if !text.trim_start().starts_with('{') {
+            let frame = Frame { at: Duration::ZERO, outcome: Outcome::Networks(parse_saved_scan(path, &text)?) };
+            return Ok(Self::new(vec![frame], None, Duration::ZERO));
+        }
+
+        let recording: RecordingFile = serde_json::from_str(&text).map_err(|err| parse_error(path, err))?;
+        let mut frames = recording.frames
+            .into_iter()
+            .map(|frame| frame.into_frame(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        if frames.is_empty() {
+            return Err(parse_error(path, "no frames"));
+        }
+        frames.sort_by_key(|frame| frame.at);
+        Ok(Self::new(
+            frames,
+            recording.loop_ms.filter(|ms| *ms > 0).map(Duration::from_millis),
+            Duration::from_millis(recording.scan_duration_ms)
+        ))
+    }
+
+    fn new(frames: Vec<Frame>, loop_after: Option<Duration>, scan_duration: Duration) -> Self {
+        Self { frames: Arc::new(frames), loop_after, scan_duration, started: Instant::now() }
+    }
+
+    /// The frame current `elapsed` into the recording, and how long it has
+    /// been current.
+    fn frame_at(&self, elapsed: Duration) -> (&Frame, Duration) {
+        let elapsed = match self.loop_after {
+            Some(period) => Duration::from_nanos((elapsed.as_nanos() % period.as_nanos()) as u64),
+            None => elapsed,
+        };
+        let frame = self.frames
+            .iter()
+            .rev()
+            .find(|frame| frame.at <= elapsed)
+            .unwrap_or(&self.frames[0]);
+        (frame, elapsed.saturating_sub(frame.at))
+    }
+}
+
+impl ScanBackend for ReplayBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            signal_dbm: true,
+            frequency: true,
+            security: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        async {
+            Ok(vec![Interface { name: String::from(NAME), mac_address: None }])
+        }.boxed()
+    }
+
+    fn scan(&self, _request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        let (frame, age) = self.frame_at(self.started.elapsed());
+        let outcome = frame.outcome.clone();
+        let scan_duration = self.scan_duration;
+        scan_stream(move |emit| async move {
+            let networks = match outcome {
+                Outcome::Networks(networks) => networks,
+                Outcome::Error(err) => {
+                    tokio::time::sleep(scan_duration).await;
+                    return Err(err);
+                }
+            };
+            let pause = scan_duration / networks.len().max(1) as u32;
+            for network in networks {
+                tokio::time::sleep(pause).await;
+                emit.network(network);
+            }
+            emit.cache_age(age);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::futures::StreamExt;
+
+    fn fixture(path: &str) -> ReplayBackend {
+        ReplayBackend::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(path)).unwrap()
+    }
+
+    fn ssids(frame: &Frame) -> Vec<&str> {
+        match &frame.outcome {
+            Outcome::Networks(networks) => networks
+                .iter()
+                .map(|network| network.ssid.display())
+                .collect(),
+            Outcome::Error(_) => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn loads_timed_recording() {
+        let backend = fixture("replay/walk.json");
+        assert_eq!(backend.frames.len(), 4);
+
+        let (frame, age) = backend.frame_at(Duration::from_millis(6500));
+        assert_eq!(ssids(frame), ["HomeNet", "Cafe Free WiFi"]);
+        assert_eq!(age, Duration::from_millis(1500));
+        let Outcome::Networks(networks) = &frame.outcome else {
+            panic!("expected networks");
+        };
+        assert_eq!(networks[0].signal_percent, 42);
+        assert!(networks[0].known);
+        assert_eq!(networks[1].channel_summary(), "2.4 GHz · ch 1 · 20 MHz");
+
+        let (frame, _) = backend.frame_at(Duration::from_millis(12000));
+        assert_eq!(ssids(frame), ["HomeNet", "Office Net", "Cafe Free WiFi", "", "Corp", "Corp6"]);
+        let (frame, _) = backend.frame_at(Duration::from_millis(16000));
+        assert_eq!(frame.outcome, Outcome::Error(ScanError::RadioDisabled));
+    }
+
+    #[test]
+    fn wraps_around_after_loop_period() {
+        let backend = fixture("replay/walk.json");
+        let (frame, age) = backend.frame_at(Duration::from_millis(20_500));
+        assert_eq!(ssids(frame), ["HomeNet", "HomeNet", "Neighbour"]);
+        assert_eq!(age, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn loads_saved_nmcli_and_iw_scans() {
+        let nmcli = fixture("nmcli/dev_wifi_basic.txt");
+        assert_eq!(ssids(nmcli.frame_at(Duration::from_secs(3600)).0).len(), 6);
+        let iw = fixture("iw/scan.txt");
+        assert_eq!(ssids(iw.frame_at(Duration::ZERO).0)[0], "HomeNet");
+    }
+
+    #[test]
+    fn rejects_broken_recordings() {
+        let dir = std::env::temp_dir().join(format!("replay-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("broken.json");
+        std::fs::write(&path, r#"{ "frames": [{ "at_ms": 0, "networks": [], "error": "timeout" }] }"#).unwrap();
+        assert!(matches!(ReplayBackend::load(&path), Err(ScanError::Parse(_))));
+        std::fs::write(&path, r#"{ "frames": [{ "networks": [{ "ssid": "x", "bssid": "nope" }] }] }"#).unwrap();
+        assert!(matches!(ReplayBackend::load(&path), Err(ScanError::Parse(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(ReplayBackend::load(Path::new("/nonexistent/recording.json")), Err(ScanError::Failed(_))));
+    }
+
+    #[tokio::test]
+    async fn replays_current_frame() {
+        let backend = fixture("replay/walk.json");
+        let events: Vec<_> = backend.scan(ScanRequest::default()).collect().await;
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[3], ScanEvent::CacheAge(_)));
+        assert_eq!(events[4], ScanEvent::Finished(Ok(())));
+    }
+}
@@ -0,0 +1,55 @@
+//! A private D-Bus daemon for tests that need to talk to mock services.
+//!
+//! These tests need `dbus-daemon` and fail without it. Setting
+//! `SKIP_DBUS_TESTS=1` skips them instead, for machines that lack it.
+
+use std::process::Stdio;
+
+use tokio::io::{ AsyncBufReadExt, BufReader };
+use tokio::process::{ Child, Command };
+
+/// Environment variable that lets D-Bus tests pass without a daemon.
+const SKIP_VARIABLE: &str = "SKIP_DBUS_TESTS";
+
+pub struct TestBus {
+    address: String,
+    _daemon: Child,
+}
+
+impl TestBus {
+    /// Starts `dbus-daemon` on a fresh session bus. When it cannot be
+    /// started, panics, or returns `None` so the calling test can skip
+    /// itself if `SKIP_DBUS_TESTS` is set.
+    pub async fn start() -> Option<Self> {
+        match Self::spawn().await {
+            Ok(bus) => Some(bus),
+            Err(err) if std::env::var_os(SKIP_VARIABLE).is_some() => {
+                eprintln!("skipping D-Bus test: {}", err);
+                None
+            }
+            Err(err) => panic!("{}; set {}=1 to skip D-Bus tests", err, SKIP_VARIABLE),
+        }
+    }
+
+    async fn spawn() -> Result<Self, String> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| format!("cannot start dbus-daemon: {}", err))?;
+        let stdout = daemon.stdout.take().expect("stdout is piped");
+        let address = BufReader::new(stdout)
+            .lines()
+            .next_line().await
+            .ok()
+            .flatten()
+            .ok_or("dbus-daemon did not print its address")?;
+        Ok(Self { address, _daemon: daemon })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
@@ -0,0 +1,559 @@
+//! Backend that talks to wpa_supplicant over its control interface, for
+//! systems that run neither NetworkManager nor iw.
+//!
+//! wpa_supplicant creates one UNIX datagram socket per interface in its
+//! control directory. Clients bind a socket of their own, send plain text
+//! commands and get one datagram back per command. After `ATTACH`,
+//! unsolicited events such as `<2>CTRL-EVENT-SCAN-RESULTS` arrive on the same
+//! socket, prefixed with their priority in angle brackets.
+
+mod parse;
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::time::{ Duration, SystemTime };
+
+use iced::futures::future::{ BoxFuture, FutureExt };
+use iced::futures::stream::BoxStream;
+use tokio::net::UnixDatagram;
+
+use super::{
+    scan_stream,
+    Capabilities,
+    Emitter,
+    Interface,
+    RescanPolicy,
+    ScanBackend,
+    ScanError,
+    ScanEvent,
+    ScanRequest,
+    AUTO_RESCAN_MAX_AGE,
+    DEFAULT_TIMEOUT,
+};
+use crate::ie::{ Elements, CAPABILITY_PRIVACY };
+use crate::network::{ dbm_to_percent, MacAddress, Mode, Network, Security, Ssid };
+
+pub const NAME: &str = "wpa_supplicant";
+
+const DEFAULT_CTRL_DIR: &str = "/var/run/wpa_supplicant";
+
+/// Large enough for `SCAN_RESULTS`, which wpa_supplicant truncates well
+/// below this.
+const REPLY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Distinguishes the client sockets of concurrent scans.
+static NEXT_SOCKET: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone)]
+pub struct WpaSupplicantBackend {
+    ctrl_dir: PathBuf,
+}
+
+impl Default for WpaSupplicantBackend {
+    fn default() -> Self {
+        Self { ctrl_dir: PathBuf::from(DEFAULT_CTRL_DIR) }
+    }
+}
+
+impl WpaSupplicantBackend {
+    #[cfg(test)]
+    fn with_ctrl_dir(ctrl_dir: &Path) -> Self {
+        Self { ctrl_dir: ctrl_dir.to_path_buf() }
+    }
+}
+
+/// A connection to the control socket of one interface.
+struct Control {
+    socket: UnixDatagram,
+    /// Path the client socket is bound to, removed again on drop.
+    local: PathBuf,
+}
+
+impl Control {
+    fn open(ctrl_dir: &Path, interface: &str) -> Result<Self, ScanError> {
+        let local = std::env::temp_dir().join(format!(
+            "wireless_scanner_gui-{}-{}",
+            std::process::id(),
+            NEXT_SOCKET.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_file(&local);
+        let socket = UnixDatagram::bind(&local).map_err(|err| {
+            ScanError::Failed(format!("cannot create control socket {}: {}", local.display(), err))
+        })?;
+        let control = Self { socket, local };
+
+        let remote = ctrl_dir.join(interface);
+        control.socket
+            .connect(&remote)
+            .map_err(|err| connect_error(ctrl_dir, interface, &err))?;
+        Ok(control)
+    }
+
+    /// Sends `command` and returns its reply, skipping any events that
+    /// arrive in between.
+    async fn request(&self, command: &str) -> Result<String, ScanError> {
+        self.socket.send(command.as_bytes()).await.map_err(socket_error)?;
+        loop {
+            let message = self.receive().await?;
+            if !message.starts_with('<') {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Waits until one of `names` is reported and returns the event without
+    /// its priority prefix. Only delivered after `ATTACH`.
+    async fn wait_for_event(&self, names: &[&str]) -> Result<String, ScanError> {
+        loop {
+            let message = self.receive().await?;
+            if let Some((_, event)) = message.split_once('>')
+                && message.starts_with('<')
+                && names.iter().any(|name| event.starts_with(name))
+            {
+                return Ok(event.trim_end().to_string());
+            }
+        }
+    }
+
+    async fn receive(&self) -> Result<String, ScanError> {
+        let mut buffer = vec![0; REPLY_BUFFER_SIZE];
+        let len = self.socket.recv(&mut buffer).await.map_err(socket_error)?;
+        Ok(String::from_utf8_lossy(&buffer[..len]).into_owned())
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.local);
+    }
+}
+
+fn connect_error(ctrl_dir: &Path, interface: &str, err: &io::Error) -> ScanError {
+    match err.kind() {
+        io::ErrorKind::NotFound if ctrl_dir.is_dir() => ScanError::NoInterface(Some(interface.to_string())),
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => ScanError::ToolMissing(String::from(NAME)),
+        io::ErrorKind::PermissionDenied => {
+            ScanError::PermissionDenied(format!("cannot open {}", ctrl_dir.join(interface).display()))
+        }
+        _ => ScanError::Failed(format!("{}: {}", NAME, err)),
+    }
+}
+
+fn socket_error(err: io::Error) -> ScanError {
+    match err.kind() {
+        io::ErrorKind::ConnectionRefused => ScanError::ToolMissing(String::from(NAME)),
+        _ => ScanError::Failed(format!("{}: {}", NAME, err)),
+    }
+}
+
+/// Interfaces wpa_supplicant controls, one socket each. P2P device sockets
+/// are not scannable interfaces of their own.
+fn interface_names(ctrl_dir: &Path) -> Result<Vec<String>, ScanError> {
+    let entries = std::fs::read_dir(ctrl_dir).map_err(|err| {
+        match err.kind() {
+            io::ErrorKind::NotFound => ScanError::ToolMissing(String::from(NAME)),
+            io::ErrorKind::PermissionDenied => {
+                ScanError::PermissionDenied(format!("cannot read {}", ctrl_dir.display()))
+            }
+            _ => ScanError::Failed(format!("{}: {}", ctrl_dir.display(), err)),
+        }
+    })?;
+    let mut names: Vec<_> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| !name.starts_with("p2p-dev-"))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Explains why wpa_supplicant rejected a `SCAN` request.
+async fn scan_rejected(control: &Control, reply: &str) -> ScanError {
+    let status = control.request("STATUS").await.unwrap_or_default();
+    let disabled = parse::key_values(&status).any(|(key, value)| key == "wpa_state" && value == "INTERFACE_DISABLED");
+    if disabled {
+        ScanError::RadioDisabled
+    } else {
+        ScanError::Failed(format!("{} rejected the scan request: {}", NAME, reply.trim()))
+    }
+}
+
+/// Triggers a scan and waits for it to finish.
+async fn trigger_scan(control: &Control) -> Result<(), ScanError> {
+    let reply = control.request("ATTACH").await?;
+    if reply.trim() != "OK" {
+        return Err(ScanError::Failed(format!("{} refused ATTACH: {}", NAME, reply.trim())));
+    }
+
+    let scanned = async {
+        let reply = control.request("SCAN").await?;
+        // FAIL-BUSY means a scan is already running; its results will do.
+        if !matches!(reply.trim(), "OK" | "FAIL-BUSY") {
+            return Err(scan_rejected(control, &reply).await);
+        }
+        let event = control.wait_for_event(&["CTRL-EVENT-SCAN-RESULTS", "CTRL-EVENT-SCAN-FAILED"]).await?;
+        if event.starts_with("CTRL-EVENT-SCAN-FAILED") {
+            return Err(ScanError::Failed(event));
+        }
+        Ok(())
+    }.await;
+
+    let _ = control.request("DETACH").await;
+    scanned
+}
+
+/// Turns a scan result into a network. The SSID column holds the SSID of
+/// the last probe response, if any; a hidden network is decloaked when the
+/// beacon elements of `BSS <n>` hide it.
+fn network_from_result(result: parse::ScanResult, details: Option<&parse::BssDetails>) -> Network {
+    let ssid = Ssid::from_bytes(result.ssid);
+    let raw = match details {
+        Some(details) => format!("{}\n\n{}", result.line, details.reply),
+        None => result.line,
+    };
+    let beacon_ssid = details
+        .filter(|details| !details.beacon_ie.is_empty())
+        .and_then(|details| Elements::decode(&details.beacon_ie).ssid)
+        .map_or_else(|| ssid.clone(), Ssid::from_bytes);
+    let mut network = Network::new(beacon_ssid, result.bssid, dbm_to_percent(result.signal_dbm));
+    network.reveal_ssid(ssid);
+    network.signal_dbm = Some(result.signal_dbm);
+    network.frequency_mhz = Some(result.frequency_mhz);
+    // The elements from `BSS <n>` carry the full suites and PMF bits; the
+    // flags column only stands in when they are missing.
+    network.elements = match details {
+        Some(details) if !details.ie.is_empty() => Elements::decode(&details.ie),
+        _ => Elements { rsn: result.flags.rsn, wpa: result.flags.wpa, ..Elements::default() },
+    };
+    // The flags column is authoritative for WEP, so the privacy bit is taken
+    // from it rather than from the capabilities.
+    let privacy = if result.flags.wep { CAPABILITY_PRIVACY } else { 0 };
+    network.security = Security::from_elements(Some(privacy), &network.elements);
+    network.derive_channel();
+    network.mode = if result.flags.ibss {
+        Mode::AdHoc
+    } else if result.flags.mesh {
+        Mode::Mesh
+    } else {
+        Mode::Infrastructure
+    };
+    network.raw = Some(raw);
+    if let Some(details) = details {
+        network.capability_info = details.capabilities;
+        if let Some(age) = details.age {
+            let now = SystemTime::now();
+            network.last_seen = now.checked_sub(Duration::from_secs(age)).unwrap_or(now);
+        }
+    }
+    network
+}
+
+/// Reads the current results: the `SCAN_RESULTS` table, completed with
+/// `BSS <n>` for each entry of the BSS table. The two are ordered
+/// differently, so entries are matched by BSSID. Returns the age of the
+/// freshest entry, when known.
+async fn results(control: &Control) -> Result<(Vec<Network>, Option<Duration>), ScanError> {
+    let rows = parse::parse_scan_results(&control.request("SCAN_RESULTS").await?);
+
+    let mut details: HashMap<MacAddress, parse::BssDetails> = HashMap::new();
+    for index in 0.. {
+        let Some(bss) = parse::parse_bss(&control.request(&format!("BSS {}", index)).await?) else {
+            break;
+        };
+        if let Some(bssid) = bss.bssid {
+            details.insert(bssid, bss);
+        }
+    }
+
+    let freshest = rows
+        .iter()
+        .filter_map(|row| details.get(&row.bssid)?.age)
+        .min()
+        .map(Duration::from_secs);
+    let networks = rows
+        .into_iter()
+        .map(|row| {
+            let bss = details.get(&row.bssid);
+            network_from_result(row, bss)
+        })
+        .collect();
+    Ok((networks, freshest))
+}
+
+async fn scan_interface(ctrl_dir: &Path, interface: &str, policy: RescanPolicy, emit: &Emitter) -> Result<(), ScanError> {
+    let control = Control::open(ctrl_dir, interface)?;
+
+    if policy != RescanPolicy::Force {
+        let (networks, age) = results(&control).await?;
+        let fresh = age.is_some_and(|age| age <= AUTO_RESCAN_MAX_AGE);
+        if policy == RescanPolicy::Cached || fresh {
+            networks.into_iter().for_each(|network| emit.network(network));
+            if let Some(age) = age {
+                emit.cache_age(age);
+            }
+            return Ok(());
+        }
+    }
+
+    trigger_scan(&control).await?;
+    let (networks, age) = results(&control).await?;
+    networks.into_iter().for_each(|network| emit.network(network));
+    emit.cache_age(age.unwrap_or_default());
+    Ok(())
+}
+
+async fn scan(ctrl_dir: PathBuf, request: ScanRequest, emit: Emitter) -> Result<(), ScanError> {
+    let interfaces = match request.interface {
+        Some(interface) => vec![interface],
+        None => interface_names(&ctrl_dir)?,
+    };
+    if interfaces.is_empty() {
+        return Err(ScanError::NoInterface(None));
+    }
+    for interface in &interfaces {
+        scan_interface(&ctrl_dir, interface, request.rescan, &emit).await?;
+    }
+    Ok(())
+}
+
+impl ScanBackend for WpaSupplicantBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            trigger_scan: true,
+            signal_dbm: true,
+            frequency: true,
+            security: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn interfaces(&self) -> BoxFuture<'static, Result<Vec<Interface>, ScanError>> {
+        let ctrl_dir = self.ctrl_dir.clone();
+        let list = async move {
+            let mut interfaces = Vec::new();
+            for name in interface_names(&ctrl_dir)? {
+                let mut mac_address = None;
+                if let Ok(control) = Control::open(&ctrl_dir, &name)
+                    && let Ok(status) = control.request("STATUS").await
+                {
+                    mac_address = parse::key_values(&status)
+                        .find(|(key, _)| *key == "address")
+                        .and_then(|(_, address)| address.parse().ok());
+                }
+                interfaces.push(Interface { name, mac_address });
+            }
+            Ok(interfaces)
+        };
+        async move {
+            tokio::time::timeout(DEFAULT_TIMEOUT, list).await.unwrap_or(Err(ScanError::Timeout(DEFAULT_TIMEOUT)))
+        }.boxed()
+    }
+
+    fn scan(&self, request: ScanRequest) -> BoxStream<'static, ScanEvent> {
+        let ctrl_dir = self.ctrl_dir.clone();
+        scan_stream(move |emit| async move {
+            let timeout = request.timeout;
+            tokio::time::timeout(timeout, scan(ctrl_dir, request, emit)).await.unwrap_or(Err(ScanError::Timeout(timeout)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::HiddenSsid;
+    use iced::futures::StreamExt;
+    use std::sync::{ Arc, Mutex };
+
+    const SCAN_RESULTS: &str = include_str!("../../fixtures/wpa_supplicant/scan_results.txt");
+    const BSS_0: &str = include_str!("../../fixtures/wpa_supplicant/bss_0.txt");
+    const BSS_1: &str = include_str!("../../fixtures/wpa_supplicant/bss_1.txt");
+
+    /// A stand-in for wpa_supplicant that serves `wlan0` from a temporary
+    /// control directory, replaying recorded replies. Commands without a
+    /// recording get an empty reply, which is what `BSS <n>` sends past the
+    /// end of the table.
+    struct StandIn {
+        ctrl_dir: PathBuf,
+        commands: Arc<Mutex<Vec<String>>>,
+        server: tokio::task::JoinHandle<()>,
+    }
+
+    impl StandIn {
+        fn start(recording: &[(&str, &str)]) -> Self {
+            static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+            let ctrl_dir = std::env::temp_dir().join(format!(
+                "wpa_supplicant-stand-in-{}-{}",
+                std::process::id(),
+                NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = std::fs::remove_dir_all(&ctrl_dir);
+            std::fs::create_dir_all(&ctrl_dir).unwrap();
+            let socket = UnixDatagram::bind(ctrl_dir.join("wlan0")).unwrap();
+
+            let replies: HashMap<String, String> = recording
+                .iter()
+                .map(|(command, reply)| (command.to_string(), reply.to_string()))
+                .collect();
+            let commands = Arc::new(Mutex::new(Vec::new()));
+            let received = commands.clone();
+            let server = tokio::spawn(async move {
+                let mut buffer = vec![0; 4096];
+                let mut attached = None;
+                while let Ok((len, peer)) = socket.recv_from(&mut buffer).await {
+                    let Some(peer) = peer.as_pathname().map(Path::to_path_buf) else {
+                        continue;
+                    };
+                    let command = String::from_utf8_lossy(&buffer[..len]).into_owned();
+                    received.lock().unwrap().push(command.clone());
+
+                    let reply = match (command.as_str(), replies.get(&command)) {
+                        (_, Some(reply)) => reply.clone(),
+                        ("ATTACH", None) => {
+                            attached = Some(peer.clone());
+                            String::from("OK\n")
+                        }
+                        ("DETACH", None) => {
+                            attached = None;
+                            String::from("OK\n")
+                        }
+                        ("SCAN", None) => String::from("OK\n"),
+                        _ => String::new(),
+                    };
+                    let _ = socket.send_to(reply.as_bytes(), &peer).await;
+                    if command == "SCAN" && reply == "OK\n" && let Some(attached) = &attached {
+                        let _ = socket.send_to(b"<3>CTRL-EVENT-SCAN-STARTED ", attached).await;
+                        let _ = socket.send_to(b"<2>CTRL-EVENT-SCAN-RESULTS ", attached).await;
+                    }
+                }
+            });
+            Self { ctrl_dir, commands, server }
+        }
+
+        fn backend(&self) -> WpaSupplicantBackend {
+            WpaSupplicantBackend::with_ctrl_dir(&self.ctrl_dir)
+        }
+
+        fn commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+    }
+
+    impl Drop for StandIn {
+        fn drop(&mut self) {
+            self.server.abort();
+            let _ = std::fs::remove_dir_all(&self.ctrl_dir);
+        }
+    }
+
+    fn recorded_results() -> Vec<(&'static str, &'static str)> {
+        vec![("SCAN_RESULTS", SCAN_RESULTS), ("BSS 0", BSS_0), ("BSS 1", BSS_1)]
+    }
+
+    async fn collect(backend: &WpaSupplicantBackend, rescan: RescanPolicy) -> Vec<ScanEvent> {
+        let request = ScanRequest { interface: Some(String::from("wlan0")), rescan, ..ScanRequest::default() };
+        backend.scan(request).collect().await
+    }
+
+    fn networks(events: &[ScanEvent]) -> Vec<&Network> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                ScanEvent::Network(network) => Some(network.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decloaks_hidden_networks_from_beacon_elements() {
+        let results = parse::parse_scan_results("bssid / frequency / signal level / flags / ssid\n02:00:00:00:00:05\t5200\t-58\t[WPA2-PSK-CCMP][ESS]\tBackstage\n");
+        let details = parse::parse_bss("bssid=02:00:00:00:00:05\nie=00094261636b7374616765\nbeacon_ie=0000\n").unwrap();
+        let network = network_from_result(results[0].clone(), Some(&details));
+        assert_eq!(network.ssid.display(), "Backstage");
+        assert_eq!(network.hidden_ssid, Some(HiddenSsid::ZeroLength));
+        assert!(network.is_decloaked());
+        assert_eq!(
+            network.raw.as_deref(),
+            Some("02:00:00:00:00:05\t5200\t-58\t[WPA2-PSK-CCMP][ESS]\tBackstage\n\nbssid=02:00:00:00:00:05\nie=00094261636b7374616765\nbeacon_ie=0000")
+        );
+    }
+
+    #[tokio::test]
+    async fn forced_scan_waits_for_results() {
+        let stand_in = StandIn::start(&recorded_results());
+        let events = collect(&stand_in.backend(), RescanPolicy::Force).await;
+
+        let networks = networks(&events);
+        let ssids: Vec<_> = networks
+            .iter()
+            .map(|network| network.ssid.display())
+            .collect();
+        assert_eq!(ssids, ["HomeNet", "Office Net", "Cafe Free WiFi", "", "Corp", "café \"old\""]);
+        let security: Vec<_> = networks
+            .iter()
+            .map(|network| network.security)
+            .collect();
+        assert_eq!(security, [
+            Security::Wpa2Psk,
+            Security::Wpa2Wpa3,
+            Security::Open,
+            Security::WpaPsk,
+            Security::EnterpriseSuiteB,
+            Security::Wep,
+        ]);
+        assert_eq!(networks[0].signal_percent, 87);
+        assert_eq!(networks[1].capability_info, Some(0x1111));
+        // Decoded from the `ie` field of `BSS 0`.
+        assert_eq!(networks[0].elements.ds_channel, Some(6));
+        assert_eq!(networks[0].channel_summary(), "2.4 GHz · ch 6 · 20 MHz");
+        assert_eq!(networks[1].channel_summary(), "5 GHz · ch 36");
+        assert_eq!(networks[0].elements.rsn.as_ref().unwrap().capabilities, Some(0x000c));
+        assert_eq!(networks[3].hidden_ssid, Some(HiddenSsid::ZeroLength));
+        assert_eq!(networks[5].mode, Mode::AdHoc);
+
+        assert!(events.contains(&ScanEvent::CacheAge(Duration::from_secs(1))));
+        assert_eq!(events.last(), Some(&ScanEvent::Finished(Ok(()))));
+        assert_eq!(stand_in.commands()[..3], ["ATTACH", "SCAN", "DETACH"]);
+    }
+
+    #[tokio::test]
+    async fn fresh_results_are_not_rescanned() {
+        let stand_in = StandIn::start(&recorded_results());
+        for policy in [RescanPolicy::Cached, RescanPolicy::Auto] {
+            let events = collect(&stand_in.backend(), policy).await;
+            assert_eq!(networks(&events).len(), 6);
+        }
+        assert!(!stand_in.commands().contains(&String::from("SCAN")));
+    }
+
+    #[tokio::test]
+    async fn reports_disabled_interface() {
+        let stand_in = StandIn::start(&[("SCAN", "FAIL\n"), ("STATUS", "wpa_state=INTERFACE_DISABLED\n")]);
+        let events = collect(&stand_in.backend(), RescanPolicy::Force).await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::RadioDisabled))]);
+    }
+
+    #[tokio::test]
+    async fn reports_missing_interface() {
+        let stand_in = StandIn::start(&[]);
+        let request = ScanRequest { interface: Some(String::from("wlan9")), ..ScanRequest::default() };
+        let events: Vec<_> = stand_in.backend().scan(request).collect().await;
+        assert_eq!(events, [ScanEvent::Finished(Err(ScanError::NoInterface(Some(String::from("wlan9")))))]);
+    }
+
+    #[tokio::test]
+    async fn lists_interfaces_with_addresses() {
+        let stand_in = StandIn::start(&[("STATUS", "wpa_state=COMPLETED\naddress=02:11:22:33:44:55\n")]);
+        let interfaces = stand_in.backend().interfaces().await.unwrap();
+        assert_eq!(interfaces, [Interface {
+            name: String::from("wlan0"),
+            mac_address: Some("02:11:22:33:44:55".parse().unwrap()),
+        }]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Channel numbers, bands and channel widths.
+//!
+//! Backends report a primary frequency and, where they can, the operation
+//! elements the AP advertises. Everything else about where a BSS lives is
+//! derived here, so all backends fill [`Network`](crate::network::Network)
+//! the same way.
+
+use std::fmt;
+
+use crate::ie::{ Elements, SecondaryChannelOffset };
+use crate::network::Band;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ChannelWidth {
+    Mhz20,
+    Mhz40,
+    Mhz80,
+    Mhz160,
+    /// Two non-adjacent 80 MHz segments.
+    Mhz80Plus80,
+    Mhz320,
+}
+
+impl ChannelWidth {
+    /// Maps a width in MHz, as NetworkManager reports it.
+    pub fn from_mhz(mhz: u32) -> Option<Self> {
+        match mhz {
+            20 => Some(ChannelWidth::Mhz20),
+            40 => Some(ChannelWidth::Mhz40),
+            80 => Some(ChannelWidth::Mhz80),
+            160 => Some(ChannelWidth::Mhz160),
+            320 => Some(ChannelWidth::Mhz320),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ChannelWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChannelWidth::Mhz20 => "20 MHz",
+            ChannelWidth::Mhz40 => "40 MHz",
+            ChannelWidth::Mhz80 => "80 MHz",
+            ChannelWidth::Mhz160 => "160 MHz",
+            ChannelWidth::Mhz80Plus80 => "80+80 MHz",
+            ChannelWidth::Mhz320 => "320 MHz",
+        })
+    }
+}
+
+impl Band {
+    pub fn from_frequency(mhz: u32) -> Option<Self> {
+        match mhz {
+            2400..=2500 => Some(Band::Band2_4GHz),
+            4900..=5924 => Some(Band::Band5GHz),
+            5925..=7125 => Some(Band::Band6GHz),
+            57000..=71000 => Some(Band::Band60GHz),
+            _ => None,
+        }
+    }
+}
+
+/// The IEEE channel number of a primary frequency. Frequencies off the
+/// channel raster have none.
+pub fn frequency_to_channel(mhz: u32) -> Option<u32> {
+    let band = Band::from_frequency(mhz)?;
+    let channel = match (band, mhz) {
+        (Band::Band2_4GHz, 2484) => 14,
+        (Band::Band2_4GHz, _) => mhz.checked_sub(2407)? / 5,
+        (Band::Band5GHz, ..=4999) => (mhz - 4000) / 5,
+        (Band::Band5GHz, _) => (mhz - 5000) / 5,
+        (Band::Band6GHz, 5935) => 2,
+        (Band::Band6GHz, _) => mhz.checked_sub(5950)? / 5,
+        (Band::Band60GHz, _) => mhz.checked_sub(56160)? / 2160,
+    };
+    (channel_to_frequency(channel, band) == Some(mhz)).then_some(channel)
+}
+
+/// The center frequency of `channel` in `band`; the inverse of
+/// [`frequency_to_channel`].
+pub fn channel_to_frequency(channel: u32, band: Band) -> Option<u32> {
+    match (band, channel) {
+        (Band::Band2_4GHz, 14) => Some(2484),
+        (Band::Band2_4GHz, 1..=13) => Some(2407 + channel * 5),
+        (Band::Band5GHz, 180..=199) => Some(4000 + channel * 5),
+        (Band::Band5GHz, 1..=179) => Some(5000 + channel * 5),
+        (Band::Band6GHz, 2) => Some(5935),
+        (Band::Band6GHz, 1..=233) => Some(5950 + channel * 5),
+        (Band::Band60GHz, 1..=6) => Some(56160 + channel * 2160),
+        _ => None,
+    }
+}
+
+/// Width and center frequencies of the whole channel a BSS operates on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation {
+    pub width: ChannelWidth,
+    pub center_mhz: u32,
+    /// Center of the second segment of an 80+80 MHz channel.
+    pub center2_mhz: Option<u32>,
+}
+
+impl Operation {
+    /// Derives the operating channel from the EHT, HE, VHT and HT operation
+    /// elements, the newest one present winning. BSSs with elements but no
+    /// HT operation are legacy 20 MHz ones. Returns `None` without elements.
+    pub fn from_elements(elements: &Elements, primary_mhz: u32) -> Option<Self> {
+        let band = Band::from_frequency(primary_mhz)?;
+        let center = |segment: u8| channel_to_frequency(u32::from(segment), band);
+
+        if let Some(eht) = elements.eht_operation
+            && let Some(width) = eht.channel_width
+        {
+            // CCFS1 is the center of a 160 or 320 MHz channel, CCFS0 that of
+            // the 80 MHz segment holding the primary channel.
+            let (width, segment) = match width {
+                0 => (ChannelWidth::Mhz20, eht.center_segment0),
+                1 => (ChannelWidth::Mhz40, eht.center_segment0),
+                2 => (ChannelWidth::Mhz80, eht.center_segment0),
+                3 => (ChannelWidth::Mhz160, eht.center_segment1),
+                _ => (ChannelWidth::Mhz320, eht.center_segment1),
+            };
+            return Some(Operation { width, center_mhz: center(segment)?, center2_mhz: None });
+        }
+
+        if let Some(six_ghz) = elements.he_operation.and_then(|he| he.six_ghz) {
+            let width = match six_ghz.channel_width {
+                0 => ChannelWidth::Mhz20,
+                1 => ChannelWidth::Mhz40,
+                2 => ChannelWidth::Mhz80,
+                _ => return wide_operation(six_ghz.center_segment0, six_ghz.center_segment1, center),
+            };
+            return Some(Operation { width, center_mhz: center(six_ghz.center_segment0)?, center2_mhz: None });
+        }
+
+        if let Some(vht) = elements.vht_operation {
+            match vht.channel_width {
+                0 => {}
+                1 => return wide_operation(vht.center_segment0, vht.center_segment1, center),
+                // Deprecated encodings, with the 160 MHz center or both
+                // 80+80 MHz centers given directly.
+                2 => {
+                    return Some(Operation {
+                        width: ChannelWidth::Mhz160,
+                        center_mhz: center(vht.center_segment0)?,
+                        center2_mhz: None,
+                    });
+                }
+                _ => {
+                    return Some(Operation {
+                        width: ChannelWidth::Mhz80Plus80,
+                        center_mhz: center(vht.center_segment0)?,
+                        center2_mhz: center(vht.center_segment1),
+                    });
+                }
+            }
+        }
+
+        if let Some(ht) = elements.ht_operation {
+            return Some(match ht.secondary_channel_offset {
+                SecondaryChannelOffset::None => Operation { width: ChannelWidth::Mhz20, center_mhz: primary_mhz, center2_mhz: None },
+                SecondaryChannelOffset::Above => Operation { width: ChannelWidth::Mhz40, center_mhz: primary_mhz + 10, center2_mhz: None },
+                SecondaryChannelOffset::Below => Operation { width: ChannelWidth::Mhz40, center_mhz: primary_mhz - 10, center2_mhz: None },
+            });
+        }
+
+        // Supported rates are in every beacon, so their presence tells
+        // decoded elements apart from ones rebuilt from security flags.
+        (!elements.supported_rates.is_empty()).then_some(Operation {
+            width: ChannelWidth::Mhz20,
+            center_mhz: primary_mhz,
+            center2_mhz: None,
+        })
+    }
+}
+
+/// An 80, 160 or 80+80 MHz channel in the VHT and HE encoding: segment 0
+/// is the center of the primary 80 MHz, segment 1 is zero for 80 MHz, the
+/// center of the whole channel for 160 MHz, or the center of the second
+/// segment for 80+80 MHz.
+fn wide_operation(segment0: u8, segment1: u8, center: impl Fn(u8) -> Option<u32>) -> Option<Operation> {
+    let (width, center_mhz, center2_mhz) = if segment1 == 0 {
+        (ChannelWidth::Mhz80, center(segment0)?, None)
+    } else if segment1.abs_diff(segment0) == 8 {
+        (ChannelWidth::Mhz160, center(segment1)?, None)
+    } else {
+        (ChannelWidth::Mhz80Plus80, center(segment0)?, center(segment1))
+    };
+    Some(Operation { width, center_mhz, center2_mhz })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ie::{ EhtOperation, HeOperation, HtOperation, Rate, SixGhzOperation, VhtOperation };
+
+    #[test]
+    fn converts_frequencies_to_channels() {
+        let cases = [
+            (2412, Band::Band2_4GHz, 1),
+            (2472, Band::Band2_4GHz, 13),
+            (2484, Band::Band2_4GHz, 14),
+            (4920, Band::Band5GHz, 184),
+            (5180, Band::Band5GHz, 36),
+            (5885, Band::Band5GHz, 177),
+            (5935, Band::Band6GHz, 2),
+            (5955, Band::Band6GHz, 1),
+            (6115, Band::Band6GHz, 33),
+            (7115, Band::Band6GHz, 233),
+            (60480, Band::Band60GHz, 2),
+        ];
+        for (mhz, band, channel) in cases {
+            assert_eq!(Band::from_frequency(mhz), Some(band), "{} MHz", mhz);
+            assert_eq!(frequency_to_channel(mhz), Some(channel), "{} MHz", mhz);
+            assert_eq!(channel_to_frequency(channel, band), Some(mhz), "channel {}", channel);
+        }
+        assert_eq!(frequency_to_channel(2300), None);
+        assert_eq!(frequency_to_channel(2418), None);
+    }
+
+    fn elements() -> Elements {
+        Elements { supported_rates: vec![Rate { half_mbps: 12, basic: true }], ..Elements::default() }
+    }
+
+    #[test]
+    fn derives_ht_and_legacy_widths() {
+        assert_eq!(Operation::from_elements(&Elements::default(), 2437), None);
+        assert_eq!(
+            Operation::from_elements(&elements(), 2437),
+            Some(Operation { width: ChannelWidth::Mhz20, center_mhz: 2437, center2_mhz: None })
+        );
+        let ht = Elements {
+            ht_operation: Some(HtOperation {
+                primary_channel: 40,
+                secondary_channel_offset: SecondaryChannelOffset::Below,
+                any_channel_width: true,
+            }),
+            ..elements()
+        };
+        assert_eq!(
+            Operation::from_elements(&ht, 5200),
+            Some(Operation { width: ChannelWidth::Mhz40, center_mhz: 5190, center2_mhz: None })
+        );
+    }
+
+    #[test]
+    fn derives_vht_widths() {
+        let vht = |channel_width, center_segment0, center_segment1| Elements {
+            vht_operation: Some(VhtOperation { channel_width, center_segment0, center_segment1 }),
+            ..elements()
+        };
+        let operation = |elements| Operation::from_elements(&elements, 5180).unwrap();
+
+        assert_eq!(operation(vht(1, 42, 0)), Operation { width: ChannelWidth::Mhz80, center_mhz: 5210, center2_mhz: None });
+        assert_eq!(operation(vht(1, 42, 50)), Operation { width: ChannelWidth::Mhz160, center_mhz: 5250, center2_mhz: None });
+        assert_eq!(
+            operation(vht(1, 42, 155)),
+            Operation { width: ChannelWidth::Mhz80Plus80, center_mhz: 5210, center2_mhz: Some(5775) }
+        );
+        assert_eq!(operation(vht(2, 50, 0)), Operation { width: ChannelWidth::Mhz160, center_mhz: 5250, center2_mhz: None });
+    }
+
+    #[test]
+    fn derives_six_ghz_and_eht_widths() {
+        let six_ghz = Elements {
+            he_operation: Some(HeOperation {
+                bss_color: Some(1),
+                six_ghz: Some(SixGhzOperation { primary_channel: 37, channel_width: 3, center_segment0: 39, center_segment1: 47 }),
+            }),
+            ..elements()
+        };
+        assert_eq!(
+            Operation::from_elements(&six_ghz, 6135),
+            Some(Operation { width: ChannelWidth::Mhz160, center_mhz: 6185, center2_mhz: None })
+        );
+
+        let eht = Elements {
+            eht_operation: Some(EhtOperation {
+                channel_width: Some(4),
+                center_segment0: 39,
+                center_segment1: 31,
+                disabled_subchannels: None,
+            }),
+            ..six_ghz
+        };
+        assert_eq!(
+            Operation::from_elements(&eht, 6135),
+            Some(Operation { width: ChannelWidth::Mhz320, center_mhz: 6105, center2_mhz: None })
+        );
+    }
+}
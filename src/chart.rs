@@ -0,0 +1,144 @@
+//! The signal history chart: the span of time it shows and where samples
+//! land on the plot.
+//!
+//! The canvas is drawn in `main.rs`; this module holds the state behind it,
+//! which zooming and panning change.
+
+use std::time::{ Duration, SystemTime };
+
+/// Signal at the top and bottom of the plot, in dBm.
+pub const STRONGEST_DBM: i32 = -30;
+pub const WEAKEST_DBM: i32 = -100;
+
+/// Narrowest span the chart can be zoomed to.
+pub const MIN_SPAN: Duration = Duration::from_secs(10);
+
+/// The span of time the chart shows. Unless panned into the past, it ends
+/// at the current time and follows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    span: Duration,
+    /// Where a panned window ends; `None` while it follows the current time.
+    end: Option<SystemTime>,
+}
+
+impl TimeWindow {
+    pub fn new(span: Duration) -> Self {
+        Self { span: span.max(MIN_SPAN), end: None }
+    }
+
+    pub fn span(&self) -> Duration {
+        self.span
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// Start and end of the window.
+    pub fn bounds(&self, now: SystemTime) -> (SystemTime, SystemTime) {
+        let end = self.end.map_or(now, |end| end.min(now));
+        (end.checked_sub(self.span).unwrap_or(SystemTime::UNIX_EPOCH), end)
+    }
+
+    /// Scales the span by `factor`, keeping the end in place. The span stays
+    /// between [`MIN_SPAN`] and `max_span`, the history length.
+    pub fn zoom(&mut self, factor: f32, max_span: Duration) {
+        if factor.is_finite() && factor > 0.0 {
+            self.span = self.span.mul_f32(factor).clamp(MIN_SPAN, max_span.max(MIN_SPAN));
+        }
+    }
+
+    /// Moves the window by `fraction` of its span, towards later times for
+    /// positive values. Moving up to the current time resumes following it.
+    pub fn pan(&mut self, fraction: f32, now: SystemTime) {
+        if !fraction.is_finite() {
+            return;
+        }
+        let (_, end) = self.bounds(now);
+        let shift = self.span.mul_f32(fraction.abs());
+        let end = if fraction > 0.0 { end.checked_add(shift) } else { end.checked_sub(shift) };
+        self.end = end.filter(|end| *end < now);
+    }
+
+    /// Horizontal position of `time` on a plot `width` wide, 0 at the start
+    /// of the window. Times outside the window fall outside `0..=width`.
+    pub fn x(&self, time: SystemTime, now: SystemTime, width: f32) -> f32 {
+        let (start, _) = self.bounds(now);
+        let seconds = match time.duration_since(start) {
+            Ok(after) => after.as_secs_f32(),
+            Err(before) => -before.duration().as_secs_f32(),
+        };
+        seconds / self.span.as_secs_f32() * width
+    }
+}
+
+/// Vertical position of `dbm` on a plot `height` high, strongest at the top.
+/// Values beyond the axis are drawn at its ends.
+pub fn y(dbm: i32, height: f32) -> f32 {
+    let below_top = STRONGEST_DBM - dbm.clamp(WEAKEST_DBM, STRONGEST_DBM);
+    below_top as f32 / (STRONGEST_DBM - WEAKEST_DBM) as f32 * height
+}
+
+/// Label of a time axis tick `age` before the current time, e.g. `-90s` or
+/// `-5m`.
+pub fn tick_label(age: Duration) -> String {
+    match age.as_secs() {
+        0 => String::from("now"),
+        seconds if seconds < 120 => format!("-{}s", seconds),
+        seconds => format!("-{}m", seconds / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn maps_samples_onto_the_plot() {
+        let window = TimeWindow::new(Duration::from_secs(100));
+        let now = at(1000);
+        assert_eq!(window.bounds(now), (at(900), at(1000)));
+        assert_eq!(window.x(at(950), now, 200.0), 100.0);
+        assert_eq!(window.x(at(850), now, 200.0), -100.0);
+        assert_eq!(y(-30, 70.0), 0.0);
+        assert_eq!(y(-65, 70.0), 35.0);
+        assert_eq!(y(-120, 70.0), 70.0);
+    }
+
+    #[test]
+    fn zooms_within_limits() {
+        let mut window = TimeWindow::new(Duration::from_secs(100));
+        window.zoom(0.5, Duration::from_secs(600));
+        assert_eq!(window.span(), Duration::from_secs(50));
+        window.zoom(0.01, Duration::from_secs(600));
+        assert_eq!(window.span(), MIN_SPAN);
+        window.zoom(1000.0, Duration::from_secs(600));
+        assert_eq!(window.span(), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn pans_and_resumes_following() {
+        let mut window = TimeWindow::new(Duration::from_secs(100));
+        let now = at(1000);
+        window.pan(-0.5, now);
+        assert!(!window.is_following());
+        assert_eq!(window.bounds(now), (at(850), at(950)));
+        // A panned window stays put as time passes.
+        assert_eq!(window.bounds(at(1010)), (at(850), at(950)));
+        window.pan(1.0, at(1010));
+        assert!(window.is_following());
+        assert_eq!(window.bounds(at(1010)), (at(910), at(1010)));
+    }
+
+    #[test]
+    fn labels_ticks() {
+        assert_eq!(tick_label(Duration::ZERO), "now");
+        assert_eq!(tick_label(Duration::from_secs(90)), "-90s");
+        assert_eq!(tick_label(Duration::from_secs(300)), "-5m");
+    }
+}
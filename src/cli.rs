@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::backend::replay::ReplayBackend;
+use crate::backend::{ self, ScanBackend, ScanRequest };
+use crate::history::DEFAULT_HISTORY_LENGTH;
+use crate::oui::OuiDatabase;
+use crate::signal::SignalThresholds;
+
+/// Command-line options passed to the application at startup.
+#[derive(Debug)]
+pub struct Flags {
+    pub backend: Arc<dyn ScanBackend>,
+    pub request: ScanRequest,
+    pub thresholds: SignalThresholds,
+    pub oui: Arc<OuiDatabase>,
+    /// How far back the signal history chart goes.
+    pub history_length: Duration,
+}
+
+impl Flags {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut backend = backend::default_backend();
+        let mut request = ScanRequest::default();
+        let mut thresholds = SignalThresholds::default();
+        let mut oui = None;
+        let mut history_length = DEFAULT_HISTORY_LENGTH;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--backend" => {
+                    let name = args.next().ok_or("--backend requires a value")?;
+                    backend = backend::by_name(&name).ok_or_else(|| format!("unknown backend {:?}", name))?;
+                }
+                "--replay" => {
+                    let path = args.next().ok_or("--replay requires a recording")?;
+                    backend = Arc::new(ReplayBackend::load(Path::new(&path)).map_err(|err| err.to_string())?);
+                }
+                "--interface" => {
+                    request.interface = Some(args.next().ok_or("--interface requires a value")?);
+                }
+                "--rescan" => {
+                    request.rescan = args.next().ok_or("--rescan requires a value")?.parse()?;
+                }
+                "--timeout" => {
+                    let seconds = args.next().ok_or("--timeout requires a value")?;
+                    let seconds = seconds
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                        .ok_or_else(|| format!("invalid timeout {:?}", seconds))?;
+                    request.timeout = Duration::from_secs_f32(seconds);
+                }
+                "--signal-thresholds" => {
+                    thresholds = args.next().ok_or("--signal-thresholds requires a value")?.parse()?;
+                }
+                "--history" => {
+                    let minutes = args.next().ok_or("--history requires a value")?;
+                    let minutes = minutes
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|minutes| minutes.is_finite() && *minutes > 0.0)
+                        .ok_or_else(|| format!("invalid history length {:?}", minutes))?;
+                    history_length = Duration::from_secs_f32(minutes * 60.0);
+                }
+                "--oui-database" => {
+                    let path = args.next().ok_or("--oui-database requires a file")?;
+                    oui = Some(OuiDatabase::load(Path::new(&path)).map_err(|err| err.to_string())?);
+                }
+                _ => {
+                    return Err(format!("unexpected argument {:?}", arg));
+                }
+            }
+        }
+        let oui = Arc::new(oui.unwrap_or_else(OuiDatabase::embedded));
+        Ok(Self { backend, request, thresholds, oui, history_length })
+    }
+}
+
+/// Runs `--update-oui-database <output> <registry.csv>...`: merges registry
+/// CSV files downloaded from the IEEE into a compressed database such as
+/// `data/oui.csv.gz`, the one embedded at build time. Returns a summary.
+pub fn update_oui_database(args: &[String]) -> Result<String, String> {
+    let [output, registries @ ..] = args else {
+        return Err(String::from("--update-oui-database requires an output file"));
+    };
+    if registries.is_empty() {
+        return Err(String::from("--update-oui-database requires at least one registry file"));
+    }
+    let mut database = OuiDatabase::default();
+    for registry in registries {
+        let path = Path::new(registry);
+        let csv = std::fs::read_to_string(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+        database.add_csv(&csv).map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    std::fs::write(output, database.to_gzip()).map_err(|err| format!("cannot write {}: {}", output, err))?;
+    Ok(format!("Wrote {} address blocks to {}", database.len(), output))
+}
+
+pub fn usage() -> String {
+    format!(
+        "Usage: wireless_scanner_gui [--backend <{}>] [--replay <recording>] [--interface <name>] [--rescan <cached|auto|force>] [--timeout <seconds>] [--signal-thresholds <excellent,good,fair,weak dBm>] [--history <minutes>] [--oui-database <registry.csv[.gz]>]\n       wireless_scanner_gui --update-oui-database <output.csv.gz> <registry.csv>...",
+        backend::BACKEND_NAMES.join("|")
+    )
+}
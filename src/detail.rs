@@ -0,0 +1,433 @@
+//! The contents of the detail pane: every known attribute of one network,
+//! as labelled text fields grouped in sections.
+//!
+//! The widgets are built in `main.rs`. Fields are plain text so each can be
+//! copied to the clipboard as shown.
+
+use std::time::SystemTime;
+
+use crate::history::Sightings;
+use crate::ie::{
+    Elements,
+    Rate,
+    Rsn,
+    SecondaryChannelOffset,
+    EXTENDED_CAPABILITY_BSS_TRANSITION,
+    EXTENDED_CAPABILITY_INTERWORKING,
+    EXTENDED_CAPABILITY_UTF8_SSID,
+};
+use crate::network::Network;
+use crate::scan_state::format_age;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub label: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub title: &'static str,
+    pub fields: Vec<Field>,
+}
+
+/// Collects fields, leaving out the ones without a value.
+#[derive(Default)]
+struct Fields(Vec<Field>);
+
+impl Fields {
+    fn add(&mut self, label: impl Into<String>, value: impl Into<String>) {
+        let value = value.into();
+        if !value.is_empty() {
+            self.0.push(Field { label: label.into(), value });
+        }
+    }
+
+    fn add_some(&mut self, label: impl Into<String>, value: Option<impl Into<String>>) {
+        if let Some(value) = value {
+            self.add(label, value);
+        }
+    }
+
+    fn section(self, title: &'static str) -> Option<Section> {
+        (!self.0.is_empty()).then_some(Section { title, fields: self.0 })
+    }
+}
+
+/// Names of the Capability Information bits.
+const CAPABILITY_NAMES: &[(u16, &str)] = &[
+    (0x0001, "ESS"),
+    (0x0002, "IBSS"),
+    (0x0010, "Privacy"),
+    (0x0020, "Short preamble"),
+    (0x0100, "Spectrum management"),
+    (0x0200, "QoS"),
+    (0x0400, "Short slot time"),
+    (0x0800, "APSD"),
+    (0x1000, "Radio measurement"),
+];
+
+/// The sections for `network`, with session statistics from `sightings`
+/// when it was in a completed scan.
+pub fn sections(network: &Network, sightings: Option<&Sightings>, now: SystemTime) -> Vec<Section> {
+    [
+        identity(network),
+        radio(network),
+        sightings.and_then(|sightings| session(sightings, now)),
+        security(network),
+        capabilities(network),
+        elements(&network.elements),
+        raw(network),
+    ]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn identity(network: &Network) -> Option<Section> {
+    let mut fields = Fields::default();
+    fields.add("SSID", network.ssid_label());
+    fields.add("SSID bytes", network.ssid.to_hex());
+    fields.add_some("Hidden", network.hidden_ssid.map(|hidden| hidden.to_string()));
+    let lookalikes: Vec<_> = network.ssid
+        .lookalikes()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    fields.add("Lookalike characters", lookalikes.join("; "));
+    fields.add("BSSID", network.bssid.to_string());
+    fields.add("Address type", if network.bssid.is_locally_administered() {
+        "Locally administered"
+    } else {
+        "Universally administered"
+    });
+    fields.add_some("Registered vendor", network.registered_vendor.as_deref());
+    fields.add("Mode", network.mode.to_string());
+    if network.known {
+        fields.add("Saved profile", "Yes");
+    }
+    fields.section("Network")
+}
+
+fn radio(network: &Network) -> Option<Section> {
+    let mut fields = Fields::default();
+    fields.add("Signal", network.signal_summary());
+    fields.add_some("Frequency", network.frequency_mhz.map(|mhz| format!("{} MHz", mhz)));
+    fields.add_some("Channel", network.channel.map(|channel| channel.to_string()));
+    fields.add_some("Band", network.band.map(|band| band.to_string()));
+    fields.add_some("Channel width", network.channel_width.map(|width| width.to_string()));
+    fields.add_some("Center frequency", network.center_frequency_mhz.map(|mhz| format!("{} MHz", mhz)));
+    fields.add_some("Second segment center", network.center_frequency2_mhz.map(|mhz| format!("{} MHz", mhz)));
+    fields.add_some("Max bitrate", network.max_bitrate_kbps.map(|kbps| format!("{} Mbit/s", megabits(kbps))));
+    fields.add("Supported rates", rates(&network.elements.supported_rates));
+    fields.section("Radio")
+}
+
+fn session(sightings: &Sightings, now: SystemTime) -> Option<Section> {
+    let age = |time: SystemTime| format_age(now.duration_since(time).unwrap_or_default());
+    let mut fields = Fields::default();
+    fields.add("First seen", age(sightings.first_seen));
+    fields.add("Last seen", age(sightings.last_seen));
+    fields.add("Scans", sightings.scans.to_string());
+    fields.add(
+        "Signal min / avg / max",
+        format!("{} / {:.1} / {} dBm", sightings.min_dbm, sightings.average_dbm(), sightings.max_dbm)
+    );
+    fields.section("This session")
+}
+
+fn security(network: &Network) -> Option<Section> {
+    let mut fields = Fields::default();
+    fields.add("Security", network.security_summary());
+    if network.has_weak_security() {
+        fields.add("Warning", "Weak or no protection against eavesdropping");
+    }
+    for (name, suites) in [("RSN", &network.elements.rsn), ("WPA", &network.elements.wpa)] {
+        if let Some(suites) = suites {
+            suite_fields(&mut fields, name, suites);
+        }
+    }
+    fields.section("Security")
+}
+
+fn suite_fields(fields: &mut Fields, name: &str, suites: &Rsn) {
+    fn join<T: ToString>(items: &[T]) -> String {
+        items.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    }
+
+    fields.add(format!("{} version", name), suites.version.to_string());
+    fields.add(format!("{} key management", name), join(&suites.akm_suites));
+    fields.add(format!("{} pairwise ciphers", name), join(&suites.pairwise_ciphers));
+    fields.add_some(format!("{} group cipher", name), suites.group_cipher.map(|cipher| cipher.to_string()));
+    fields.add_some(
+        format!("{} group management cipher", name),
+        suites.group_management_cipher.map(|cipher| cipher.to_string())
+    );
+    if let Some(capabilities) = suites.capabilities {
+        fields.add(format!("{} capabilities", name), format!("{:#06x}", capabilities));
+        fields.add(format!("{} management frame protection", name), suites.management_frame_protection().to_string());
+    }
+}
+
+fn capabilities(network: &Network) -> Option<Section> {
+    let elements = &network.elements;
+    let mut fields = Fields::default();
+    if let Some(info) = network.capability_info {
+        let names: Vec<_> = CAPABILITY_NAMES
+            .iter()
+            .filter(|(bit, _)| info & bit != 0)
+            .map(|(_, name)| *name)
+            .collect();
+        fields.add("Capability information", format!("{:#06x} ({})", info, names.join(", ")));
+    }
+    if let Some(ht) = elements.ht_capabilities {
+        let mut parts = vec![streams(ht.spatial_streams())];
+        if ht.supports_40mhz() {
+            parts.push(String::from("40 MHz"));
+        }
+        if ht.short_gi_20mhz() || ht.short_gi_40mhz() {
+            parts.push(String::from("short GI"));
+        }
+        fields.add("HT (Wi-Fi 4)", parts.join(", "));
+    }
+    if let Some(vht) = elements.vht_capabilities {
+        let mut parts = vec![streams(vht.spatial_streams())];
+        parts.push(String::from(match vht.supported_channel_width() {
+            0 => "80 MHz",
+            1 => "160 MHz",
+            _ => "160 and 80+80 MHz",
+        }));
+        if vht.short_gi_80mhz() {
+            parts.push(String::from("short GI"));
+        }
+        fields.add("VHT (Wi-Fi 5)", parts.join(", "));
+    }
+    if let Some(he) = elements.he_capabilities {
+        let width = if he.supports_160mhz() { "160 MHz" } else { "80 MHz" };
+        fields.add("HE (Wi-Fi 6)", format!("{}, {}", streams(he.spatial_streams()), width));
+    }
+    if let Some(eht) = elements.eht_capabilities {
+        fields.add("EHT (Wi-Fi 7)", if eht.supports_320mhz() { "320 MHz" } else { "up to 160 MHz" });
+    }
+    if let Some(extended) = &elements.extended_capabilities {
+        let names: Vec<_> = [
+            (EXTENDED_CAPABILITY_BSS_TRANSITION, "BSS transition"),
+            (EXTENDED_CAPABILITY_INTERWORKING, "Interworking"),
+            (EXTENDED_CAPABILITY_UTF8_SSID, "UTF-8 SSID"),
+        ]
+            .iter()
+            .filter(|(bit, _)| extended.has(*bit))
+            .map(|(_, name)| *name)
+            .collect();
+        let hex = hex(&extended.0);
+        fields.add("Extended capabilities", if names.is_empty() { hex } else { format!("{} ({})", hex, names.join(", ")) });
+    }
+    if let Some(rm) = elements.rm_enabled_capabilities {
+        let names: Vec<_> = [
+            (rm.link_measurement(), "link measurement"),
+            (rm.neighbor_report(), "neighbor report"),
+            (rm.beacon_report(), "beacon report"),
+        ]
+            .iter()
+            .filter(|(supported, _)| *supported)
+            .map(|(_, name)| *name)
+            .collect();
+        fields.add("Radio measurement", if names.is_empty() { String::from("none") } else { names.join(", ") });
+    }
+    fields.section("Capabilities")
+}
+
+fn elements(elements: &Elements) -> Option<Section> {
+    let mut fields = Fields::default();
+    fields.add_some("DS channel", elements.ds_channel.map(|channel| channel.to_string()));
+    if let Some(tim) = elements.tim {
+        fields.add("TIM", format!("DTIM period {}, count {}", tim.dtim_period, tim.dtim_count));
+    }
+    if let Some(country) = &elements.country {
+        let value = match &country.environment {
+            Some(environment) => format!("{} ({})", country.code, environment),
+            None => country.code.clone(),
+        };
+        fields.add("Country", value);
+    }
+    if let Some(load) = elements.bss_load {
+        fields.add(
+            "BSS load",
+            format!("{} stations, {}% channel utilization", load.station_count, load.utilization_percent())
+        );
+    }
+    if let Some(domain) = elements.mobility_domain {
+        let over_ds = if domain.ft_over_ds { ", FT over DS" } else { "" };
+        fields.add("Mobility domain", format!("{:#06x}{}", domain.id, over_ds));
+    }
+    if let Some(ht) = elements.ht_operation {
+        let secondary = match ht.secondary_channel_offset {
+            SecondaryChannelOffset::None => "no secondary channel",
+            SecondaryChannelOffset::Above => "secondary above",
+            SecondaryChannelOffset::Below => "secondary below",
+        };
+        fields.add("HT operation", format!("primary channel {}, {}", ht.primary_channel, secondary));
+    }
+    if let Some(vht) = elements.vht_operation {
+        fields.add(
+            "VHT operation",
+            format!("width {}, center segments {} and {}", vht.channel_width, vht.center_segment0, vht.center_segment1)
+        );
+    }
+    if let Some(he) = elements.he_operation {
+        let mut parts = vec![];
+        if let Some(color) = he.bss_color {
+            parts.push(format!("BSS color {}", color));
+        }
+        if let Some(six_ghz) = he.six_ghz {
+            parts.push(format!("6 GHz primary channel {}, width {}", six_ghz.primary_channel, six_ghz.channel_width));
+        }
+        fields.add("HE operation", parts.join(", "));
+    }
+    if let Some(eht) = elements.eht_operation {
+        let mut parts = vec![];
+        if let Some(width) = eht.channel_width {
+            parts.push(format!("width {}", width));
+        }
+        if let Some(disabled) = eht.disabled_subchannels {
+            parts.push(format!("punctured subchannels {:#06x}", disabled));
+        }
+        fields.add("EHT operation", parts.join(", "));
+    }
+    if let Some(wps) = &elements.wps {
+        let mut state = vec![];
+        if let Some(version) = wps.version {
+            state.push(format!("version {}.{}", version >> 4, version & 0x0f));
+        }
+        match wps.configured {
+            Some(true) => state.push(String::from("configured")),
+            Some(false) => state.push(String::from("not configured")),
+            None => {}
+        }
+        if wps.ap_setup_locked == Some(true) {
+            state.push(String::from("setup locked"));
+        }
+        fields.add("WPS", state.join(", "));
+        fields.add_some("WPS device name", wps.device_name.as_deref());
+        fields.add_some("WPS manufacturer", wps.manufacturer.as_deref());
+        let model = [&wps.model_name, &wps.model_number]
+            .iter()
+            .filter_map(|part| part.as_deref())
+            .collect::<Vec<_>>()
+            .join(" ");
+        fields.add("WPS model", model);
+        fields.add_some("WPS serial number", wps.serial_number.as_deref());
+    }
+    let vendor_elements: Vec<_> = elements.vendor_specific
+        .iter()
+        .map(|element| format!("{}: {}", hex(&element.oui).replace(' ', ":"), hex(&element.data)))
+        .collect();
+    fields.add("Vendor specific", vendor_elements.join("\n"));
+    fields.section("Information elements")
+}
+
+fn raw(network: &Network) -> Option<Section> {
+    let mut fields = Fields::default();
+    fields.add_some("Backend output", network.raw.as_deref());
+    fields.section("Raw")
+}
+
+/// Rates in Mbit/s, with basic rates marked `*`.
+fn rates(rates: &[Rate]) -> String {
+    if rates.is_empty() {
+        return String::new();
+    }
+    let rates: Vec<_> = rates
+        .iter()
+        .map(|rate| format!("{}{}", megabits(rate.kbps()), if rate.basic { "*" } else { "" }))
+        .collect();
+    format!("{} Mbit/s", rates.join(", "))
+}
+
+/// A rate in Mbit/s, with a decimal only where there is one: `5.5`, `54`.
+fn megabits(kbps: u32) -> String {
+    if kbps.is_multiple_of(1000) {
+        (kbps / 1000).to_string()
+    } else {
+        format!("{:.1}", kbps as f32 / 1000.0)
+    }
+}
+
+fn streams(count: u8) -> String {
+    match count {
+        1 => String::from("1 stream"),
+        count => format!("{} streams", count),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::history::History;
+    use crate::ie::{ Akm, Cipher };
+    use crate::network::Security;
+
+    fn field<'a>(sections: &'a [Section], label: &str) -> Option<&'a str> {
+        sections
+            .iter()
+            .flat_map(|section| &section.fields)
+            .find(|field| field.label == label)
+            .map(|field| field.value.as_str())
+    }
+
+    #[test]
+    fn lists_known_attributes() {
+        let mut network = Network::with_signal("Office", "00:11:22:33:44:55", -60);
+        network.capability_info = Some(0x0411);
+        network.elements.supported_rates = vec![
+            Rate { half_mbps: 2, basic: true },
+            Rate { half_mbps: 11, basic: true },
+            Rate { half_mbps: 108, basic: false },
+        ];
+        network.elements.rsn = Some(Rsn {
+            version: 1,
+            group_cipher: Some(Cipher::Ccmp128),
+            pairwise_ciphers: vec![Cipher::Ccmp128],
+            akm_suites: vec![Akm::Psk],
+            capabilities: Some(0x0080),
+            group_management_cipher: None,
+        });
+        network.security = Security::Wpa2Psk;
+        network.raw = Some(String::from("BSS 00:11:22:33:44:55(on wlan0)"));
+
+        let sections = sections(&network, None, network.last_seen);
+        let titles: Vec<_> = sections.iter().map(|section| section.title).collect();
+        assert_eq!(titles, ["Network", "Radio", "Security", "Capabilities", "Raw"]);
+        assert_eq!(field(&sections, "SSID bytes"), Some("4f 66 66 69 63 65"));
+        assert_eq!(field(&sections, "Address type"), Some("Universally administered"));
+        assert_eq!(field(&sections, "Supported rates"), Some("1*, 5.5*, 54 Mbit/s"));
+        assert_eq!(field(&sections, "Capability information"), Some("0x0411 (ESS, Privacy, Short slot time)"));
+        assert_eq!(field(&sections, "RSN management frame protection"), Some("Optional"));
+        assert_eq!(field(&sections, "Backend output"), Some("BSS 00:11:22:33:44:55(on wlan0)"));
+        assert_eq!(field(&sections, "Registered vendor"), None);
+    }
+
+    #[test]
+    fn includes_session_statistics() {
+        let network = Network::with_signal("Office", "02:11:22:33:44:55", -60);
+        let mut later = Network::with_signal("Office", "02:11:22:33:44:55", -70);
+        later.last_seen = network.last_seen + Duration::from_secs(10);
+        let mut history = History::default();
+        history.record([&network]);
+        history.record([&later]);
+
+        let sections = sections(&network, history.get(network.bssid), network.last_seen);
+        assert_eq!(field(&sections, "Scans"), Some("2"));
+        assert_eq!(field(&sections, "Signal min / avg / max"), Some("-70 / -65.0 / -60 dBm"));
+        assert_eq!(field(&sections, "Address type"), Some("Locally administered"));
+    }
+}
@@ -0,0 +1,126 @@
+//! Grouping of BSSIDs into ESSs, the set of APs sharing one SSID.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use crate::network::{ Band, Network, Ssid };
+use crate::scan_state::format_age;
+use crate::table::Column;
+
+/// The BSSIDs seen for one SSID. Hidden networks that were not decloaked
+/// have no SSID to group by, so each forms a group of its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ess<'a> {
+    pub networks: Vec<&'a Network>,
+}
+
+impl<'a> Ess<'a> {
+    pub fn ssid(&self) -> &'a Ssid {
+        &self.networks[0].ssid
+    }
+
+    /// The BSSID with the strongest signal.
+    pub fn best(&self) -> &'a Network {
+        self.networks
+            .iter()
+            .copied()
+            .max_by_key(|network| network.effective_signal_dbm())
+            .expect("an ESS has at least one BSSID")
+    }
+
+    /// The bands the ESS is seen on, lowest first.
+    pub fn bands(&self) -> Vec<Band> {
+        let mut bands: Vec<_> = self.networks.iter().filter_map(|network| network.band).collect();
+        bands.sort();
+        bands.dedup();
+        bands
+    }
+
+    /// The cell contents of the group row. Signal and security are those of
+    /// the best BSSID.
+    pub fn text(&self, column: Column, now: SystemTime) -> String {
+        match column {
+            Column::Bssid => match self.networks.len() {
+                1 => String::from("1 AP"),
+                count => format!("{} APs", count),
+            },
+            Column::Band => self.bands()
+                .iter()
+                .map(Band::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            Column::Channel | Column::Width | Column::Vendor => String::new(),
+            Column::LastSeen => {
+                let last_seen = self.networks
+                    .iter()
+                    .map(|network| network.last_seen)
+                    .max()
+                    .expect("an ESS has at least one BSSID");
+                format_age(now.duration_since(last_seen).unwrap_or_default())
+            }
+            Column::Ssid | Column::Signal | Column::Security => column.text(self.best(), now),
+        }
+    }
+}
+
+/// Groups `networks` by SSID. Groups are ordered by their first member and
+/// keep the order of `networks` within, so an already sorted list yields
+/// sorted groups.
+pub fn group_by_ssid<'a>(networks: impl IntoIterator<Item = &'a Network>) -> Vec<Ess<'a>> {
+    let mut groups: Vec<Ess<'a>> = vec![];
+    let mut index_by_ssid: HashMap<&[u8], usize> = HashMap::new();
+    for network in networks {
+        if network.ssid.hidden().is_some() {
+            groups.push(Ess { networks: vec![network] });
+            continue;
+        }
+        match index_by_ssid.get(network.ssid.as_bytes()) {
+            Some(&index) => groups[index].networks.push(network),
+            None => {
+                index_by_ssid.insert(network.ssid.as_bytes(), groups.len());
+                groups.push(Ess { networks: vec![network] });
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(ssid: &str, last_octet: u8, dbm: i32, band: Band) -> Network {
+        let mut network = Network::with_signal(ssid, &format!("AA:BB:CC:DD:EE:{:02X}", last_octet), dbm);
+        network.band = Some(band);
+        network
+    }
+
+    #[test]
+    fn groups_bssids_by_ssid_in_order() {
+        let networks = [
+            network("Office", 1, -50, Band::Band5GHz),
+            network("Cafe", 2, -60, Band::Band2_4GHz),
+            network("", 3, -65, Band::Band5GHz),
+            network("Office", 4, -70, Band::Band2_4GHz),
+            network("", 5, -75, Band::Band5GHz),
+            network("Office", 6, -80, Band::Band5GHz),
+        ];
+        let groups = group_by_ssid(&networks);
+        let sizes: Vec<_> = groups.iter().map(|ess| (ess.ssid().to_string(), ess.networks.len())).collect();
+        assert_eq!(sizes, [
+            (String::from("Office"), 3),
+            (String::from("Cafe"), 1),
+            (String::new(), 1),
+            (String::new(), 1),
+        ]);
+
+        let office = &groups[0];
+        assert_eq!(office.best().bssid, networks[0].bssid);
+        assert_eq!(office.bands(), [Band::Band2_4GHz, Band::Band5GHz]);
+        let now = SystemTime::now();
+        assert_eq!(office.text(Column::Bssid, now), "3 APs");
+        assert_eq!(office.text(Column::Band, now), "2.4 GHz, 5 GHz");
+        assert_eq!(office.text(Column::Signal, now), "84% (-50 dBm)");
+        assert_eq!(groups[1].text(Column::Bssid, now), "1 AP");
+    }
+}
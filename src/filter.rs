@@ -0,0 +1,362 @@
+//! The query language of the filter bar.
+//!
+//! A query is a list of whitespace-separated terms, all of which a network
+//! must match:
+//!
+//! - `text` matches the SSID or BSSID, case-insensitively; SSIDs also match
+//!   what they look like, so `home` finds `Hоme` with a Cyrillic `о`, and
+//!   BSSIDs also match without separators, e.g. `aabbcc`
+//! - `band:2.4`, `band:5`, `band:6`, `band:60`
+//! - `sec:open`, `owe`, `wep`, `wpa`, `wpa2`, `wpa3`, `enterprise`,
+//!   `suite-b` or `weak`
+//! - `signal>-70`, also with `>=`, `<` and `<=`, in dBm, or in percent with a
+//!   `%` suffix, e.g. `signal>=50%`
+//! - `vendor:text` matches the vendor name
+//! - `is:hidden` matches networks that do not broadcast their SSID, and
+//!   `is:decloaked` those of them whose SSID a probe response revealed
+//! - `is:local` matches locally administered, often randomized, BSSIDs
+//!
+//! Values containing spaces can be quoted: `vendor:"Acme Networks"`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::network::{ Band, Network, Security };
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFilterError(String);
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseFilterError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityFilter {
+    Open,
+    Owe,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3,
+    Enterprise,
+    SuiteB,
+    /// Anything [`Network::has_weak_security`] flags.
+    Weak,
+}
+
+impl SecurityFilter {
+    fn matches(self, network: &Network) -> bool {
+        match self {
+            SecurityFilter::Open => network.security == Security::Open,
+            SecurityFilter::Owe => network.security == Security::Owe,
+            SecurityFilter::Wep => network.security == Security::Wep,
+            SecurityFilter::Wpa => network.security == Security::WpaPsk,
+            SecurityFilter::Wpa2 => matches!(network.security, Security::Wpa2Psk | Security::Wpa2Wpa3),
+            SecurityFilter::Wpa3 => matches!(network.security, Security::Wpa3Sae | Security::Wpa2Wpa3),
+            SecurityFilter::Enterprise => matches!(network.security, Security::Enterprise | Security::EnterpriseSuiteB),
+            SecurityFilter::SuiteB => network.security == Security::EnterpriseSuiteB,
+            SecurityFilter::Weak => network.has_weak_security(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl Comparison {
+    fn holds(self, value: i32, bound: i32) -> bool {
+        match self {
+            Comparison::Greater => value > bound,
+            Comparison::GreaterOrEqual => value >= bound,
+            Comparison::Less => value < bound,
+            Comparison::LessOrEqual => value <= bound,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalBound {
+    Dbm(i32),
+    Percent(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    /// Lowercased text to find in the SSID or BSSID.
+    Text(String),
+    Band(Band),
+    Security(SecurityFilter),
+    Signal(Comparison, SignalBound),
+    /// Lowercased text to find in the vendor name.
+    Vendor(String),
+    Hidden,
+    Decloaked,
+    LocallyAdministered,
+}
+
+impl Term {
+    fn matches(&self, network: &Network) -> bool {
+        match self {
+            Term::Text(text) => {
+                let bssid = network.bssid.to_string().to_lowercase();
+                network.ssid.display().to_lowercase().contains(text)
+                    || network.ssid.skeleton().to_lowercase().contains(text)
+                    || bssid.contains(text)
+                    || bssid.replace(':', "").contains(&text.replace([':', '-'], ""))
+            }
+            Term::Band(band) => network.band == Some(*band),
+            Term::Security(security) => security.matches(network),
+            Term::Signal(comparison, SignalBound::Dbm(dbm)) => comparison.holds(network.effective_signal_dbm(), *dbm),
+            Term::Signal(comparison, SignalBound::Percent(percent)) => {
+                comparison.holds(i32::from(network.signal_percent), i32::from(*percent))
+            }
+            Term::Vendor(text) => network.vendor().is_some_and(|vendor| vendor.to_lowercase().contains(text)),
+            Term::Hidden => network.hidden_ssid.is_some(),
+            Term::Decloaked => network.is_decloaked(),
+            Term::LocallyAdministered => network.bssid.is_locally_administered(),
+        }
+    }
+}
+
+impl FromStr for Term {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = s.strip_prefix("signal")
+            && rest.starts_with(['<', '>', '='])
+        {
+            return parse_signal(rest).ok_or_else(|| {
+                ParseFilterError(format!("invalid signal filter {:?}, expected e.g. signal>-70 or signal>=50%", s))
+            });
+        }
+        let Some((key, value)) = s.split_once(':') else {
+            return Ok(Term::Text(s.to_lowercase()));
+        };
+        let value_lower = value.to_lowercase();
+        match key {
+            "band" => {
+                let band = match value_lower.trim_end_matches("ghz") {
+                    "2.4" | "2" => Band::Band2_4GHz,
+                    "5" => Band::Band5GHz,
+                    "6" => Band::Band6GHz,
+                    "60" => Band::Band60GHz,
+                    _ => return Err(ParseFilterError(format!("unknown band {:?}, expected 2.4, 5, 6 or 60", value))),
+                };
+                Ok(Term::Band(band))
+            }
+            "sec" => {
+                let security = match value_lower.as_str() {
+                    "open" => SecurityFilter::Open,
+                    "owe" => SecurityFilter::Owe,
+                    "wep" => SecurityFilter::Wep,
+                    "wpa" => SecurityFilter::Wpa,
+                    "wpa2" => SecurityFilter::Wpa2,
+                    "wpa3" => SecurityFilter::Wpa3,
+                    "enterprise" | "eap" => SecurityFilter::Enterprise,
+                    "suite-b" => SecurityFilter::SuiteB,
+                    "weak" => SecurityFilter::Weak,
+                    _ => return Err(ParseFilterError(format!("unknown security type {:?}", value))),
+                };
+                Ok(Term::Security(security))
+            }
+            "vendor" => Ok(Term::Vendor(value_lower)),
+            "is" if value_lower == "hidden" => Ok(Term::Hidden),
+            "is" if value_lower == "decloaked" => Ok(Term::Decloaked),
+            "is" if value_lower == "local" => Ok(Term::LocallyAdministered),
+            "is" => Err(ParseFilterError(format!(
+                "unknown filter is:{}, expected is:hidden, is:decloaked or is:local",
+                value
+            ))),
+            // Not a known key, so look for the text as is; BSSIDs contain
+            // colons too.
+            _ => Ok(Term::Text(s.to_lowercase())),
+        }
+    }
+}
+
+/// Parses the part of a signal term after `signal`, e.g. `>=-70`.
+fn parse_signal(s: &str) -> Option<Term> {
+    let (comparison, value) = if let Some(value) = s.strip_prefix(">=") {
+        (Comparison::GreaterOrEqual, value)
+    } else if let Some(value) = s.strip_prefix("<=") {
+        (Comparison::LessOrEqual, value)
+    } else if let Some(value) = s.strip_prefix('>') {
+        (Comparison::Greater, value)
+    } else {
+        (Comparison::Less, s.strip_prefix('<')?)
+    };
+    let bound = match value.strip_suffix('%') {
+        Some(percent) => SignalBound::Percent(percent.parse().ok().filter(|percent| *percent <= 100)?),
+        None => SignalBound::Dbm(value.trim_end_matches("dBm").trim_end_matches("dbm").parse().ok()?),
+    };
+    Some(Term::Signal(comparison, bound))
+}
+
+/// Splits a query at whitespace outside double quotes, dropping the quotes.
+fn tokenize(query: &str) -> Result<Vec<String>, ParseFilterError> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        return Err(ParseFilterError(String::from("unterminated quote")));
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A parsed query. The empty query matches every network.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    terms: Vec<Term>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, network: &Network) -> bool {
+        self.terms.iter().all(|term| term.matches(network))
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = tokenize(s)?
+            .iter()
+            .map(|token| token.parse())
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Ssid;
+
+    fn network(ssid: &str, bssid: &str, dbm: i32, band: Band, security: Security) -> Network {
+        let mut network = Network::with_signal(ssid, bssid, dbm);
+        network.band = Some(band);
+        network.security = security;
+        network
+    }
+
+    fn networks() -> Vec<Network> {
+        vec![
+            network("HomeNet", "AA:BB:CC:DD:EE:01", -48, Band::Band5GHz, Security::Wpa3Sae),
+            network("Cafe", "AA:BB:CC:DD:EE:02", -75, Band::Band2_4GHz, Security::Open),
+            network("Office", "11:22:33:44:55:66", -62, Band::Band5GHz, Security::Wpa2Wpa3),
+            network("", "11:22:33:44:55:77", -80, Band::Band6GHz, Security::Wpa3Sae),
+            network("Legacy", "AA:BB:CC:00:00:01", -70, Band::Band2_4GHz, Security::Wep),
+        ]
+    }
+
+    fn matching(query: &str) -> Vec<String> {
+        let filter: Filter = query.parse().unwrap();
+        networks()
+            .iter()
+            .filter(|network| filter.matches(network))
+            .map(|network| network.bssid.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parses_terms() {
+        assert_eq!(
+            "band:5 sec:WPA3 signal>-70 vendor:\"Acme Networks\" is:hidden home".parse(),
+            Ok(Filter {
+                terms: vec![
+                    Term::Band(Band::Band5GHz),
+                    Term::Security(SecurityFilter::Wpa3),
+                    Term::Signal(Comparison::Greater, SignalBound::Dbm(-70)),
+                    Term::Vendor(String::from("acme networks")),
+                    Term::Hidden,
+                    Term::Text(String::from("home")),
+                ],
+            })
+        );
+        assert_eq!("  ".parse::<Filter>(), Ok(Filter::default()));
+        assert_eq!(
+            "signal>=50% signal<=-60dBm".parse::<Filter>().unwrap().terms,
+            [
+                Term::Signal(Comparison::GreaterOrEqual, SignalBound::Percent(50)),
+                Term::Signal(Comparison::LessOrEqual, SignalBound::Dbm(-60)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_terms() {
+        for query in ["band:3", "sec:wpa4", "signal=-70", "signal>", "signal>150%", "is:open", "vendor:\"Acme"] {
+            assert!(query.parse::<Filter>().is_err(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn matches_text_against_ssid_and_bssid() {
+        assert_eq!(matching("home"), ["AA:BB:CC:DD:EE:01"]);
+        assert_eq!(matching("ee:02"), ["AA:BB:CC:DD:EE:02"]);
+        assert_eq!(matching("112233"), ["11:22:33:44:55:66", "11:22:33:44:55:77"]);
+        assert_eq!(matching("aa:bb:cc dd"), ["AA:BB:CC:DD:EE:01", "AA:BB:CC:DD:EE:02"]);
+
+        let lookalike = network("Hоme\u{200b}Net", "AA:BB:CC:DD:EE:0B", -60, Band::Band5GHz, Security::Open);
+        assert!("homenet".parse::<Filter>().unwrap().matches(&lookalike));
+    }
+
+    #[test]
+    fn combines_filters() {
+        assert_eq!(matching("band:5 sec:wpa3 signal>-70"), ["AA:BB:CC:DD:EE:01", "11:22:33:44:55:66"]);
+        assert_eq!(matching("band:2.4GHz sec:weak"), ["AA:BB:CC:DD:EE:02", "AA:BB:CC:00:00:01"]);
+        assert_eq!(matching("signal<=-75"), ["AA:BB:CC:DD:EE:02", "11:22:33:44:55:77"]);
+        assert_eq!(matching("is:hidden"), ["11:22:33:44:55:77"]);
+        assert!(matching("vendor:acme").is_empty());
+        assert!(matching("signalnet").is_empty());
+        assert_eq!(matching("is:local sec:wpa3"), ["AA:BB:CC:DD:EE:01"]);
+    }
+
+    #[test]
+    fn matches_registered_vendor() {
+        let mut network = network("Office", "00:11:22:33:44:55", -60, Band::Band5GHz, Security::Wpa2Psk);
+        let filter: Filter = "vendor:acme".parse().unwrap();
+        assert!(!filter.matches(&network));
+        network.registered_vendor = Some(String::from("Acme Networks, Inc."));
+        assert!(filter.matches(&network));
+    }
+
+    #[test]
+    fn matches_hidden_and_decloaked_networks() {
+        let mut decloaked = network("", "AA:BB:CC:DD:EE:09", -60, Band::Band5GHz, Security::Wpa2Psk);
+        decloaked.reveal_ssid(Ssid::from("Backstage"));
+        let hidden = network("", "AA:BB:CC:DD:EE:0A", -60, Band::Band5GHz, Security::Wpa2Psk);
+
+        let filter: Filter = "is:hidden".parse().unwrap();
+        assert!(filter.matches(&decloaked) && filter.matches(&hidden));
+        let filter: Filter = "is:decloaked backstage".parse().unwrap();
+        assert!(filter.matches(&decloaked) && !filter.matches(&hidden));
+    }
+}
@@ -0,0 +1,183 @@
+//! What the application remembers about each BSSID across the scans of a
+//! session.
+
+use std::collections::{ HashMap, VecDeque };
+use std::time::{ Duration, SystemTime };
+
+use crate::network::{ MacAddress, Network };
+
+/// How far back signal samples are kept unless configured otherwise.
+pub const DEFAULT_HISTORY_LENGTH: Duration = Duration::from_secs(10 * 60);
+
+/// How far apart two times may be and still be the same sighting. Backends
+/// that report the age of a sighting give a slightly different time for it
+/// on every read.
+const SAME_SIGHTING_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// The signal of a BSSID at the time a scan saw it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub time: SystemTime,
+    pub dbm: i32,
+}
+
+/// The scans one BSSID showed up in, and the signal it had. The statistics
+/// cover the whole session, the samples only the history length.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sightings {
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    /// Number of scans the BSSID was in.
+    pub scans: u32,
+    pub min_dbm: i32,
+    pub max_dbm: i32,
+    sum_dbm: i64,
+    samples: VecDeque<Sample>,
+}
+
+impl Sightings {
+    fn new(network: &Network) -> Self {
+        let dbm = network.effective_signal_dbm();
+        Self {
+            first_seen: network.last_seen,
+            last_seen: network.last_seen,
+            scans: 1,
+            min_dbm: dbm,
+            max_dbm: dbm,
+            sum_dbm: i64::from(dbm),
+            samples: VecDeque::from([Sample { time: network.last_seen, dbm }]),
+        }
+    }
+
+    fn record(&mut self, network: &Network) {
+        // Backends serving cached results report the same sighting again.
+        if network.last_seen <= self.last_seen + SAME_SIGHTING_TOLERANCE {
+            return;
+        }
+        let dbm = network.effective_signal_dbm();
+        self.last_seen = network.last_seen;
+        self.scans += 1;
+        self.min_dbm = self.min_dbm.min(dbm);
+        self.max_dbm = self.max_dbm.max(dbm);
+        self.sum_dbm += i64::from(dbm);
+        self.samples.push_back(Sample { time: network.last_seen, dbm });
+    }
+
+    pub fn average_dbm(&self) -> f32 {
+        self.sum_dbm as f32 / self.scans as f32
+    }
+
+    /// Signal samples within the history length, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    /// How far back samples are kept.
+    length: Duration,
+    by_bssid: HashMap<MacAddress, Sightings>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LENGTH)
+    }
+}
+
+impl History {
+    pub fn new(length: Duration) -> Self {
+        Self { length, by_bssid: HashMap::new() }
+    }
+
+    pub fn length(&self) -> Duration {
+        self.length
+    }
+
+    /// Adds the results of one completed scan, then drops samples more than
+    /// the history length older than the newest of them.
+    pub fn record<'a>(&mut self, networks: impl IntoIterator<Item = &'a Network>) {
+        let mut newest = None;
+        for network in networks {
+            newest = newest.max(Some(network.last_seen));
+            self.by_bssid
+                .entry(network.bssid)
+                .and_modify(|sightings| sightings.record(network))
+                .or_insert_with(|| Sightings::new(network));
+        }
+        let Some(cutoff) = newest.and_then(|newest| newest.checked_sub(self.length)) else {
+            return;
+        };
+        for sightings in self.by_bssid.values_mut() {
+            while sightings.samples.front().is_some_and(|sample| sample.time < cutoff) {
+                sightings.samples.pop_front();
+            }
+        }
+    }
+
+    pub fn get(&self, bssid: MacAddress) -> Option<&Sightings> {
+        self.by_bssid.get(&bssid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn network(dbm: i32, seen_after: u64) -> Network {
+        let mut network = Network::with_signal("Office", "00:11:22:33:44:55", dbm);
+        network.last_seen = SystemTime::UNIX_EPOCH + Duration::from_secs(seen_after);
+        network
+    }
+
+    #[test]
+    fn accumulates_signal_over_scans() {
+        let mut history = History::default();
+        history.record(&[network(-60, 10)]);
+        history.record(&[network(-50, 20)]);
+        history.record(&[network(-73, 30)]);
+
+        let sightings = history.get("00:11:22:33:44:55".parse().unwrap()).unwrap();
+        assert_eq!(sightings.scans, 3);
+        assert_eq!((sightings.min_dbm, sightings.max_dbm), (-73, -50));
+        assert_eq!(sightings.average_dbm(), -61.0);
+        assert_eq!(sightings.first_seen, SystemTime::UNIX_EPOCH + Duration::from_secs(10));
+        assert_eq!(sightings.last_seen, SystemTime::UNIX_EPOCH + Duration::from_secs(30));
+        assert!(history.get("00:11:22:33:44:66".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn ignores_repeated_sightings() {
+        let mut history = History::default();
+        history.record(&[network(-60, 10)]);
+        // Read again from a cache, with a time computed from its age.
+        let mut repeated = network(-60, 10);
+        repeated.last_seen += Duration::from_millis(700);
+        history.record(&[repeated]);
+
+        let sightings = history.get("00:11:22:33:44:55".parse().unwrap()).unwrap();
+        assert_eq!(sightings.scans, 1);
+        assert_eq!(sightings.last_seen, SystemTime::UNIX_EPOCH + Duration::from_secs(10));
+    }
+
+    #[test]
+    fn keeps_samples_within_history_length() {
+        let mut history = History::new(Duration::from_secs(60));
+        history.record(&[network(-60, 0)]);
+        history.record(&[network(-70, 0)]);
+        history.record(&[network(-55, 30)]);
+        let bssid = "00:11:22:33:44:55".parse().unwrap();
+        let dbm: Vec<_> = history.get(bssid).unwrap().samples().map(|sample| sample.dbm).collect();
+        assert_eq!(dbm, [-60, -55]);
+
+        history.record(&[network(-50, 90)]);
+        let sightings = history.get(bssid).unwrap();
+        let times: Vec<_> = sightings.samples().map(|sample| sample.time).collect();
+        assert_eq!(times, [SystemTime::UNIX_EPOCH + Duration::from_secs(30), SystemTime::UNIX_EPOCH + Duration::from_secs(90)]);
+        assert_eq!(sightings.scans, 3);
+        assert_eq!(sightings.min_dbm, -60);
+        assert_eq!(sightings.average_dbm(), -55.0);
+    }
+}
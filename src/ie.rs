@@ -0,0 +1,561 @@
+//! Typed IEEE 802.11 information elements.
+//!
+//! Backends fill these from whatever representation they get: already
+//! decoded text (iw) or raw element bytes, which [`Elements::decode`]
+//! turns into these types.
+
+mod decode;
+
+use std::fmt;
+
+/// OUI used by RSN suite selectors.
+pub const IEEE_OUI: [u8; 3] = [0x00, 0x0f, 0xac];
+/// Microsoft OUI, used by the pre-RSN WPA vendor element.
+pub const MICROSOFT_OUI: [u8; 3] = [0x00, 0x50, 0xf2];
+
+pub const ELEMENT_SSID: u8 = 0;
+pub const ELEMENT_SUPPORTED_RATES: u8 = 1;
+pub const ELEMENT_DS_PARAMETER_SET: u8 = 3;
+pub const ELEMENT_TIM: u8 = 5;
+pub const ELEMENT_COUNTRY: u8 = 7;
+pub const ELEMENT_BSS_LOAD: u8 = 11;
+pub const ELEMENT_HT_CAPABILITIES: u8 = 45;
+pub const ELEMENT_RSN: u8 = 48;
+pub const ELEMENT_EXTENDED_SUPPORTED_RATES: u8 = 50;
+pub const ELEMENT_MOBILITY_DOMAIN: u8 = 54;
+pub const ELEMENT_HT_OPERATION: u8 = 61;
+pub const ELEMENT_RM_ENABLED_CAPABILITIES: u8 = 70;
+pub const ELEMENT_EXTENDED_CAPABILITIES: u8 = 127;
+pub const ELEMENT_VHT_CAPABILITIES: u8 = 191;
+pub const ELEMENT_VHT_OPERATION: u8 = 192;
+pub const ELEMENT_VENDOR_SPECIFIC: u8 = 221;
+/// Elements whose real ID is the first byte of the body.
+pub const ELEMENT_EXTENSION: u8 = 255;
+
+pub const EXTENSION_HE_CAPABILITIES: u8 = 35;
+pub const EXTENSION_HE_OPERATION: u8 = 36;
+pub const EXTENSION_EHT_OPERATION: u8 = 106;
+pub const EXTENSION_EHT_CAPABILITIES: u8 = 108;
+
+/// Vendor specific element type of the Microsoft OUI that carries WPA.
+pub const VENDOR_TYPE_WPA: u8 = 1;
+/// Vendor specific element type of the Microsoft OUI that carries WPS.
+pub const VENDOR_TYPE_WPS: u8 = 4;
+
+/// Bits of the Capability Information field of beacons and probe responses.
+pub const CAPABILITY_IBSS: u16 = 0x0002;
+pub const CAPABILITY_PRIVACY: u16 = 0x0010;
+
+/// A pairwise, group or group management cipher suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cipher {
+    UseGroup,
+    Wep40,
+    Tkip,
+    Ccmp128,
+    Wep104,
+    BipCmac128,
+    GroupAddressedNotAllowed,
+    Gcmp128,
+    Gcmp256,
+    Ccmp256,
+    BipGmac128,
+    BipGmac256,
+    BipCmac256,
+    Other { oui: [u8; 3], kind: u8 },
+}
+
+impl Cipher {
+    /// Decodes a suite selector. WPA elements use the Microsoft OUI with the
+    /// same suite numbers.
+    pub fn from_selector(oui: [u8; 3], kind: u8) -> Self {
+        if oui != IEEE_OUI && oui != MICROSOFT_OUI {
+            return Cipher::Other { oui, kind };
+        }
+        match kind {
+            0 => Cipher::UseGroup,
+            1 => Cipher::Wep40,
+            2 => Cipher::Tkip,
+            4 => Cipher::Ccmp128,
+            5 => Cipher::Wep104,
+            6 => Cipher::BipCmac128,
+            7 => Cipher::GroupAddressedNotAllowed,
+            8 => Cipher::Gcmp128,
+            9 => Cipher::Gcmp256,
+            10 => Cipher::Ccmp256,
+            11 => Cipher::BipGmac128,
+            12 => Cipher::BipGmac256,
+            13 => Cipher::BipCmac256,
+            _ => Cipher::Other { oui, kind },
+        }
+    }
+}
+
+impl fmt::Display for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cipher::UseGroup => f.write_str("Use group"),
+            Cipher::Wep40 => f.write_str("WEP-40"),
+            Cipher::Tkip => f.write_str("TKIP"),
+            Cipher::Ccmp128 => f.write_str("CCMP"),
+            Cipher::Wep104 => f.write_str("WEP-104"),
+            Cipher::BipCmac128 => f.write_str("BIP-CMAC-128"),
+            Cipher::GroupAddressedNotAllowed => f.write_str("No group"),
+            Cipher::Gcmp128 => f.write_str("GCMP-128"),
+            Cipher::Gcmp256 => f.write_str("GCMP-256"),
+            Cipher::Ccmp256 => f.write_str("CCMP-256"),
+            Cipher::BipGmac128 => f.write_str("BIP-GMAC-128"),
+            Cipher::BipGmac256 => f.write_str("BIP-GMAC-256"),
+            Cipher::BipCmac256 => f.write_str("BIP-CMAC-256"),
+            Cipher::Other { oui, kind } => write!(f, "{:02x}-{:02x}-{:02x}:{}", oui[0], oui[1], oui[2], kind),
+        }
+    }
+}
+
+/// An authentication and key management suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Akm {
+    Ieee8021x,
+    Psk,
+    FtIeee8021x,
+    FtPsk,
+    Ieee8021xSha256,
+    PskSha256,
+    Tdls,
+    Sae,
+    FtSae,
+    SuiteB,
+    SuiteB192,
+    FtIeee8021xSha384,
+    FilsSha256,
+    FilsSha384,
+    FtFilsSha256,
+    FtFilsSha384,
+    Owe,
+    FtPskSha384,
+    PskSha384,
+    Ieee8021xSha384,
+    SaeExtKey,
+    FtSaeExtKey,
+    Other { oui: [u8; 3], kind: u8 },
+}
+
+impl Akm {
+    pub fn from_selector(oui: [u8; 3], kind: u8) -> Self {
+        if oui == MICROSOFT_OUI {
+            return match kind {
+                1 => Akm::Ieee8021x,
+                2 => Akm::Psk,
+                _ => Akm::Other { oui, kind },
+            };
+        }
+        if oui != IEEE_OUI {
+            return Akm::Other { oui, kind };
+        }
+        match kind {
+            1 => Akm::Ieee8021x,
+            2 => Akm::Psk,
+            3 => Akm::FtIeee8021x,
+            4 => Akm::FtPsk,
+            5 => Akm::Ieee8021xSha256,
+            6 => Akm::PskSha256,
+            7 => Akm::Tdls,
+            8 => Akm::Sae,
+            9 => Akm::FtSae,
+            11 => Akm::SuiteB,
+            12 => Akm::SuiteB192,
+            13 => Akm::FtIeee8021xSha384,
+            14 => Akm::FilsSha256,
+            15 => Akm::FilsSha384,
+            16 => Akm::FtFilsSha256,
+            17 => Akm::FtFilsSha384,
+            18 => Akm::Owe,
+            19 => Akm::FtPskSha384,
+            20 => Akm::PskSha384,
+            23 => Akm::Ieee8021xSha384,
+            24 => Akm::SaeExtKey,
+            25 => Akm::FtSaeExtKey,
+            _ => Akm::Other { oui, kind },
+        }
+    }
+}
+
+impl fmt::Display for Akm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Akm::Ieee8021x => f.write_str("802.1X"),
+            Akm::Psk => f.write_str("PSK"),
+            Akm::FtIeee8021x => f.write_str("FT-802.1X"),
+            Akm::FtPsk => f.write_str("FT-PSK"),
+            Akm::Ieee8021xSha256 => f.write_str("802.1X-SHA256"),
+            Akm::PskSha256 => f.write_str("PSK-SHA256"),
+            Akm::Tdls => f.write_str("TDLS"),
+            Akm::Sae => f.write_str("SAE"),
+            Akm::FtSae => f.write_str("FT-SAE"),
+            Akm::SuiteB => f.write_str("802.1X Suite B"),
+            Akm::SuiteB192 => f.write_str("802.1X Suite B 192"),
+            Akm::FtIeee8021xSha384 => f.write_str("FT-802.1X-SHA384"),
+            Akm::FilsSha256 => f.write_str("FILS-SHA256"),
+            Akm::FilsSha384 => f.write_str("FILS-SHA384"),
+            Akm::FtFilsSha256 => f.write_str("FT-FILS-SHA256"),
+            Akm::FtFilsSha384 => f.write_str("FT-FILS-SHA384"),
+            Akm::Owe => f.write_str("OWE"),
+            Akm::FtPskSha384 => f.write_str("FT-PSK-SHA384"),
+            Akm::PskSha384 => f.write_str("PSK-SHA384"),
+            Akm::Ieee8021xSha384 => f.write_str("802.1X-SHA384"),
+            Akm::SaeExtKey => f.write_str("SAE-EXT-KEY"),
+            Akm::FtSaeExtKey => f.write_str("FT-SAE-EXT-KEY"),
+            Akm::Other { oui, kind } => write!(f, "{:02x}-{:02x}-{:02x}:{}", oui[0], oui[1], oui[2], kind),
+        }
+    }
+}
+
+/// Contents of an RSN element, or of a WPA vendor element, which shares the
+/// same layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rsn {
+    pub version: u16,
+    pub group_cipher: Option<Cipher>,
+    pub pairwise_ciphers: Vec<Cipher>,
+    pub akm_suites: Vec<Akm>,
+    /// RSN capabilities field. Absent in WPA elements.
+    pub capabilities: Option<u16>,
+    /// Cipher protecting broadcast management frames, when PMF is in use
+    /// and the element names one other than the BIP-CMAC-128 default.
+    pub group_management_cipher: Option<Cipher>,
+}
+
+/// Bits of the RSN capabilities field.
+pub const RSN_CAPABILITY_MFP_REQUIRED: u16 = 0x0040;
+pub const RSN_CAPABILITY_MFP_CAPABLE: u16 = 0x0080;
+
+/// Protected Management Frames (802.11w) support advertised in an RSN
+/// element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManagementFrameProtection {
+    Disabled,
+    Optional,
+    Required,
+}
+
+impl fmt::Display for ManagementFrameProtection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ManagementFrameProtection::Disabled => "Disabled",
+            ManagementFrameProtection::Optional => "Optional",
+            ManagementFrameProtection::Required => "Required",
+        })
+    }
+}
+
+impl Rsn {
+    pub fn management_frame_protection(&self) -> ManagementFrameProtection {
+        let capabilities = self.capabilities.unwrap_or(0);
+        if capabilities & RSN_CAPABILITY_MFP_REQUIRED != 0 {
+            ManagementFrameProtection::Required
+        } else if capabilities & RSN_CAPABILITY_MFP_CAPABLE != 0 {
+            ManagementFrameProtection::Optional
+        } else {
+            ManagementFrameProtection::Disabled
+        }
+    }
+}
+
+/// A supported rate from the Supported Rates or Extended Supported Rates
+/// elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    /// Rate in units of 500 kbit/s.
+    pub half_mbps: u8,
+    /// Part of the BSS basic rate set.
+    pub basic: bool,
+}
+
+impl Rate {
+    pub fn kbps(&self) -> u32 {
+        u32::from(self.half_mbps) * 500
+    }
+}
+
+/// The Traffic Indication Map element of a beacon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tim {
+    pub dtim_count: u8,
+    /// Beacons between DTIMs.
+    pub dtim_period: u8,
+    /// Group addressed frames are buffered at the AP.
+    pub multicast_buffered: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BssLoad {
+    pub station_count: u16,
+    /// Share of time the AP sensed the medium busy, scaled to 0..=255.
+    pub channel_utilization: u8,
+    /// Remaining admission control capacity, in units of 32 µs per second.
+    pub available_admission_capacity: u16,
+}
+
+impl BssLoad {
+    pub fn utilization_percent(&self) -> u8 {
+        (u32::from(self.channel_utilization) * 100 / 255) as u8
+    }
+}
+
+/// Fast BSS transition (802.11r) domain of the AP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MobilityDomain {
+    pub id: u16,
+    pub ft_over_ds: bool,
+    pub resource_request: bool,
+}
+
+/// Number of spatial streams a VHT or HE MCS map supports: the highest
+/// stream whose 2-bit field is not 3 (not supported).
+fn mcs_map_streams(map: u16) -> u8 {
+    (0..8)
+        .rev()
+        .find(|stream| (map >> (stream * 2)) & 0b11 != 0b11)
+        .map_or(0, |stream| stream as u8 + 1)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HtCapabilities {
+    pub info: u16,
+    /// First four bytes of the receive MCS bitmask, one per spatial stream.
+    pub rx_mcs: [u8; 4],
+}
+
+impl HtCapabilities {
+    pub fn supports_40mhz(&self) -> bool {
+        self.info & 0x0002 != 0
+    }
+
+    pub fn short_gi_20mhz(&self) -> bool {
+        self.info & 0x0020 != 0
+    }
+
+    pub fn short_gi_40mhz(&self) -> bool {
+        self.info & 0x0040 != 0
+    }
+
+    pub fn spatial_streams(&self) -> u8 {
+        self.rx_mcs
+            .iter()
+            .rposition(|mcs| *mcs != 0)
+            .map_or(0, |index| index as u8 + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhtCapabilities {
+    pub info: u32,
+    pub rx_mcs_map: u16,
+    pub tx_mcs_map: u16,
+}
+
+impl VhtCapabilities {
+    /// 0 for 80 MHz only, 1 for 160 MHz, 2 for 160 and 80+80 MHz.
+    pub fn supported_channel_width(&self) -> u8 {
+        ((self.info >> 2) & 0b11) as u8
+    }
+
+    pub fn short_gi_80mhz(&self) -> bool {
+        self.info & 0x0020 != 0
+    }
+
+    pub fn spatial_streams(&self) -> u8 {
+        mcs_map_streams(self.rx_mcs_map)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeCapabilities {
+    pub mac: [u8; 6],
+    pub phy: [u8; 11],
+    /// Receive HE-MCS map for channels up to 80 MHz.
+    pub rx_mcs_map_80: u16,
+}
+
+impl HeCapabilities {
+    pub fn supports_160mhz(&self) -> bool {
+        self.phy[0] & 0x08 != 0
+    }
+
+    pub fn spatial_streams(&self) -> u8 {
+        mcs_map_streams(self.rx_mcs_map_80)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EhtCapabilities {
+    pub mac: u16,
+    pub phy: [u8; 9],
+}
+
+impl EhtCapabilities {
+    pub fn supports_320mhz(&self) -> bool {
+        self.phy[0] & 0x02 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondaryChannelOffset {
+    None,
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HtOperation {
+    pub primary_channel: u8,
+    pub secondary_channel_offset: SecondaryChannelOffset,
+    /// The STA may use any channel width the capabilities allow, rather than
+    /// only 20 MHz.
+    pub any_channel_width: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhtOperation {
+    /// 0 for 20/40 MHz, 1 for 80/160/80+80 MHz, 2 and 3 for the deprecated
+    /// 160 and 80+80 encodings.
+    pub channel_width: u8,
+    pub center_segment0: u8,
+    pub center_segment1: u8,
+}
+
+/// The 6 GHz Operation Information carried inside an HE Operation element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SixGhzOperation {
+    pub primary_channel: u8,
+    /// 0 = 20 MHz, 1 = 40 MHz, 2 = 80 MHz, 3 = 160 or 80+80 MHz.
+    pub channel_width: u8,
+    pub center_segment0: u8,
+    pub center_segment1: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeOperation {
+    pub bss_color: Option<u8>,
+    pub six_ghz: Option<SixGhzOperation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EhtOperation {
+    /// 0 = 20 MHz up to 4 = 320 MHz, when the AP sends EHT operation
+    /// information.
+    pub channel_width: Option<u8>,
+    pub center_segment0: u8,
+    pub center_segment1: u8,
+    /// Punctured 20 MHz subchannels, lowest frequency first.
+    pub disabled_subchannels: Option<u16>,
+}
+
+/// Bit numbers in the Extended Capabilities element.
+pub const EXTENDED_CAPABILITY_BSS_TRANSITION: usize = 19;
+pub const EXTENDED_CAPABILITY_INTERWORKING: usize = 31;
+pub const EXTENDED_CAPABILITY_UTF8_SSID: usize = 48;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedCapabilities(pub Vec<u8>);
+
+impl ExtendedCapabilities {
+    /// Whether capability bit `bit` is set. Bits past the end of the
+    /// element are clear.
+    pub fn has(&self, bit: usize) -> bool {
+        self.0.get(bit / 8).is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+    }
+}
+
+/// Radio measurement (802.11k) capabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RmEnabledCapabilities(pub [u8; 5]);
+
+impl RmEnabledCapabilities {
+    pub fn link_measurement(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    pub fn neighbor_report(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    /// Any of the passive, active or table beacon report modes.
+    pub fn beacon_report(&self) -> bool {
+        self.0[0] & 0x70 != 0
+    }
+}
+
+/// Attributes of a Wi-Fi Protected Setup vendor element.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Wps {
+    /// Version byte, 0x10 for 1.0. WPS 2.0 APs still send 0x10 here.
+    pub version: Option<u8>,
+    /// The AP has been configured, as opposed to running out of the box.
+    pub configured: Option<bool>,
+    /// The AP refuses PIN registration, usually after repeated failures.
+    pub ap_setup_locked: Option<bool>,
+    pub device_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VendorElement {
+    pub oui: [u8; 3],
+    /// The body after the OUI, usually starting with a vendor-defined type.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Country {
+    /// ISO 3166-1 alpha-2 code.
+    pub code: String,
+    /// Third octet of the country string: indoor, outdoor or both.
+    pub environment: Option<String>,
+}
+
+/// The elements of a beacon or probe response that the scanner understands.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Elements {
+    /// Raw SSID bytes, which may be all zeroes for hidden networks.
+    pub ssid: Option<Vec<u8>>,
+    /// Supported and extended supported rates together.
+    pub supported_rates: Vec<Rate>,
+    pub ds_channel: Option<u8>,
+    pub tim: Option<Tim>,
+    pub country: Option<Country>,
+    pub bss_load: Option<BssLoad>,
+    pub rsn: Option<Rsn>,
+    pub wpa: Option<Rsn>,
+    pub mobility_domain: Option<MobilityDomain>,
+    pub ht_capabilities: Option<HtCapabilities>,
+    pub ht_operation: Option<HtOperation>,
+    pub vht_capabilities: Option<VhtCapabilities>,
+    pub vht_operation: Option<VhtOperation>,
+    pub he_capabilities: Option<HeCapabilities>,
+    pub he_operation: Option<HeOperation>,
+    pub eht_capabilities: Option<EhtCapabilities>,
+    pub eht_operation: Option<EhtOperation>,
+    pub extended_capabilities: Option<ExtendedCapabilities>,
+    pub rm_enabled_capabilities: Option<RmEnabledCapabilities>,
+    pub wps: Option<Wps>,
+    /// Every vendor specific element, including the WPA and WPS ones
+    /// decoded above.
+    pub vendor_specific: Vec<VendorElement>,
+}
+
+/// Iterates over the `(id, body)` pairs of raw element bytes, as found in
+/// beacons and probe responses. Stops at the first truncated element.
+pub fn raw_elements(bytes: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut rest = bytes;
+    std::iter::from_fn(move || {
+        let (&id, after_id) = rest.split_first()?;
+        let (&len, after_len) = after_id.split_first()?;
+        let body = after_len.get(..usize::from(len))?;
+        rest = &after_len[usize::from(len)..];
+        Some((id, body))
+    })
+}
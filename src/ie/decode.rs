@@ -0,0 +1,545 @@
+//! Decoding of raw element bytes into the typed elements.
+//!
+//! Elements that are too short for their fixed fields are skipped rather
+//! than failing the whole BSS; drivers and APs get these wrong often enough.
+//! Trailing optional fields, such as those at the end of an RSN element,
+//! are decoded as far as they are present.
+
+use super::*;
+
+/// WPS attribute types. Unlike elements, WPS attributes are big-endian.
+const WPS_VERSION: u16 = 0x104a;
+const WPS_STATE: u16 = 0x1044;
+const WPS_AP_SETUP_LOCKED: u16 = 0x1057;
+const WPS_DEVICE_NAME: u16 = 0x1011;
+const WPS_MANUFACTURER: u16 = 0x1021;
+const WPS_MODEL_NAME: u16 = 0x1023;
+const WPS_MODEL_NUMBER: u16 = 0x1024;
+const WPS_SERIAL_NUMBER: u16 = 0x1042;
+/// Value of the WPS state attribute once the AP has been set up.
+const WPS_STATE_CONFIGURED: u8 = 2;
+
+/// Rates with the basic bit set and these values are BSS membership
+/// selectors (HT, VHT, SAE H2E, ...) rather than rates.
+const MEMBERSHIP_SELECTORS: std::ops::RangeInclusive<u8> = 122..=127;
+
+/// Little-endian cursor over an element body.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N)?.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.array::<1>().map(|[byte]| byte)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    /// A suite selector: an OUI followed by a suite type.
+    fn suite(&mut self) -> Option<([u8; 3], u8)> {
+        let [a, b, c, kind] = self.array()?;
+        Some(([a, b, c], kind))
+    }
+
+    /// A 16-bit count followed by that many suite selectors.
+    fn suites(&mut self) -> Option<Vec<([u8; 3], u8)>> {
+        let count = self.u16()?;
+        (0..count).map(|_| self.suite()).collect()
+    }
+}
+
+impl Elements {
+    /// Decodes the elements of a beacon or probe response body.
+    pub fn decode(bytes: &[u8]) -> Self {
+        let mut elements = Elements::default();
+        for (id, body) in raw_elements(bytes) {
+            match id {
+                ELEMENT_SSID => elements.ssid = Some(body.to_vec()),
+                ELEMENT_SUPPORTED_RATES | ELEMENT_EXTENDED_SUPPORTED_RATES => {
+                    elements.supported_rates.extend(decode_rates(body));
+                }
+                ELEMENT_DS_PARAMETER_SET => elements.ds_channel = Reader(body).u8(),
+                ELEMENT_TIM => elements.tim = decode_tim(body),
+                ELEMENT_COUNTRY => elements.country = decode_country(body),
+                ELEMENT_BSS_LOAD => elements.bss_load = decode_bss_load(body),
+                ELEMENT_RSN => elements.rsn = decode_rsn(body),
+                ELEMENT_MOBILITY_DOMAIN => elements.mobility_domain = decode_mobility_domain(body),
+                ELEMENT_HT_CAPABILITIES => elements.ht_capabilities = decode_ht_capabilities(body),
+                ELEMENT_HT_OPERATION => elements.ht_operation = decode_ht_operation(body),
+                ELEMENT_VHT_CAPABILITIES => elements.vht_capabilities = decode_vht_capabilities(body),
+                ELEMENT_VHT_OPERATION => elements.vht_operation = decode_vht_operation(body),
+                ELEMENT_EXTENDED_CAPABILITIES => {
+                    elements.extended_capabilities = Some(ExtendedCapabilities(body.to_vec()));
+                }
+                ELEMENT_RM_ENABLED_CAPABILITIES => {
+                    elements.rm_enabled_capabilities = Reader(body).array().map(RmEnabledCapabilities);
+                }
+                ELEMENT_VENDOR_SPECIFIC => decode_vendor_specific(body, &mut elements),
+                ELEMENT_EXTENSION => {
+                    let Some((&extension, body)) = body.split_first() else {
+                        continue;
+                    };
+                    match extension {
+                        EXTENSION_HE_CAPABILITIES => elements.he_capabilities = decode_he_capabilities(body),
+                        EXTENSION_HE_OPERATION => elements.he_operation = decode_he_operation(body),
+                        EXTENSION_EHT_CAPABILITIES => elements.eht_capabilities = decode_eht_capabilities(body),
+                        EXTENSION_EHT_OPERATION => elements.eht_operation = decode_eht_operation(body),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        elements
+    }
+}
+
+fn decode_rates(body: &[u8]) -> impl Iterator<Item = Rate> + '_ {
+    body.iter()
+        .map(|byte| Rate { half_mbps: byte & 0x7f, basic: byte & 0x80 != 0 })
+        .filter(|rate| !(rate.basic && MEMBERSHIP_SELECTORS.contains(&rate.half_mbps)))
+}
+
+fn decode_tim(body: &[u8]) -> Option<Tim> {
+    let [dtim_count, dtim_period, bitmap_control] = Reader(body).array()?;
+    Some(Tim { dtim_count, dtim_period, multicast_buffered: bitmap_control & 0x01 != 0 })
+}
+
+/// Decodes the country string. The channel triplets that follow it are
+/// left out.
+fn decode_country(body: &[u8]) -> Option<Country> {
+    let [a, b, environment] = Reader(body).array()?;
+    if !a.is_ascii_alphabetic() || !b.is_ascii_alphabetic() {
+        return None;
+    }
+    // Spelled like iw, so both backends show the same text.
+    let environment = match environment {
+        b'I' => Some("Indoor only"),
+        b'O' => Some("Outdoor only"),
+        b' ' => Some("Indoor/Outdoor"),
+        _ => None,
+    };
+    Some(Country {
+        code: String::from_utf8_lossy(&[a, b]).into_owned(),
+        environment: environment.map(String::from),
+    })
+}
+
+fn decode_bss_load(body: &[u8]) -> Option<BssLoad> {
+    let mut reader = Reader(body);
+    Some(BssLoad {
+        station_count: reader.u16()?,
+        channel_utilization: reader.u8()?,
+        available_admission_capacity: reader.u16()?,
+    })
+}
+
+/// Decodes the body of an RSN element, or of a WPA element after its OUI
+/// and type. Everything after the version is optional.
+fn decode_rsn(body: &[u8]) -> Option<Rsn> {
+    let mut reader = Reader(body);
+    let mut rsn = Rsn {
+        version: reader.u16()?,
+        group_cipher: None,
+        pairwise_ciphers: Vec::new(),
+        akm_suites: Vec::new(),
+        capabilities: None,
+        group_management_cipher: None,
+    };
+    decode_rsn_fields(&mut reader, &mut rsn);
+    Some(rsn)
+}
+
+/// Fills in the optional fields of `rsn` until the body runs out.
+fn decode_rsn_fields(reader: &mut Reader<'_>, rsn: &mut Rsn) -> Option<()> {
+    rsn.group_cipher = reader.suite().map(|(oui, kind)| Cipher::from_selector(oui, kind));
+    rsn.pairwise_ciphers = reader.suites()?
+        .into_iter()
+        .map(|(oui, kind)| Cipher::from_selector(oui, kind))
+        .collect();
+    rsn.akm_suites = reader.suites()?
+        .into_iter()
+        .map(|(oui, kind)| Akm::from_selector(oui, kind))
+        .collect();
+    rsn.capabilities = Some(reader.u16()?);
+    let pmkid_count = reader.u16()?;
+    reader.bytes(usize::from(pmkid_count) * 16)?;
+    rsn.group_management_cipher = reader.suite().map(|(oui, kind)| Cipher::from_selector(oui, kind));
+    Some(())
+}
+
+fn decode_mobility_domain(body: &[u8]) -> Option<MobilityDomain> {
+    let mut reader = Reader(body);
+    let id = reader.u16()?;
+    let policy = reader.u8()?;
+    Some(MobilityDomain { id, ft_over_ds: policy & 0x01 != 0, resource_request: policy & 0x02 != 0 })
+}
+
+fn decode_ht_capabilities(body: &[u8]) -> Option<HtCapabilities> {
+    let mut reader = Reader(body);
+    let info = reader.u16()?;
+    let _ampdu_parameters = reader.u8()?;
+    Some(HtCapabilities { info, rx_mcs: reader.array()? })
+}
+
+fn decode_ht_operation(body: &[u8]) -> Option<HtOperation> {
+    let [primary_channel, info] = Reader(body).array()?;
+    let secondary_channel_offset = match info & 0b11 {
+        1 => SecondaryChannelOffset::Above,
+        3 => SecondaryChannelOffset::Below,
+        _ => SecondaryChannelOffset::None,
+    };
+    Some(HtOperation { primary_channel, secondary_channel_offset, any_channel_width: info & 0x04 != 0 })
+}
+
+fn decode_vht_capabilities(body: &[u8]) -> Option<VhtCapabilities> {
+    let mut reader = Reader(body);
+    let info = reader.u32()?;
+    let rx_mcs_map = reader.u16()?;
+    let _rx_highest_rate = reader.u16()?;
+    Some(VhtCapabilities { info, rx_mcs_map, tx_mcs_map: reader.u16()? })
+}
+
+fn decode_vht_operation(body: &[u8]) -> Option<VhtOperation> {
+    let [channel_width, center_segment0, center_segment1] = Reader(body).array()?;
+    Some(VhtOperation { channel_width, center_segment0, center_segment1 })
+}
+
+fn decode_he_capabilities(body: &[u8]) -> Option<HeCapabilities> {
+    let mut reader = Reader(body);
+    Some(HeCapabilities { mac: reader.array()?, phy: reader.array()?, rx_mcs_map_80: reader.u16()? })
+}
+
+fn decode_he_operation(body: &[u8]) -> Option<HeOperation> {
+    const VHT_OPERATION_PRESENT: u32 = 1 << 14;
+    const CO_HOSTED_BSS: u32 = 1 << 15;
+    const SIX_GHZ_OPERATION_PRESENT: u32 = 1 << 17;
+    const BSS_COLOR_DISABLED: u8 = 0x80;
+
+    let mut reader = Reader(body);
+    let [a, b, c] = reader.array()?;
+    let parameters = u32::from_le_bytes([a, b, c, 0]);
+    let color = reader.u8()?;
+    let _basic_mcs = reader.u16()?;
+    if parameters & VHT_OPERATION_PRESENT != 0 {
+        reader.bytes(3)?;
+    }
+    if parameters & CO_HOSTED_BSS != 0 {
+        reader.bytes(1)?;
+    }
+    let six_ghz = if parameters & SIX_GHZ_OPERATION_PRESENT != 0 {
+        let [primary_channel, control, center_segment0, center_segment1, _minimum_rate] = reader.array()?;
+        Some(SixGhzOperation { primary_channel, channel_width: control & 0b11, center_segment0, center_segment1 })
+    } else {
+        None
+    };
+    Some(HeOperation {
+        bss_color: (color & BSS_COLOR_DISABLED == 0).then_some(color & 0x3f),
+        six_ghz,
+    })
+}
+
+fn decode_eht_capabilities(body: &[u8]) -> Option<EhtCapabilities> {
+    let mut reader = Reader(body);
+    Some(EhtCapabilities { mac: reader.u16()?, phy: reader.array()? })
+}
+
+fn decode_eht_operation(body: &[u8]) -> Option<EhtOperation> {
+    const INFORMATION_PRESENT: u8 = 0x01;
+    const DISABLED_SUBCHANNELS_PRESENT: u8 = 0x02;
+
+    let mut reader = Reader(body);
+    let parameters = reader.u8()?;
+    let _basic_mcs = reader.u32()?;
+    let mut operation = EhtOperation {
+        channel_width: None,
+        center_segment0: 0,
+        center_segment1: 0,
+        disabled_subchannels: None,
+    };
+    if parameters & INFORMATION_PRESENT != 0 {
+        let [control, center_segment0, center_segment1] = reader.array()?;
+        operation.channel_width = Some(control & 0b111);
+        operation.center_segment0 = center_segment0;
+        operation.center_segment1 = center_segment1;
+        if parameters & DISABLED_SUBCHANNELS_PRESENT != 0 {
+            operation.disabled_subchannels = Some(reader.u16()?);
+        }
+    }
+    Some(operation)
+}
+
+fn decode_vendor_specific(body: &[u8], elements: &mut Elements) {
+    let Some(oui) = body.get(..3).and_then(|oui| <[u8; 3]>::try_from(oui).ok()) else {
+        return;
+    };
+    let data = &body[3..];
+    if oui == MICROSOFT_OUI {
+        match data.split_first() {
+            Some((&VENDOR_TYPE_WPA, wpa)) => elements.wpa = decode_rsn(wpa),
+            Some((&VENDOR_TYPE_WPS, wps)) => elements.wps = Some(decode_wps(wps)),
+            _ => {}
+        }
+    }
+    elements.vendor_specific.push(VendorElement { oui, data: data.to_vec() });
+}
+
+/// Decodes WPS attributes, stopping at the first truncated one.
+fn decode_wps(mut body: &[u8]) -> Wps {
+    let mut wps = Wps::default();
+    while let [t0, t1, l0, l1, rest @ ..] = body {
+        let len = usize::from(u16::from_be_bytes([*l0, *l1]));
+        let Some(value) = rest.get(..len) else {
+            break;
+        };
+        body = &rest[len..];
+
+        let text = || Some(String::from_utf8_lossy(value).trim_end_matches('\0').trim().to_string());
+        match u16::from_be_bytes([*t0, *t1]) {
+            WPS_VERSION => wps.version = value.first().copied(),
+            WPS_STATE => wps.configured = value.first().map(|state| *state == WPS_STATE_CONFIGURED),
+            WPS_AP_SETUP_LOCKED => wps.ap_setup_locked = value.first().map(|locked| *locked != 0),
+            WPS_DEVICE_NAME => wps.device_name = text(),
+            WPS_MANUFACTURER => wps.manufacturer = text(),
+            WPS_MODEL_NAME => wps.model_name = text(),
+            WPS_MODEL_NUMBER => wps.model_number = text(),
+            WPS_SERIAL_NUMBER => wps.serial_number = text(),
+            _ => {}
+        }
+    }
+    wps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds raw element bytes from `(id, body)` pairs.
+    fn elements(list: &[(u8, &[u8])]) -> Vec<u8> {
+        list.iter()
+            .flat_map(|(id, body)| [&[*id, body.len() as u8][..], body].concat())
+            .collect()
+    }
+
+    const RSN_SAE_PMF: &[u8] = &[
+        0x01, 0x00, // version 1
+        0x00, 0x0f, 0xac, 0x04, // group: CCMP
+        0x01, 0x00, 0x00, 0x0f, 0xac, 0x04, // pairwise: CCMP
+        0x02, 0x00, 0x00, 0x0f, 0xac, 0x02, 0x00, 0x0f, 0xac, 0x08, // AKMs: PSK, SAE
+        0x80, 0x00, // capabilities: MFP capable
+        0x00, 0x00, // no PMKIDs
+        0x00, 0x0f, 0xac, 0x06, // group management: BIP-CMAC-128
+    ];
+
+    #[test]
+    fn decodes_basic_elements() {
+        let bytes = elements(&[
+            (ELEMENT_SSID, b"HomeNet"),
+            (ELEMENT_SUPPORTED_RATES, &[0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24]),
+            (ELEMENT_DS_PARAMETER_SET, &[6]),
+            (ELEMENT_TIM, &[0, 3, 1, 0]),
+            (ELEMENT_COUNTRY, b"DE \x01\x0d\x14"),
+            (ELEMENT_BSS_LOAD, &[4, 0, 128, 0x10, 0x27]),
+            (ELEMENT_EXTENDED_SUPPORTED_RATES, &[0x30, 0x48, 0x60, 0x6c, 0xfa]),
+        ]);
+        let decoded = Elements::decode(&bytes);
+        assert_eq!(decoded.ssid.as_deref(), Some(&b"HomeNet"[..]));
+        assert_eq!(decoded.ds_channel, Some(6));
+        // The SAE H2E membership selector (0xfa) is not a rate.
+        assert_eq!(decoded.supported_rates.len(), 12);
+        assert_eq!(decoded.supported_rates[0], Rate { half_mbps: 2, basic: true });
+        assert_eq!(decoded.supported_rates[11].kbps(), 54_000);
+        assert_eq!(decoded.tim, Some(Tim { dtim_count: 0, dtim_period: 3, multicast_buffered: true }));
+        assert_eq!(decoded.country, Some(Country { code: String::from("DE"), environment: Some(String::from("Indoor/Outdoor")) }));
+        let load = decoded.bss_load.unwrap();
+        assert_eq!(load.station_count, 4);
+        assert_eq!(load.utilization_percent(), 50);
+        assert_eq!(load.available_admission_capacity, 10_000);
+    }
+
+    #[test]
+    fn decodes_rsn_with_pmf() {
+        let decoded = Elements::decode(&elements(&[(ELEMENT_RSN, RSN_SAE_PMF)]));
+        let rsn = decoded.rsn.unwrap();
+        assert_eq!(rsn.version, 1);
+        assert_eq!(rsn.group_cipher, Some(Cipher::Ccmp128));
+        assert_eq!(rsn.pairwise_ciphers, [Cipher::Ccmp128]);
+        assert_eq!(rsn.akm_suites, [Akm::Psk, Akm::Sae]);
+        assert_eq!(rsn.management_frame_protection(), ManagementFrameProtection::Optional);
+        assert_eq!(rsn.group_management_cipher, Some(Cipher::BipCmac128));
+    }
+
+    #[test]
+    fn decodes_truncated_rsn_as_far_as_present() {
+        let rsn = decode_rsn(&RSN_SAE_PMF[..14]).unwrap();
+        assert_eq!(rsn.pairwise_ciphers, [Cipher::Ccmp128]);
+        assert!(rsn.akm_suites.is_empty());
+        assert_eq!(rsn.capabilities, None);
+        assert_eq!(rsn.management_frame_protection(), ManagementFrameProtection::Disabled);
+        assert!(decode_rsn(&[0x01]).is_none());
+    }
+
+    #[test]
+    fn decodes_wpa_and_wps_vendor_elements() {
+        let wpa: &[u8] = &[
+            0x00, 0x50, 0xf2, 0x01, 0x01, 0x00,
+            0x00, 0x50, 0xf2, 0x02,
+            0x01, 0x00, 0x00, 0x50, 0xf2, 0x02,
+            0x01, 0x00, 0x00, 0x50, 0xf2, 0x02,
+        ];
+        let wps: &[u8] = &[
+            0x00, 0x50, 0xf2, 0x04,
+            0x10, 0x4a, 0x00, 0x01, 0x10,
+            0x10, 0x44, 0x00, 0x01, 0x02,
+            0x10, 0x57, 0x00, 0x01, 0x01,
+            0x10, 0x21, 0x00, 0x07, b'N', b'E', b'T', b'G', b'E', b'A', b'R',
+            0x10, 0x11, 0x00, 0x06, b'R', b'7', b'0', b'0', b'0', 0x00,
+            0x10, 0x23, 0x00, 0x10, b'c', b'u', b't',
+        ];
+        let decoded = Elements::decode(&elements(&[
+            (ELEMENT_VENDOR_SPECIFIC, wpa),
+            (ELEMENT_VENDOR_SPECIFIC, wps),
+            (ELEMENT_VENDOR_SPECIFIC, &[0x00, 0x10, 0x18, 0x02, 0x00]),
+        ]));
+
+        let wpa = decoded.wpa.unwrap();
+        assert_eq!(wpa.group_cipher, Some(Cipher::Tkip));
+        assert_eq!(wpa.akm_suites, [Akm::Psk]);
+        assert_eq!(decoded.wps, Some(Wps {
+            version: Some(0x10),
+            configured: Some(true),
+            ap_setup_locked: Some(true),
+            device_name: Some(String::from("R7000")),
+            manufacturer: Some(String::from("NETGEAR")),
+            ..Wps::default()
+        }));
+        let ouis: Vec<_> = decoded.vendor_specific
+            .iter()
+            .map(|vendor| vendor.oui)
+            .collect();
+        assert_eq!(ouis, [MICROSOFT_OUI, MICROSOFT_OUI, [0x00, 0x10, 0x18]]);
+    }
+
+    #[test]
+    fn decodes_ht_and_vht() {
+        let mut ht_capabilities = vec![0x6e, 0x00, 0x17];
+        ht_capabilities.extend([0xff, 0xff, 0x00, 0x00]);
+        ht_capabilities.resize(26, 0);
+        let mut ht_operation = vec![36, 0x05];
+        ht_operation.resize(22, 0);
+        let vht_capabilities = [0xb2, 0x01, 0x80, 0x33, 0xfa, 0xff, 0x00, 0x00, 0xfa, 0xff, 0x00, 0x00];
+        let decoded = Elements::decode(&elements(&[
+            (ELEMENT_HT_CAPABILITIES, &ht_capabilities),
+            (ELEMENT_HT_OPERATION, &ht_operation),
+            (ELEMENT_VHT_CAPABILITIES, &vht_capabilities),
+            (ELEMENT_VHT_OPERATION, &[1, 42, 0, 0xfc, 0xff]),
+        ]));
+
+        let ht = decoded.ht_capabilities.unwrap();
+        assert!(ht.supports_40mhz());
+        assert!(ht.short_gi_20mhz() && ht.short_gi_40mhz());
+        assert_eq!(ht.spatial_streams(), 2);
+        assert_eq!(decoded.ht_operation, Some(HtOperation {
+            primary_channel: 36,
+            secondary_channel_offset: SecondaryChannelOffset::Above,
+            any_channel_width: true,
+        }));
+        let vht = decoded.vht_capabilities.unwrap();
+        assert_eq!(vht.supported_channel_width(), 0);
+        assert!(vht.short_gi_80mhz());
+        assert_eq!(vht.spatial_streams(), 2);
+        assert_eq!(decoded.vht_operation, Some(VhtOperation { channel_width: 1, center_segment0: 42, center_segment1: 0 }));
+    }
+
+    #[test]
+    fn decodes_he_and_eht_extension_elements() {
+        let mut he_capabilities = vec![EXTENSION_HE_CAPABILITIES];
+        he_capabilities.extend([0; 6]);
+        he_capabilities.extend([0x0c, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        he_capabilities.extend([0xfe, 0xff]);
+        let he_operation = [
+            EXTENSION_HE_OPERATION,
+            0x00, 0x00, 0x02, // 6 GHz operation information present
+            0x05, // BSS color 5
+            0xfc, 0xff,
+            37, 0x02, 39, 0, 6, // primary 37, 80 MHz around 39
+        ];
+        let eht_operation = [EXTENSION_EHT_OPERATION, 0x03, 0, 0, 0, 0, 0x04, 47, 31, 0x02, 0x00];
+        let mut eht_capabilities = vec![EXTENSION_EHT_CAPABILITIES, 0, 0, 0x02];
+        eht_capabilities.resize(12, 0);
+        let decoded = Elements::decode(&elements(&[
+            (ELEMENT_EXTENSION, &he_capabilities),
+            (ELEMENT_EXTENSION, &he_operation),
+            (ELEMENT_EXTENSION, &eht_operation),
+            (ELEMENT_EXTENSION, &eht_capabilities),
+            (ELEMENT_EXTENSION, &[]),
+        ]));
+
+        let he = decoded.he_capabilities.unwrap();
+        assert!(he.supports_160mhz());
+        assert_eq!(he.spatial_streams(), 1);
+        assert_eq!(decoded.he_operation, Some(HeOperation {
+            bss_color: Some(5),
+            six_ghz: Some(SixGhzOperation { primary_channel: 37, channel_width: 2, center_segment0: 39, center_segment1: 0 }),
+        }));
+        assert_eq!(decoded.eht_operation, Some(EhtOperation {
+            channel_width: Some(4),
+            center_segment0: 47,
+            center_segment1: 31,
+            disabled_subchannels: Some(0x0002),
+        }));
+        assert!(decoded.eht_capabilities.unwrap().supports_320mhz());
+    }
+
+    #[test]
+    fn decodes_capability_elements() {
+        let decoded = Elements::decode(&elements(&[
+            (ELEMENT_MOBILITY_DOMAIN, &[0x34, 0x12, 0x01]),
+            (ELEMENT_RM_ENABLED_CAPABILITIES, &[0x73, 0, 0, 0, 0]),
+            (ELEMENT_EXTENDED_CAPABILITIES, &[0x04, 0x00, 0x08, 0x80, 0x00, 0x00, 0x01]),
+        ]));
+        assert_eq!(decoded.mobility_domain, Some(MobilityDomain { id: 0x1234, ft_over_ds: true, resource_request: false }));
+        let rm = decoded.rm_enabled_capabilities.unwrap();
+        assert!(rm.link_measurement() && rm.neighbor_report() && rm.beacon_report());
+        let extended = decoded.extended_capabilities.unwrap();
+        assert!(extended.has(EXTENDED_CAPABILITY_BSS_TRANSITION));
+        assert!(extended.has(EXTENDED_CAPABILITY_INTERWORKING));
+        assert!(extended.has(EXTENDED_CAPABILITY_UTF8_SSID));
+        assert!(!extended.has(1) && !extended.has(200));
+    }
+
+    #[test]
+    fn skips_malformed_elements() {
+        let decoded = Elements::decode(&elements(&[
+            (ELEMENT_TIM, &[0, 3]),
+            (ELEMENT_BSS_LOAD, &[1, 0]),
+            (ELEMENT_COUNTRY, b"\x00\x00 "),
+            (ELEMENT_HT_OPERATION, &[]),
+            (ELEMENT_VENDOR_SPECIFIC, &[0x00, 0x50]),
+            (ELEMENT_DS_PARAMETER_SET, &[11]),
+        ]));
+        assert_eq!(decoded, Elements { ds_channel: Some(11), ..Elements::default() });
+
+        // A truncated trailing element ends decoding without losing the rest.
+        let mut bytes = elements(&[(ELEMENT_SSID, b"Cafe")]);
+        bytes.extend([ELEMENT_DS_PARAMETER_SET, 1]);
+        assert_eq!(Elements::decode(&bytes).ssid.as_deref(), Some(&b"Cafe"[..]));
+    }
+}
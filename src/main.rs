@@ -1,79 +1,583 @@
-use core::str;
-use std::collections::HashMap;
+mod backend;
+mod channel;
+mod chart;
+mod cli;
+mod detail;
+mod ess;
+mod filter;
+mod history;
+mod ie;
+mod network;
+mod oui;
+mod scan_state;
+mod signal;
+mod ssid;
+mod table;
 
-use iced::widget::{ button, column, container, text, scrollable };
-use iced::{ executor, Application, Command, Element, Settings, Theme };
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{ Duration, Instant, SystemTime };
+
+use iced::widget::canvas::{ self, Canvas, Frame, Geometry, Path, Stroke };
+use iced::widget::{ button, checkbox, column, container, horizontal_space, mouse_area, pick_list, row, text, scrollable, text_input, tooltip, vertical_rule, Row };
+use iced::{ executor, mouse, Application, Command, Element, Event, Length, Point, Rectangle, Renderer, Settings, Subscription, Theme };
 use iced::Color;
-use std::process::Command as ProcessCommand;
-
-fn list_wifi_networks() -> Vec<(String, String, i32)> {
-    let mut networks = Vec::new();
-
-    #[cfg(target_os = "linux")]
-    {
-        let output = ProcessCommand::new("nmcli")
-            .arg("-t")
-            .arg("-f")
-            .arg("SSID,BSSID,SIGNAL")
-            .arg("dev")
-            .arg("wifi")
-            .output()
-            .expect("Failed to execute command");
-
-        if output.status.success() {
-            let wifi_list = String::from_utf8_lossy(&output.stdout);
-            for wifi in wifi_list.lines() {
-                if let Some((mac_address_and_ssid, signal_strength)) = wifi.rsplit_once(":") {
-                    if let Ok(strength) = signal_strength.parse::<i32>() {
-                        let mut parts = mac_address_and_ssid.splitn(2, ":");
-                        if let Some(name) = parts.next() {
-                            if let Some(mac_address) = parts.next() {
-                                if !name.trim().is_empty() && !mac_address.trim().is_empty() {
-                                    hash_map.insert(name.to_string(), (
-                                        mac_address.to_string(),
-                                        strength,
-                                    ));
-                                }
-                            }
-                        }
-                    }
+use iced::futures::StreamExt;
+
+use backend::{ Interface, RescanPolicy, ScanBackend, ScanError, ScanEvent, ScanRequest };
+use chart::TimeWindow;
+use cli::Flags;
+use ess::Ess;
+use filter::{ Filter, ParseFilterError };
+use history::{ History, Sample, Sightings };
+use network::{ MacAddress, Network, Security, Ssid };
+use oui::OuiDatabase;
+use scan_state::{ format_age, ScanState };
+use signal::{ SignalLevel, SignalThresholds };
+use table::{ Column, ColumnWidths, Resize, Sort };
+
+fn signal_color(level: SignalLevel) -> Color {
+    match level {
+        SignalLevel::Excellent => Color::from_rgb8(0, 200, 0),
+        SignalLevel::Good => Color::from_rgb8(120, 200, 0),
+        SignalLevel::Fair => Color::from_rgb8(230, 200, 0),
+        SignalLevel::Weak => Color::from_rgb8(255, 140, 0),
+        SignalLevel::Poor => Color::from_rgb8(139, 0, 0),
+    }
+}
+
+/// One entry per signal level, in its colour, with the dBm range it covers.
+fn signal_legend(thresholds: &SignalThresholds) -> Element<'static, Message> {
+    SignalLevel::ALL
+        .iter()
+        .fold(row![text("Signal:")], |legend, &level| {
+            legend.push(
+                text(format!("■ {} {}", level, thresholds.describe(level)))
+                    .style(iced::theme::Text::Color(signal_color(level)))
+            )
+        })
+        .spacing(10)
+        .into()
+}
+
+/// Weak security is shown in the same red as errors.
+fn warning_color() -> Color {
+    Color::from_rgb8(220, 50, 47)
+}
+
+fn security_icon(network: &Network) -> &'static str {
+    match network.security {
+        Security::Unknown => "?",
+        Security::Open => "🔓",
+        _ if network.has_weak_security() => "⚠",
+        _ => "🔒",
+    }
+}
+
+/// Space taken by the draggable border between two header cells; data
+/// rows leave the same gap so cells line up with their headers.
+const COLUMN_BORDER_WIDTH: f32 = 6.0;
+
+fn table_header(columns: &[Column], sort: &Sort, widths: &ColumnWidths) -> Element<'static, Message> {
+    columns
+        .iter()
+        .fold(Row::new(), |header, &column| {
+            header
+                .push(
+                    button(text(sort.header(column)))
+                        .style(iced::theme::Button::Text)
+                        .width(Length::Fixed(widths.get(column)))
+                        .on_press(Message::SortBy(column))
+                )
+                .push(
+                    mouse_area(
+                        container(vertical_rule(COLUMN_BORDER_WIDTH))
+                            .width(Length::Fixed(COLUMN_BORDER_WIDTH))
+                            .height(Length::Fixed(24.0))
+                    ).on_press(Message::ResizeStarted(column))
+                )
+        })
+        .align_items(iced::Alignment::Center)
+        .into()
+}
+
+/// The SSID, flagged when it contains homoglyphs or invisible characters,
+/// with its bytes in hex and what makes it a lookalike in a tooltip.
+fn ssid_cell(network: &Network, imitated: Option<&Ssid>) -> Element<'static, Message> {
+    let lookalikes = network.ssid.lookalikes();
+    let mut details = vec![format!("Hex: {}", network.ssid.to_hex())];
+    details.extend(lookalikes.iter().map(ToString::to_string));
+    if let Some(imitated) = imitated {
+        details.push(format!("Looks like \"{}\" nearby", imitated));
+    }
+
+    let label = if lookalikes.is_empty() {
+        text(network.ssid_label())
+    } else {
+        text(format!("⚠ {}", network.ssid_label())).style(iced::theme::Text::Color(warning_color()))
+    };
+    tooltip(label, details.join("\n"), tooltip::Position::Bottom)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+/// The signal in its level's colour. dBm values estimated from the
+/// percentage are left out for backends that report no dBm at all.
+fn signal_cell(network: &Network, estimate_dbm: bool, thresholds: &SignalThresholds, now: SystemTime) -> Element<'static, Message> {
+    let label = if estimate_dbm || network.signal_dbm.is_some() {
+        Column::Signal.text(network, now)
+    } else {
+        format!("{}%", network.signal_percent)
+    };
+    text(label)
+        .style(iced::theme::Text::Color(signal_color(thresholds.level(network.effective_signal_dbm()))))
+        .into()
+}
+
+/// A network's row; clicking it opens the detail pane.
+fn table_row(
+    network: &Network,
+    imitated: Option<&Ssid>,
+    selected: bool,
+    columns: &[Column],
+    estimate_dbm: bool,
+    widths: &ColumnWidths,
+    thresholds: &SignalThresholds,
+    now: SystemTime,
+) -> Element<'static, Message> {
+    let row = columns
+        .iter()
+        .fold(Row::new(), |row, &column| {
+            let cell: Element<'static, Message> = match column {
+                Column::Ssid => ssid_cell(network, imitated),
+                Column::Signal => signal_cell(network, estimate_dbm, thresholds, now),
+                Column::Security => security_label(network),
+                _ => text(column.text(network, now)).into(),
+            };
+            row.push(container(cell).width(Length::Fixed(widths.get(column))).padding([0, 5]))
+        })
+        .spacing(COLUMN_BORDER_WIDTH);
+    let row = if selected {
+        container(row).style(iced::theme::Container::Box)
+    } else {
+        container(row)
+    };
+    mouse_area(row).on_press(Message::NetworkSelected(network.bssid)).into()
+}
+
+/// The summary row of an ESS, with a button to show or hide its BSSIDs.
+fn ess_row(
+    ess: &Ess,
+    expanded: bool,
+    columns: &[Column],
+    estimate_dbm: bool,
+    widths: &ColumnWidths,
+    thresholds: &SignalThresholds,
+    now: SystemTime,
+) -> Element<'static, Message> {
+    let best = ess.best();
+    columns
+        .iter()
+        .fold(Row::new(), |row, &column| {
+            let cell: Element<'static, Message> = match column {
+                Column::Ssid => button(text(format!("{} {}", if expanded { "▼" } else { "▶" }, ess.text(column, now))))
+                    .style(iced::theme::Button::Text)
+                    .padding(0)
+                    .on_press(Message::EssToggled(ess.ssid().clone()))
+                    .into(),
+                Column::Signal => signal_cell(best, estimate_dbm, thresholds, now),
+                Column::Security => security_label(best),
+                _ => text(ess.text(column, now)).into(),
+            };
+            row.push(container(cell).width(Length::Fixed(widths.get(column))).padding([0, 5]))
+        })
+        .spacing(COLUMN_BORDER_WIDTH)
+        .into()
+}
+
+const DETAIL_PANE_WIDTH: f32 = 460.0;
+
+/// Every known attribute of `network`, each with a button copying it to the
+/// clipboard. Without `elements_reported`, a note explains why the decoded
+/// information elements are missing.
+fn detail_pane(
+    network: &Network,
+    sightings: Option<&Sightings>,
+    charted: bool,
+    elements_reported: bool,
+    now: SystemTime,
+) -> Element<'static, Message> {
+    let sections = detail::sections(network, sightings, now)
+        .into_iter()
+        .fold(column![].spacing(16), |sections, section| {
+            let fields = section.fields
+                .into_iter()
+                .fold(column![text(section.title).size(20)].spacing(4), |fields, field| {
+                    fields.push(
+                        row![
+                            text(field.label).width(Length::Fixed(150.0)),
+                            text(field.value.clone()).width(Length::Fill),
+                            button("Copy")
+                                .style(iced::theme::Button::Secondary)
+                                .padding([2, 6])
+                                .on_press(Message::CopyToClipboard(field.value)),
+                        ]
+                            .spacing(8)
+                    )
+                });
+            sections.push(fields)
+        });
+    let title = row![
+        text(network.ssid_label()).size(24).width(Length::Fill),
+        button(if charted { "Remove from chart" } else { "Chart signal" }).on_press(Message::ChartToggled(network.bssid)),
+        button("Close").on_press(Message::DetailClosed)
+    ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+
+    let mut pane = column![title].spacing(10);
+    if !elements_reported {
+        pane = pane.push(text("This backend does not pass on information elements; the iw and nl80211 backends do."));
+    }
+    container(pane.push(scrollable(sections).height(Length::Fill)))
+        .width(Length::Fixed(DETAIL_PANE_WIDTH))
+        .height(Length::Fill)
+        .padding(10)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+/// Colour of the `index`th network in the signal chart.
+fn series_color(index: usize) -> Color {
+    match index % 6 {
+        0 => Color::from_rgb8(38, 139, 210),
+        1 => Color::from_rgb8(211, 54, 130),
+        2 => Color::from_rgb8(133, 153, 0),
+        3 => Color::from_rgb8(203, 75, 22),
+        4 => Color::from_rgb8(108, 113, 196),
+        _ => Color::from_rgb8(42, 161, 152),
+    }
+}
+
+/// Space left of the plot for the dBm labels, and below it for the time
+/// labels.
+const CHART_AXIS_WIDTH: f32 = 40.0;
+const CHART_AXIS_HEIGHT: f32 = 20.0;
+
+/// Signal over time of the charted networks, one line each.
+struct SignalChart {
+    series: Vec<(Color, Vec<Sample>)>,
+    window: TimeWindow,
+    now: SystemTime,
+}
+
+impl canvas::Program<Message> for SignalChart {
+    /// Where a drag last moved the cursor to.
+    type State = Option<f32>;
+
+    fn update(
+        &self,
+        drag: &mut Option<f32>,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor
+    ) -> (canvas::event::Status, Option<Message>) {
+        let captured = canvas::event::Status::Captured;
+        match event {
+            canvas::Event::Mouse(mouse::Event::WheelScrolled { delta }) if cursor.is_over(bounds) => {
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y / 40.0,
+                };
+                // Scrolling up zooms in.
+                (captured, Some(Message::ChartZoomed(0.8f32.powf(lines))))
+            }
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) if cursor.is_over(bounds) => {
+                *drag = cursor.position().map(|position| position.x);
+                (captured, None)
+            }
+            canvas::Event::Mouse(mouse::Event::CursorMoved { position }) => match *drag {
+                Some(last_x) => {
+                    *drag = Some(position.x);
+                    // Dragging right brings earlier samples into view.
+                    (captured, Some(Message::ChartPanned((last_x - position.x) / bounds.width)))
                 }
+                None => (canvas::event::Status::Ignored, None),
+            },
+            canvas::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) if drag.is_some() => {
+                *drag = None;
+                (captured, None)
             }
-        } else {
-            eprintln!("Error: {}", str::from_utf8(&output.stderr).unwrap());
+            _ => (canvas::event::Status::Ignored, None),
         }
     }
-    networks
+
+    fn draw(
+        &self,
+        _drag: &Option<f32>,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let text_color = theme.palette().text;
+        let grid = Stroke::default().with_color(Color { a: 0.2, ..text_color }).with_width(1.0);
+        let plot = Rectangle {
+            x: CHART_AXIS_WIDTH,
+            y: 0.0,
+            width: (bounds.width - CHART_AXIS_WIDTH).max(1.0),
+            height: (bounds.height - CHART_AXIS_HEIGHT).max(1.0),
+        };
+        let label = |content: String, position: Point| canvas::Text {
+            content,
+            position,
+            color: text_color,
+            size: 12.0,
+            ..canvas::Text::default()
+        };
+
+        for dbm in (chart::WEAKEST_DBM..=chart::STRONGEST_DBM).step_by(10) {
+            let y = plot.y + chart::y(dbm, plot.height);
+            frame.stroke(&Path::line(Point::new(plot.x, y), Point::new(plot.x + plot.width, y)), grid.clone());
+            frame.fill_text(label(dbm.to_string(), Point::new(0.0, (y - 6.0).max(0.0))));
+        }
+        let (_, end) = self.window.bounds(self.now);
+        let end_age = self.now.duration_since(end).unwrap_or_default();
+        for tick in 0..=4 {
+            let fraction = tick as f32 / 4.0;
+            let x = plot.x + plot.width * fraction;
+            frame.stroke(&Path::line(Point::new(x, plot.y), Point::new(x, plot.y + plot.height)), grid.clone());
+            let age = end_age + self.window.span().mul_f32(1.0 - fraction);
+            frame.fill_text(label(chart::tick_label(age), Point::new((x - 14.0).max(plot.x), plot.y + plot.height + 4.0)));
+        }
+
+        frame.with_clip(plot, |frame| {
+            for (color, samples) in &self.series {
+                let points: Vec<_> = samples
+                    .iter()
+                    .map(|sample| {
+                        Point::new(self.window.x(sample.time, self.now, plot.width), chart::y(sample.dbm, plot.height))
+                    })
+                    .collect();
+                let line = Path::new(|builder| {
+                    if let Some((first, rest)) = points.split_first() {
+                        builder.move_to(*first);
+                        rest.iter().for_each(|point| builder.line_to(*point));
+                    }
+                });
+                frame.stroke(&line, Stroke::default().with_color(*color).with_width(2.0));
+                for point in &points {
+                    frame.fill(&Path::circle(*point, 3.0), *color);
+                }
+            }
+        });
+        vec![frame.into_geometry()]
+    }
+}
+
+/// The chart of the networks in `charted`, under a legend whose entries
+/// take a network off the chart when clicked.
+fn signal_chart(
+    charted: &[MacAddress],
+    networks: &[Network],
+    history: &History,
+    window: TimeWindow,
+    now: SystemTime
+) -> Element<'static, Message> {
+    let legend = charted
+        .iter()
+        .enumerate()
+        .fold(Row::new().spacing(12), |legend, (index, bssid)| {
+            let name = networks
+                .iter()
+                .find(|network| network.bssid == *bssid)
+                .map_or_else(|| bssid.to_string(), |network| format!("{} ({})", network.ssid_label(), bssid));
+            legend.push(
+                button(text(format!("■ {} ✕", name)).style(iced::theme::Text::Color(series_color(index))))
+                    .style(iced::theme::Button::Text)
+                    .padding(0)
+                    .on_press(Message::ChartToggled(*bssid))
+            )
+        });
+    let hint = if window.is_following() {
+        "Scroll to zoom, drag to pan"
+    } else {
+        "Paused; drag to the right edge to follow"
+    };
+    let controls = row![
+        legend,
+        horizontal_space(Length::Fill),
+        text(hint),
+        button("Reset view").on_press(Message::ChartReset)
+    ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+
+    let series = charted
+        .iter()
+        .enumerate()
+        .map(|(index, bssid)| {
+            let samples = history.get(*bssid).map(|sightings| sightings.samples().copied().collect());
+            (series_color(index), samples.unwrap_or_default())
+        })
+        .collect();
+    let chart = Canvas::new(SignalChart { series, window, now })
+        .width(Length::Fill)
+        .height(Length::Fixed(220.0));
+    column![controls, chart].spacing(5).into()
 }
 
-fn signal_color(strength: i32) -> Color {
-    match strength {
-        0..=20 => Color::from_rgb8(139, 0, 0),
-        21..=50 => Color::from_rgb8(255, 165, 0),
-        51..=80 => Color::from_rgb8(255, 255, 0),
-        81..=100 => Color::from_rgb8(0, 255, 0),
-        _ => Color::BLACK,
+/// Cursor moves and the button release of a column border drag.
+fn resize_event(event: Event, _status: iced::event::Status) -> Option<Message> {
+    match event {
+        Event::Mouse(mouse::Event::CursorMoved { position }) => Some(Message::ResizeMoved(position.x)),
+        Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => Some(Message::ResizeEnded),
+        _ => None,
     }
 }
 
+fn security_label(network: &Network) -> Element<'static, Message> {
+    let label = text(format!("{} {}", security_icon(network), network.security_summary()));
+    if network.has_weak_security() {
+        label.style(iced::theme::Text::Color(warning_color())).into()
+    } else {
+        label.into()
+    }
+}
+
+fn error_banner(error: &ScanError) -> Element<'static, Message> {
+    row![
+        text(error.to_string()).style(iced::theme::Text::Color(warning_color())),
+        button("Retry").on_press(Message::Scan)
+    ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center)
+        .into()
+}
+
 pub fn main() -> iced::Result {
-    WirelessScanner::run(Settings::default())
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--update-oui-database") {
+        match cli::update_oui_database(&args[1..]) {
+            Ok(summary) => {
+                println!("{}", summary);
+                return Ok(());
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    let flags = match Flags::from_args(args.into_iter()) {
+        Ok(flags) => flags,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            eprintln!("{}", cli::usage());
+            std::process::exit(2);
+        }
+    };
+    WirelessScanner::run(Settings::with_flags(flags))
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     Scan,
-    ScanResult(Vec<(String, String, i32)>),
+    CancelScan,
+    RescanPolicySelected(RescanPolicy),
+    InterfacesListed(Result<Vec<Interface>, ScanError>),
+    InterfaceSelected(InterfaceChoice),
+    ScanEvent(u64, ScanEvent),
+    Tick(Instant),
+    FilterChanged(String),
+    GroupByEssToggled(bool),
+    EssToggled(Ssid),
+    SortBy(Column),
+    ResizeStarted(Column),
+    ResizeMoved(f32),
+    ResizeEnded,
+    NetworkSelected(MacAddress),
+    DetailClosed,
+    CopyToClipboard(String),
+    ChartToggled(MacAddress),
+    ChartZoomed(f32),
+    ChartPanned(f32),
+    ChartReset,
+}
+
+/// An entry of the interface picker: an interface, or none to leave the
+/// choice to the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InterfaceChoice(Option<Interface>);
+
+impl fmt::Display for InterfaceChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(Interface { name, mac_address: Some(mac_address) }) => write!(f, "{} ({})", name, mac_address),
+            Some(Interface { name, mac_address: None }) => f.write_str(name),
+            None => f.write_str("Default interface"),
+        }
+    }
 }
 
 struct WirelessScanner {
-    networks: Vec<(String, String, i32)>,
-    scanning: bool,
+    backend: Arc<dyn ScanBackend>,
+    request: ScanRequest,
+    /// The interfaces the backend can scan on, once listed.
+    interfaces: Vec<Interface>,
+    thresholds: SignalThresholds,
+    /// Registry the vendor of each BSSID is looked up in.
+    oui: Arc<OuiDatabase>,
+    filter_query: String,
+    /// The last query that parsed; it stays applied while the query being
+    /// typed does not.
+    filter: Filter,
+    filter_error: Option<ParseFilterError>,
+    /// Show one row per SSID, with the BSSIDs of `expanded` ones below it.
+    group_by_ess: bool,
+    expanded: HashSet<Ssid>,
+    /// Sort order and column widths of the table, kept across scans.
+    sort: Sort,
+    column_widths: ColumnWidths,
+    resize: Option<Resize>,
+    /// The network shown in the detail pane.
+    selected: Option<MacAddress>,
+    /// Signal statistics and samples of every BSSID seen in a completed
+    /// scan.
+    history: History,
+    /// Networks in the signal chart, in the order of their colours.
+    charted: Vec<MacAddress>,
+    chart_window: TimeWindow,
+    networks: Vec<Network>,
+    /// Networks streamed in by the scan in flight. They replace `networks`
+    /// once the scan completes and are dropped if it fails or is cancelled.
+    partial: Vec<Network>,
+    /// When the displayed results were at least this fresh, if the backend
+    /// reported it.
+    fresh_since: Option<Instant>,
+    partial_fresh_since: Option<Instant>,
+    scan_state: ScanState,
+    /// Id handed to the next scan, so late results from a cancelled scan
+    /// can be recognised and dropped.
+    next_scan_id: u64,
+    now: Instant,
 }
 
-impl Default for WirelessScanner {
-    fn default() -> Self {
-        Self { networks: vec![], scanning: false }
+impl WirelessScanner {
+    fn is_current_scan(&self, id: u64) -> bool {
+        matches!(self.scan_state, ScanState::Scanning { id: current, .. } if current == id)
+    }
+
+    fn cache_age_status(&self) -> String {
+        match (&self.scan_state, self.fresh_since) {
+            (ScanState::Idle, _) => String::new(),
+            (_, Some(fresh_since)) => {
+                format!("Results from {}", format_age(self.now.saturating_duration_since(fresh_since)))
+            }
+            (_, None) => String::from("Results of unknown age"),
+        }
     }
 }
 
@@ -81,10 +585,37 @@ impl Application for WirelessScanner {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
+    type Flags = Flags;
 
-    fn new(_flags: ()) -> (Self, Command<Self::Message>) {
-        (Self::default(), Command::none())
+    fn new(flags: Flags) -> (Self, Command<Self::Message>) {
+        let list_interfaces = Command::perform(flags.backend.interfaces(), Message::InterfacesListed);
+        let scanner = Self {
+            backend: flags.backend,
+            request: flags.request,
+            interfaces: vec![],
+            thresholds: flags.thresholds,
+            oui: flags.oui,
+            filter_query: String::new(),
+            filter: Filter::default(),
+            filter_error: None,
+            group_by_ess: false,
+            expanded: HashSet::new(),
+            sort: Sort::default(),
+            column_widths: ColumnWidths::default(),
+            resize: None,
+            selected: None,
+            history: History::new(flags.history_length),
+            charted: vec![],
+            chart_window: TimeWindow::new(flags.history_length),
+            networks: vec![],
+            partial: vec![],
+            fresh_since: None,
+            partial_fresh_since: None,
+            scan_state: ScanState::Idle,
+            next_scan_id: 0,
+            now: Instant::now(),
+        };
+        (scanner, list_interfaces)
     }
 
     fn title(&self) -> String {
@@ -94,35 +625,265 @@ impl Application for WirelessScanner {
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
             Message::Scan => {
-                if self.scanning {
-                    return Command::none();
+                self.now = Instant::now();
+                if self.scan_state.start(self.next_scan_id, self.now) {
+                    self.next_scan_id += 1;
+                    self.partial.clear();
+                    self.partial_fresh_since = None;
+                }
+                Command::none()
+            }
+            Message::CancelScan => {
+                self.scan_state.cancel();
+                self.partial.clear();
+                Command::none()
+            }
+            Message::ScanEvent(id, ScanEvent::Network(network)) => {
+                if self.is_current_scan(id) {
+                    let mut network = *network;
+                    network.registered_vendor = self.oui.lookup(network.bssid).map(String::from);
+                    self.partial.push(network);
+                }
+                Command::none()
+            }
+            Message::ScanEvent(id, ScanEvent::CacheAge(age)) => {
+                if self.is_current_scan(id) {
+                    self.partial_fresh_since = Instant::now().checked_sub(age);
+                }
+                Command::none()
+            }
+            Message::ScanEvent(id, ScanEvent::Finished(result)) => {
+                self.now = Instant::now();
+                if self.scan_state.finish(id, &result, self.now) {
+                    let partial = std::mem::take(&mut self.partial);
+                    let partial_fresh_since = self.partial_fresh_since.take();
+                    if result.is_ok() {
+                        self.history.record(&partial);
+                        self.networks = partial;
+                        self.fresh_since = partial_fresh_since;
+                    }
+                }
+                Command::none()
+            }
+            Message::RescanPolicySelected(policy) => {
+                self.request.rescan = policy;
+                Command::none()
+            }
+            Message::InterfacesListed(result) => {
+                // Without a list there is nothing to pick from; scans report
+                // the problem, if it persists.
+                self.interfaces = result.unwrap_or_default();
+                Command::none()
+            }
+            Message::InterfaceSelected(InterfaceChoice(interface)) => {
+                self.request.interface = interface.map(|interface| interface.name);
+                Command::none()
+            }
+            Message::Tick(now) => {
+                self.now = now;
+                Command::none()
+            }
+            Message::FilterChanged(query) => {
+                match query.parse() {
+                    Ok(filter) => {
+                        self.filter = filter;
+                        self.filter_error = None;
+                    }
+                    Err(err) => self.filter_error = Some(err),
+                }
+                self.filter_query = query;
+                Command::none()
+            }
+            Message::GroupByEssToggled(group_by_ess) => {
+                self.group_by_ess = group_by_ess;
+                Command::none()
+            }
+            Message::EssToggled(ssid) => {
+                if !self.expanded.remove(&ssid) {
+                    self.expanded.insert(ssid);
+                }
+                Command::none()
+            }
+            Message::SortBy(column) => {
+                self.sort.toggle(column);
+                Command::none()
+            }
+            Message::ResizeStarted(column) => {
+                self.resize = Some(Resize::new(column));
+                Command::none()
+            }
+            Message::ResizeMoved(x) => {
+                if let Some(resize) = &mut self.resize {
+                    resize.drag(x, &mut self.column_widths);
                 }
-                self.scanning = true;
-                self.networks = vec![];
-                Command::perform(async { list_wifi_networks() }, Message::ScanResult)
+                Command::none()
+            }
+            Message::ResizeEnded => {
+                self.resize = None;
+                Command::none()
+            }
+            Message::NetworkSelected(bssid) => {
+                self.selected = if self.selected == Some(bssid) { None } else { Some(bssid) };
+                Command::none()
             }
-            Message::ScanResult(results) => {
-                self.networks = results;
+            Message::DetailClosed => {
+                self.selected = None;
+                Command::none()
+            }
+            Message::CopyToClipboard(value) => iced::clipboard::write(value),
+            Message::ChartToggled(bssid) => {
+                match self.charted.iter().position(|charted| *charted == bssid) {
+                    Some(index) => {
+                        self.charted.remove(index);
+                    }
+                    None => self.charted.push(bssid),
+                }
+                Command::none()
+            }
+            Message::ChartZoomed(factor) => {
+                self.chart_window.zoom(factor, self.history.length());
+                Command::none()
+            }
+            Message::ChartPanned(fraction) => {
+                self.chart_window.pan(fraction, SystemTime::now());
+                Command::none()
+            }
+            Message::ChartReset => {
+                self.chart_window = TimeWindow::new(self.history.length());
                 Command::none()
             }
         }
     }
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let resize = if self.resize.is_some() {
+            iced::subscription::events_with(resize_event)
+        } else {
+            Subscription::none()
+        };
+        let scan = match self.scan_state {
+            ScanState::Idle => Subscription::none(),
+            ScanState::Scanning { id, .. } => {
+                // The scan runs for as long as this subscription is returned;
+                // once it is not, iced drops the stream and with it any child
+                // process.
+                let scan = self.backend
+                    .scan(self.request.clone())
+                    .map(move |event| Message::ScanEvent(id, event));
+                Subscription::batch([
+                    iced::subscription::run_with_id(("scan", id), scan),
+                    iced::time::every(Duration::from_millis(100)).map(Message::Tick),
+                ])
+            }
+            ScanState::Completed { .. } | ScanState::Failed { .. } => {
+                iced::time::every(Duration::from_secs(1)).map(Message::Tick)
+            }
+        };
+        Subscription::batch([scan, resize])
+    }
+
     fn view(&self) -> Element<Self::Message> {
-        let scan_button = button("Scan").on_press(Message::Scan);
-
-        let network_list = self.networks
-            .iter()
-            .fold(column![], |col, (ssid, bssid, strength)| {
-                col.push(
-                    text(
-                        format!("SSID: {} | BSSID: {} | Strength: {}%", ssid, bssid, strength)
-                    ).style(iced::theme::Text::Color(signal_color(*strength)))
-                )
+        let capabilities = self.backend.capabilities();
+        let columns = Column::supported(capabilities);
+        let mut scan_controls = if self.scan_state.is_scanning() {
+            row![button(text(self.scan_state.status(self.now))), button("Cancel").on_press(Message::CancelScan)]
+        } else {
+            row![button("Scan").on_press(Message::Scan), text(self.scan_state.status(self.now))]
+        }
+            .spacing(10)
+            .align_items(iced::Alignment::Center);
+        if !self.interfaces.is_empty() {
+            let choices: Vec<_> = std::iter::once(None)
+                .chain(self.interfaces.iter().cloned().map(Some))
+                .map(InterfaceChoice)
+                .collect();
+            // An interface given on the command line may not be listed.
+            let selected = self.request.interface.as_ref().map(|name| {
+                self.interfaces
+                    .iter()
+                    .find(|interface| interface.name == *name)
+                    .cloned()
+                    .unwrap_or_else(|| Interface { name: name.clone(), mac_address: None })
             });
+            let selected = InterfaceChoice(selected);
+            scan_controls = scan_controls.push(pick_list(choices, Some(selected), Message::InterfaceSelected));
+        }
+        // Backends that cannot trigger a scan only ever return cached results.
+        scan_controls = if capabilities.trigger_scan {
+            scan_controls.push(pick_list(&RescanPolicy::ALL[..], Some(self.request.rescan), Message::RescanPolicySelected))
+        } else {
+            scan_controls.push(text("Cached results only"))
+        };
+        let scan_controls = scan_controls.push(text(self.cache_age_status()));
+
+        let networks = if self.scan_state.is_scanning() && !self.partial.is_empty() {
+            &self.partial
+        } else {
+            &self.networks
+        };
+        let now = SystemTime::now();
+        let imitations = ssid::imitations(networks.iter().map(|network| &network.ssid));
+        let shown = self.sort.apply(networks.iter().filter(|network| self.filter.matches(network)));
+
+        let mut filter_bar = row![
+            text_input("Filter, e.g. band:5 sec:wpa3 signal>-70", &self.filter_query)
+                .on_input(Message::FilterChanged)
+                .width(Length::Fixed(400.0)),
+            checkbox("Group by SSID", self.group_by_ess, Message::GroupByEssToggled)
+        ]
+            .spacing(10)
+            .align_items(iced::Alignment::Center);
+        if !self.filter.is_empty() {
+            filter_bar = filter_bar.push(text(format!("{} of {} networks", shown.len(), networks.len())));
+        }
+        if let Some(err) = &self.filter_error {
+            filter_bar = filter_bar.push(text(err.to_string()).style(iced::theme::Text::Color(warning_color())));
+        }
+
+        let network_row = |network: &Network| {
+            let imitated = imitations.get(&network.ssid).copied();
+            let selected = self.selected == Some(network.bssid);
+            table_row(network, imitated, selected, &columns, capabilities.signal_dbm, &self.column_widths, &self.thresholds, now)
+        };
+        let network_list = if self.group_by_ess {
+            ess::group_by_ssid(shown)
+                .iter()
+                .fold(column![], |col, ess| {
+                    let expanded = self.expanded.contains(ess.ssid());
+                    let col = col.push(ess_row(ess, expanded, &columns, capabilities.signal_dbm, &self.column_widths, &self.thresholds, now));
+                    if !expanded {
+                        return col;
+                    }
+                    ess.networks.iter().fold(col, |col, network| col.push(network_row(network)))
+                })
+        } else {
+            shown
+                .into_iter()
+                .fold(column![], |col, network| col.push(network_row(network)))
+        };
 
-        let scrollable_network_list = scrollable(network_list).width(iced::Length::Fill).height(iced::Length::Fill);
+        let scrollable_network_list = scrollable(network_list).width(Length::Fill).height(Length::Fill);
+
+        let mut content = column![scan_controls, signal_legend(&self.thresholds)];
+        if let ScanState::Failed { error, .. } = &self.scan_state {
+            content = content.push(error_banner(error));
+        }
+        let table = column![table_header(&columns, &self.sort, &self.column_widths), scrollable_network_list].width(Length::Fill);
+        let selected = self.selected.and_then(|bssid| networks.iter().find(|network| network.bssid == bssid));
+        let body: Element<Self::Message> = match selected {
+            Some(network) => {
+                let charted = self.charted.contains(&network.bssid);
+                row![table, detail_pane(network, self.history.get(network.bssid), charted, capabilities.information_elements, now)]
+                    .spacing(10)
+                    .into()
+            }
+            None => table.into(),
+        };
+        content = content.push(filter_bar).push(body);
+        if !self.charted.is_empty() {
+            content = content.push(signal_chart(&self.charted, networks, &self.history, self.chart_window, now));
+        }
 
-        container(column![scan_button, scrollable_network_list]).center_x().center_y().into()
+        container(content).center_x().center_y().into()
     }
 }
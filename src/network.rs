@@ -0,0 +1,530 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::channel::{ self, ChannelWidth, Operation };
+use crate::ssid;
+use crate::ie::{ Akm, Cipher, Elements, ManagementFrameProtection, Rsn, CAPABILITY_PRIVACY };
+
+/// An SSID as broadcast by the access point.
+///
+/// SSIDs are arbitrary byte strings of up to 32 bytes, so the raw bytes are
+/// kept alongside the string used for display, in which anything
+/// unprintable is escaped (see [`crate::ssid::escape`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ssid {
+    bytes: Vec<u8>,
+    display: String,
+}
+
+impl Ssid {
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        let bytes = bytes.into();
+        let display = if bytes.iter().all(|byte| *byte == 0) {
+            String::new()
+        } else {
+            ssid::escape(&bytes)
+        };
+        Self { bytes, display }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// How the SSID is hidden, if it is.
+    pub fn hidden(&self) -> Option<HiddenSsid> {
+        match self.bytes.len() {
+            0 => Some(HiddenSsid::ZeroLength),
+            length if self.bytes.iter().all(|byte| *byte == 0) => Some(HiddenSsid::NullPadded { length }),
+            _ => None,
+        }
+    }
+}
+
+/// How an AP hides its SSID in beacons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HiddenSsid {
+    /// An empty SSID element.
+    ZeroLength,
+    /// As many zero bytes as the real SSID is long, which gives away its
+    /// length.
+    NullPadded { length: usize },
+    /// Hidden in a way the backend cannot tell, since it does not pass on
+    /// the SSID element.
+    Unknown,
+}
+
+impl fmt::Display for HiddenSsid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiddenSsid::ZeroLength | HiddenSsid::Unknown => f.write_str("<hidden>"),
+            HiddenSsid::NullPadded { length: 1 } => f.write_str("<hidden, 1 null byte>"),
+            HiddenSsid::NullPadded { length } => write!(f, "<hidden, {} null bytes>", length),
+        }
+    }
+}
+
+impl From<&str> for Ssid {
+    fn from(value: &str) -> Self {
+        Self::from_bytes(value.as_bytes())
+    }
+}
+
+impl fmt::Display for Ssid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.display)
+    }
+}
+
+/// A 48-bit IEEE 802 MAC address, used for BSSIDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddress([u8; 6]);
+
+impl MacAddress {
+    pub const fn new(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+
+    pub const fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMacAddressError(String);
+
+impl fmt::Display for ParseMacAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid MAC address: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseMacAddressError {}
+
+impl FromStr for MacAddress {
+    type Err = ParseMacAddressError;
+
+    /// Parses `aa:bb:cc:dd:ee:ff`, also accepting `-` as the separator.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseMacAddressError(s.to_string());
+        let mut octets = [0u8; 6];
+        let mut parts = s.trim().split([':', '-']);
+        for octet in octets.iter_mut() {
+            let part = parts.next().ok_or_else(err)?;
+            if part.len() != 2 {
+                return Err(err());
+            }
+            *octet = u8::from_str_radix(part, 16).map_err(|_| err())?;
+        }
+        if parts.next().is_some() {
+            return Err(err());
+        }
+        Ok(Self(octets))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}", a, b, c, d, e, g)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Band {
+    Band2_4GHz,
+    Band5GHz,
+    Band6GHz,
+    Band60GHz,
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Band::Band2_4GHz => "2.4 GHz",
+            Band::Band5GHz => "5 GHz",
+            Band::Band6GHz => "6 GHz",
+            Band::Band60GHz => "60 GHz",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Security {
+    #[default]
+    Unknown,
+    Open,
+    /// Opportunistic Wireless Encryption: encrypted, but without a password.
+    Owe,
+    Wep,
+    WpaPsk,
+    Wpa2Psk,
+    Wpa3Sae,
+    /// WPA3 transition mode, accepting both WPA2-PSK and WPA3-SAE.
+    Wpa2Wpa3,
+    /// WPA, WPA2 or WPA3 with 802.1X authentication.
+    Enterprise,
+    /// WPA3-Enterprise with the Suite B (192-bit) AKMs.
+    EnterpriseSuiteB,
+}
+
+impl Security {
+    /// Classifies a BSS from the AKMs of its RSN or WPA element, falling
+    /// back to the privacy capability bit for WEP.
+    pub fn from_elements(capability: Option<u16>, elements: &Elements) -> Self {
+        if let Some(rsn) = &elements.rsn {
+            let akms = &rsn.akm_suites;
+            let has = |wanted: &[Akm]| akms.iter().any(|akm| wanted.contains(akm));
+            let psk = [Akm::Psk, Akm::FtPsk, Akm::PskSha256, Akm::PskSha384, Akm::FtPskSha384];
+            // An element that omits the AKM list defaults to 802.1X.
+            return if akms.is_empty() {
+                Security::Enterprise
+            } else if has(&[Akm::SuiteB, Akm::SuiteB192]) {
+                Security::EnterpriseSuiteB
+            } else if has(&[
+                Akm::Ieee8021x,
+                Akm::FtIeee8021x,
+                Akm::Ieee8021xSha256,
+                Akm::Ieee8021xSha384,
+                Akm::FtIeee8021xSha384,
+                Akm::FilsSha256,
+                Akm::FilsSha384,
+                Akm::FtFilsSha256,
+                Akm::FtFilsSha384,
+            ]) {
+                Security::Enterprise
+            } else if has(&[Akm::Owe]) {
+                Security::Owe
+            } else if has(&[Akm::Sae, Akm::FtSae, Akm::SaeExtKey, Akm::FtSaeExtKey]) {
+                if has(&psk) {
+                    Security::Wpa2Wpa3
+                } else {
+                    Security::Wpa3Sae
+                }
+            } else if has(&psk) {
+                Security::Wpa2Psk
+            } else {
+                Security::Unknown
+            };
+        }
+        if let Some(wpa) = &elements.wpa {
+            if wpa.akm_suites.contains(&Akm::Ieee8021x) {
+                Security::Enterprise
+            } else {
+                Security::WpaPsk
+            }
+        } else if capability.is_some_and(|capability| capability & CAPABILITY_PRIVACY != 0) {
+            Security::Wep
+        } else if capability.is_some() {
+            Security::Open
+        } else {
+            Security::Unknown
+        }
+    }
+
+    /// Modes that give little or no protection against eavesdropping.
+    pub fn is_weak(&self) -> bool {
+        matches!(self, Security::Open | Security::Wep | Security::WpaPsk)
+    }
+}
+
+impl fmt::Display for Security {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Security::Unknown => "Unknown",
+            Security::Open => "Open",
+            Security::Owe => "OWE",
+            Security::Wep => "WEP",
+            Security::WpaPsk => "WPA-PSK",
+            Security::Wpa2Psk => "WPA2-PSK",
+            Security::Wpa3Sae => "WPA3-SAE",
+            Security::Wpa2Wpa3 => "WPA2/WPA3",
+            Security::Enterprise => "Enterprise",
+            Security::EnterpriseSuiteB => "Enterprise (Suite B)",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Mode {
+    #[default]
+    Infrastructure,
+    AdHoc,
+    Mesh,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Infrastructure => "Infra",
+            Mode::AdHoc => "Ad-Hoc",
+            Mode::Mesh => "Mesh",
+        })
+    }
+}
+
+/// A single BSS seen during a scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    /// The SSID, or for a hidden network the one a probe response revealed,
+    /// if any.
+    pub ssid: Ssid,
+    /// How the beacons hide the SSID, for hidden networks.
+    pub hidden_ssid: Option<HiddenSsid>,
+    pub bssid: MacAddress,
+    /// Signal quality in percent, 0..=100.
+    pub signal_percent: u8,
+    /// Received signal strength, when the backend reports it.
+    pub signal_dbm: Option<i32>,
+    pub frequency_mhz: Option<u32>,
+    pub channel: Option<u32>,
+    pub band: Option<Band>,
+    /// Width of the whole operating channel.
+    pub channel_width: Option<ChannelWidth>,
+    /// Center of the whole operating channel, or of the first segment of an
+    /// 80+80 MHz channel.
+    pub center_frequency_mhz: Option<u32>,
+    /// Center of the second segment of an 80+80 MHz channel.
+    pub center_frequency2_mhz: Option<u32>,
+    /// Highest bitrate the AP advertises, in kbit/s.
+    pub max_bitrate_kbps: Option<u32>,
+    pub security: Security,
+    pub mode: Mode,
+    /// A saved profile exists for this network, for backends that can tell.
+    pub known: bool,
+    /// Capability information field of the beacon, when the backend reports
+    /// it.
+    pub capability_info: Option<u16>,
+    /// Decoded information elements, for backends that expose them.
+    pub elements: Elements,
+    /// Organization the BSSID is registered to in the IEEE registry, filled
+    /// in by the application as results arrive.
+    pub registered_vendor: Option<String>,
+    /// What the backend reported for this network: the lines of output it
+    /// was parsed from, or a hex dump for binary replies.
+    pub raw: Option<String>,
+    pub last_seen: SystemTime,
+}
+
+impl Network {
+    pub fn new(ssid: Ssid, bssid: MacAddress, signal_percent: u8) -> Self {
+        Self {
+            hidden_ssid: ssid.hidden(),
+            ssid,
+            bssid,
+            signal_percent: signal_percent.min(100),
+            signal_dbm: None,
+            frequency_mhz: None,
+            channel: None,
+            band: None,
+            channel_width: None,
+            center_frequency_mhz: None,
+            center_frequency2_mhz: None,
+            max_bitrate_kbps: None,
+            security: Security::Unknown,
+            mode: Mode::Infrastructure,
+            known: false,
+            capability_info: None,
+            elements: Elements::default(),
+            registered_vendor: None,
+            raw: None,
+            last_seen: SystemTime::now(),
+        }
+    }
+
+    /// Fills in channel, band, width and center frequencies from the
+    /// primary frequency and the decoded elements. Values the backend set
+    /// itself are kept. Backends call this once the network is complete.
+    pub fn derive_channel(&mut self) {
+        let Some(frequency) = self.frequency_mhz else {
+            self.channel = self.channel
+                .or(self.elements.ds_channel.map(u32::from))
+                .or(self.elements.ht_operation.map(|ht| u32::from(ht.primary_channel)));
+            return;
+        };
+        self.band = self.band.or(Band::from_frequency(frequency));
+        self.channel = self.channel.or(channel::frequency_to_channel(frequency));
+        match Operation::from_elements(&self.elements, frequency) {
+            Some(operation) => {
+                self.channel_width = self.channel_width.or(Some(operation.width));
+                self.center_frequency_mhz = self.center_frequency_mhz.or(Some(operation.center_mhz));
+                self.center_frequency2_mhz = self.center_frequency2_mhz.or(operation.center2_mhz);
+            }
+            None if self.channel_width == Some(ChannelWidth::Mhz20) => {
+                self.center_frequency_mhz = self.center_frequency_mhz.or(Some(frequency));
+            }
+            None => {}
+        }
+    }
+
+    /// Takes the SSID from a probe response, for backends that see those
+    /// apart from beacons. A hidden network answering a probe for its SSID
+    /// gives it away; `hidden_ssid` keeps recording how the beacons hide it.
+    pub fn reveal_ssid(&mut self, ssid: Ssid) {
+        if self.ssid.hidden().is_some() && ssid.hidden().is_none() {
+            self.ssid = ssid;
+        }
+    }
+
+    /// A hidden network whose SSID was revealed by a probe response.
+    pub fn is_decloaked(&self) -> bool {
+        self.hidden_ssid.is_some() && self.ssid.hidden().is_none()
+    }
+
+    /// The SSID for display, with hidden and decloaked networks labelled.
+    pub fn ssid_label(&self) -> String {
+        match self.hidden_ssid {
+            Some(_) if self.is_decloaked() => format!("{} (decloaked)", self.ssid),
+            Some(hidden) => hidden.to_string(),
+            None => self.ssid.to_string(),
+        }
+    }
+
+    /// Signal strength in dBm: as reported by the backend, or estimated from
+    /// the percentage with [`percent_to_dbm`].
+    pub fn effective_signal_dbm(&self) -> i32 {
+        self.signal_dbm.unwrap_or_else(|| percent_to_dbm(self.signal_percent))
+    }
+
+    /// Percentage and dBm, e.g. `87% (-48 dBm)`, with estimated dBm values
+    /// marked `≈`.
+    pub fn signal_summary(&self) -> String {
+        match self.signal_dbm {
+            Some(dbm) => format!("{}% ({} dBm)", self.signal_percent, dbm),
+            None => format!("{}% (≈ {} dBm)", self.signal_percent, percent_to_dbm(self.signal_percent)),
+        }
+    }
+
+    /// The organization the BSSID is registered to, or else the
+    /// manufacturer the AP names in its WPS element.
+    pub fn vendor(&self) -> Option<&str> {
+        self.registered_vendor
+            .as_deref()
+            .or_else(|| self.elements.wps.as_ref()?.manufacturer.as_deref())
+    }
+
+    /// Band, channel and width as far as known, e.g. `5 GHz · ch 36 · 80 MHz`.
+    pub fn channel_summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(band) = self.band {
+            parts.push(band.to_string());
+        }
+        match (self.channel, self.frequency_mhz) {
+            (Some(channel), _) => parts.push(format!("ch {}", channel)),
+            (None, Some(frequency)) => parts.push(format!("{} MHz", frequency)),
+            (None, None) => {}
+        }
+        if let Some(width) = self.channel_width {
+            parts.push(width.to_string());
+        }
+        parts.join(" · ")
+    }
+
+    /// The RSN element, or the WPA element of WPA-only networks, which
+    /// carries the ciphers and PMF status.
+    pub fn security_suites(&self) -> Option<&Rsn> {
+        self.elements.rsn.as_ref().or(self.elements.wpa.as_ref())
+    }
+
+    /// A weak mode, or a network that only offers TKIP or WEP as pairwise
+    /// cipher.
+    pub fn has_weak_security(&self) -> bool {
+        self.security.is_weak() || self.security_suites().is_some_and(|suites| {
+            !suites.pairwise_ciphers.is_empty() && suites.pairwise_ciphers
+                .iter()
+                .all(|cipher| matches!(cipher, Cipher::Tkip | Cipher::Wep40 | Cipher::Wep104))
+        })
+    }
+
+    /// The mode followed by the ciphers and PMF status where known, e.g.
+    /// `WPA2-PSK · CCMP · PMF optional`.
+    pub fn security_summary(&self) -> String {
+        let mut parts = vec![self.security.to_string()];
+        if let Some(suites) = self.security_suites() {
+            if !suites.pairwise_ciphers.is_empty() {
+                let pairwise: Vec<_> = suites.pairwise_ciphers
+                    .iter()
+                    .map(Cipher::to_string)
+                    .collect();
+                parts.push(pairwise.join("/"));
+            }
+            if let Some(group) = suites.group_cipher
+                && suites.pairwise_ciphers != [group]
+            {
+                parts.push(format!("group {}", group));
+            }
+            if suites.capabilities.is_some() {
+                parts.push(String::from(match suites.management_frame_protection() {
+                    ManagementFrameProtection::Disabled => "no PMF",
+                    ManagementFrameProtection::Optional => "PMF optional",
+                    ManagementFrameProtection::Required => "PMF required",
+                }));
+            }
+        }
+        parts.join(" · ")
+    }
+}
+
+#[cfg(test)]
+impl Network {
+    /// A network seen at `dbm`, with the percentage derived from it the way
+    /// backends reporting dBm do.
+    pub fn with_signal(ssid: &str, bssid: &str, dbm: i32) -> Self {
+        let mut network = Self::new(Ssid::from(ssid), bssid.parse().unwrap(), dbm_to_percent(dbm));
+        network.signal_dbm = Some(dbm);
+        network
+    }
+}
+
+/// Converts a signal level in dBm to a 0..=100 quality percentage, the same
+/// way NetworkManager does: -100 dBm or worse is 0 %, -40 dBm or better is
+/// 100 %, linear in between.
+pub fn dbm_to_percent(dbm: i32) -> u8 {
+    let below_ceiling = -40 - dbm.clamp(-100, -40);
+    (100 - below_ceiling * 100 / 60) as u8
+}
+
+/// The inverse of [`dbm_to_percent`], for backends that only report a
+/// percentage. The result is exact to within a dBm, but 0 % and 100 % stand
+/// for "-100 dBm or worse" and "-40 dBm or better".
+pub fn percent_to_dbm(percent: u8) -> i32 {
+    -100 + i32::from(percent.min(100)) * 60 / 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rsn(akm_suites: Vec<Akm>) -> Elements {
+        let rsn = Rsn {
+            version: 1,
+            group_cipher: Some(Cipher::Ccmp128),
+            pairwise_ciphers: vec![Cipher::Ccmp128],
+            akm_suites,
+            capabilities: Some(0),
+            group_management_cipher: None,
+        };
+        Elements { rsn: Some(rsn), ..Elements::default() }
+    }
+
+    #[test]
+    fn classifies_rsn_akms() {
+        let classify = |akms| Security::from_elements(Some(CAPABILITY_PRIVACY), &rsn(akms));
+        assert_eq!(classify(vec![Akm::Psk]), Security::Wpa2Psk);
+        assert_eq!(classify(vec![Akm::FtPsk, Akm::Sae]), Security::Wpa2Wpa3);
+        assert_eq!(classify(vec![Akm::Ieee8021xSha256]), Security::Enterprise);
+        assert_eq!(classify(vec![Akm::from_selector([0x00, 0x0f, 0xac], 23)]), Security::Enterprise);
+        assert_eq!(classify(vec![]), Security::Enterprise);
+        assert_eq!(classify(vec![Akm::from_selector([0x00, 0x0f, 0xac], 99)]), Security::Unknown);
+        assert_eq!(classify(vec![Akm::Other { oui: [0x00, 0x10, 0x18], kind: 1 }]), Security::Unknown);
+    }
+}
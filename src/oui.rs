@@ -0,0 +1,293 @@
+//! MAC vendor lookup against the IEEE registry of address blocks.
+//!
+//! The IEEE assigns blocks of three sizes: an MA-L block fixes the first 24
+//! bits of an address (the OUI), an MA-M block 28 bits and an MA-S block 36
+//! bits. Smaller blocks are carved out of MA-L blocks registered to the IEEE
+//! itself, so a lookup prefers the longest matching block.
+//!
+//! A copy of the registry ships gzip-compressed in the binary, as CSV in the
+//! format the IEEE publishes it in. `--update-oui-database` rebuilds it from
+//! downloaded registry files, and `--oui-database` loads one at startup
+//! instead of the embedded copy.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{ Read, Write };
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::network::MacAddress;
+
+/// The embedded registry, built with `--update-oui-database` from the IEEE's
+/// MA-L, MA-M and MA-S files.
+static EMBEDDED: &[u8] = include_bytes!("../data/oui.csv.gz");
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OuiError(String);
+
+impl fmt::Display for OuiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for OuiError {}
+
+/// The registries of universally administered address blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Registry {
+    MaL,
+    MaM,
+    MaS,
+}
+
+impl Registry {
+    /// Longest blocks first, the order lookups try them in.
+    const ALL: [Registry; 3] = [Registry::MaS, Registry::MaM, Registry::MaL];
+
+    /// Number of leading address bits a block of this registry fixes.
+    fn prefix_bits(self) -> u32 {
+        match self {
+            Registry::MaL => 24,
+            Registry::MaM => 28,
+            Registry::MaS => 36,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "MA-L" => Some(Registry::MaL),
+            "MA-M" => Some(Registry::MaM),
+            "MA-S" => Some(Registry::MaS),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Registry::MaL => "MA-L",
+            Registry::MaM => "MA-M",
+            Registry::MaS => "MA-S",
+        })
+    }
+}
+
+impl MacAddress {
+    /// The U/L bit is set: the address was not assigned by the IEEE but by
+    /// whoever configured it, as with randomized addresses and the extra
+    /// BSSIDs of an AP serving several SSIDs.
+    pub fn is_locally_administered(&self) -> bool {
+        self.octets()[0] & 0x02 != 0
+    }
+
+    fn prefix(&self, bits: u32) -> u64 {
+        let value = self.octets().iter().fold(0u64, |value, octet| value << 8 | u64::from(*octet));
+        value >> (48 - bits)
+    }
+}
+
+/// Organization names by registry and block prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OuiDatabase {
+    blocks: HashMap<(Registry, u64), String>,
+}
+
+impl OuiDatabase {
+    /// The registry embedded at build time.
+    pub fn embedded() -> Self {
+        Self::from_bytes(EMBEDDED).expect("the embedded OUI database is valid")
+    }
+
+    /// Reads a registry CSV file, optionally gzip-compressed.
+    pub fn load(path: &Path) -> Result<Self, OuiError> {
+        let bytes = std::fs::read(path).map_err(|err| OuiError(format!("cannot read {}: {}", path.display(), err)))?;
+        Self::from_bytes(&bytes).map_err(|err| OuiError(format!("{}: {}", path.display(), err)))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, OuiError> {
+        let mut text = String::new();
+        if bytes.starts_with(&GZIP_MAGIC) {
+            GzDecoder::new(bytes)
+                .read_to_string(&mut text)
+                .map_err(|err| OuiError(format!("cannot decompress: {}", err)))?;
+        } else {
+            text = String::from_utf8(bytes.to_vec()).map_err(|_| OuiError(String::from("not UTF-8")))?;
+        }
+        let mut database = Self::default();
+        database.add_csv(&text)?;
+        Ok(database)
+    }
+
+    /// Adds the rows of a registry CSV file: `Registry,Assignment,Organization
+    /// Name`, optionally followed by more columns. Header rows and registries
+    /// of other kinds of identifiers, such as CIDs, are skipped.
+    pub fn add_csv(&mut self, text: &str) -> Result<(), OuiError> {
+        for (index, record) in records(text).into_iter().enumerate() {
+            let line = index + 1;
+            let [registry, assignment, organization, ..] = record.as_slice() else {
+                return Err(OuiError(format!("record {}: expected at least 3 columns", line)));
+            };
+            if registry == "Registry" {
+                continue;
+            }
+            let Some(registry) = Registry::from_name(registry) else {
+                continue;
+            };
+            let digits = registry.prefix_bits() as usize / 4;
+            let prefix = Some(assignment)
+                .filter(|assignment| assignment.len() == digits)
+                .and_then(|assignment| u64::from_str_radix(assignment, 16).ok())
+                .ok_or_else(|| OuiError(format!("record {}: invalid {} assignment {:?}", line, registry, assignment)))?;
+            self.blocks.insert((registry, prefix), organization.trim().to_string());
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// The organization `address` was assigned to. Locally administered
+    /// addresses belong to no one.
+    pub fn lookup(&self, address: MacAddress) -> Option<&str> {
+        if address.is_locally_administered() {
+            return None;
+        }
+        Registry::ALL
+            .iter()
+            .find_map(|registry| self.blocks.get(&(*registry, address.prefix(registry.prefix_bits()))))
+            .map(String::as_str)
+    }
+
+    /// The database as gzip-compressed CSV, in the format the embedded copy
+    /// is stored in. Rows are sorted so rebuilding from the same registry
+    /// gives the same file.
+    pub fn to_gzip(&self) -> Vec<u8> {
+        let mut blocks: Vec<_> = self.blocks.iter().collect();
+        blocks.sort_by_key(|((registry, prefix), _)| (registry.prefix_bits(), *prefix));
+        let mut csv = String::from("Registry,Assignment,Organization Name\n");
+        for ((registry, prefix), organization) in blocks {
+            let digits = registry.prefix_bits() as usize / 4;
+            csv.push_str(&format!("{},{:0digits$X},{}\n", registry, prefix, quote(organization), digits = digits));
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(csv.as_bytes()).expect("writing to a Vec cannot fail");
+        encoder.finish().expect("writing to a Vec cannot fail")
+    }
+}
+
+/// Quotes a CSV field if it needs it.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits CSV text into records of fields. Quoted fields may contain commas,
+/// newlines and doubled quotes. Blank lines are skipped.
+fn records(text: &str) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                if !record.is_empty() || !field.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+            }
+            c => field.push(c),
+        }
+    }
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY: &str = "\
+Registry,Assignment,Organization Name,Organization Address\r
+MA-L,001122,\"Acme Networks, Inc.\",\"1 Main St\r
+Springfield\"\r
+MA-L,70B3D5,IEEE Registration Authority,445 Hoes Lane Piscataway NJ US 08554\r
+MA-M,70B3D51,Small Block Ltd,\r
+MA-S,70B3D5123,\"The \"\"Tiny\"\" Co\",\r
+CID,0A1B2C,Not An Address Block,\r
+";
+
+    fn mac(s: &str) -> MacAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn looks_up_longest_matching_block() {
+        let mut database = OuiDatabase::default();
+        database.add_csv(REGISTRY).unwrap();
+        assert_eq!(database.len(), 4);
+        assert_eq!(database.lookup(mac("00:11:22:33:44:55")), Some("Acme Networks, Inc."));
+        assert_eq!(database.lookup(mac("70:B3:D5:12:34:56")), Some("The \"Tiny\" Co"));
+        assert_eq!(database.lookup(mac("70:B3:D5:1F:00:00")), Some("Small Block Ltd"));
+        assert_eq!(database.lookup(mac("70:B3:D5:20:00:00")), Some("IEEE Registration Authority"));
+        assert_eq!(database.lookup(mac("00:11:23:00:00:00")), None);
+    }
+
+    #[test]
+    fn ignores_locally_administered_addresses() {
+        let mut database = OuiDatabase::default();
+        database.add_csv("MA-L,021122,Nobody\n").unwrap();
+        assert!(mac("02:11:22:33:44:55").is_locally_administered());
+        assert!(!mac("00:11:22:33:44:55").is_locally_administered());
+        assert_eq!(database.lookup(mac("02:11:22:33:44:55")), None);
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        for csv in ["MA-L,0011,Short\n", "MA-M,001122,Wrong length\n", "MA-L,00112G,Not hex\n", "MA-L\n"] {
+            assert!(OuiDatabase::default().add_csv(csv).is_err(), "{}", csv);
+        }
+    }
+
+    #[test]
+    fn roundtrips_through_gzip() {
+        let mut database = OuiDatabase::default();
+        database.add_csv(REGISTRY).unwrap();
+        let compressed = database.to_gzip();
+        assert!(compressed.starts_with(&GZIP_MAGIC));
+        assert_eq!(OuiDatabase::from_bytes(&compressed), Ok(database));
+    }
+
+    #[test]
+    fn embedded_registry_is_valid() {
+        let database = OuiDatabase::embedded();
+        assert!(!database.is_empty());
+        assert_eq!(database.lookup(mac("B8:27:EB:00:00:01")), Some("Raspberry Pi Foundation"));
+    }
+}
@@ -0,0 +1,164 @@
+use std::time::{ Duration, Instant };
+
+use crate::backend::ScanError;
+
+const SPINNER_FRAMES: &[&str] = &["|", "/", "-", "\\"];
+const SPINNER_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// Where the scanner is in its scan lifecycle.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanState {
+    Idle,
+    /// A scan is in flight. `id` identifies it so results from a cancelled
+    /// scan can be told apart from the current one; `previous` is restored
+    /// on cancellation.
+    Scanning {
+        id: u64,
+        started: Instant,
+        previous: Box<ScanState>,
+    },
+    Completed {
+        at: Instant,
+    },
+    Failed {
+        error: ScanError,
+        at: Instant,
+    },
+}
+
+impl ScanState {
+    pub fn is_scanning(&self) -> bool {
+        matches!(self, ScanState::Scanning { .. })
+    }
+
+    /// Moves into [`ScanState::Scanning`] for scan `id`. Returns `false` if a
+    /// scan is already running.
+    pub fn start(&mut self, id: u64, now: Instant) -> bool {
+        if self.is_scanning() {
+            return false;
+        }
+        let previous = Box::new(std::mem::replace(self, ScanState::Idle));
+        *self = ScanState::Scanning { id, started: now, previous };
+        true
+    }
+
+    /// Records the outcome of scan `id`. Returns `false` and leaves the state
+    /// untouched when `id` is not the scan currently in flight.
+    pub fn finish<T>(&mut self, id: u64, result: &Result<T, ScanError>, now: Instant) -> bool {
+        match self {
+            ScanState::Scanning { id: current, .. } if *current == id => {
+                *self = match result {
+                    Ok(_) => ScanState::Completed { at: now },
+                    Err(error) => ScanState::Failed { error: error.clone(), at: now },
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Abandons the scan in flight, returning to the state before it began.
+    pub fn cancel(&mut self) {
+        if let ScanState::Scanning { previous, .. } = self {
+            let previous = std::mem::replace(previous.as_mut(), ScanState::Idle);
+            *self = previous;
+        }
+    }
+
+    /// A short human-readable status line for the current state.
+    pub fn status(&self, now: Instant) -> String {
+        match self {
+            ScanState::Idle => String::from("Not scanned yet"),
+            ScanState::Scanning { started, .. } => {
+                format!("Scanning {}", spinner_frame(now.saturating_duration_since(*started)))
+            }
+            ScanState::Completed { at } => {
+                format!("Last scanned {}", format_age(now.saturating_duration_since(*at)))
+            }
+            ScanState::Failed { at, .. } => {
+                format!("Scan failed {}", format_age(now.saturating_duration_since(*at)))
+            }
+        }
+    }
+}
+
+fn spinner_frame(elapsed: Duration) -> &'static str {
+    let frame = (elapsed.as_millis() / SPINNER_FRAME_TIME.as_millis()) as usize;
+    SPINNER_FRAMES[frame % SPINNER_FRAMES.len()]
+}
+
+/// Formats an elapsed time as "just now", "N seconds ago", "N minutes ago"
+/// or "N hours ago".
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0 => String::from("just now"),
+        1 => String::from("1 second ago"),
+        2..=59 => format!("{} seconds ago", seconds),
+        60..=119 => String::from("1 minute ago"),
+        120..=3599 => format!("{} minutes ago", seconds / 60),
+        3600..=7199 => String::from("1 hour ago"),
+        _ => format!("{} hours ago", seconds / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_is_refused_while_scanning() {
+        let now = Instant::now();
+        let mut state = ScanState::Idle;
+        assert!(state.start(1, now));
+        assert!(!state.start(2, now));
+    }
+
+    #[test]
+    fn finish_completes_and_allows_next_scan() {
+        let now = Instant::now();
+        let mut state = ScanState::Idle;
+        state.start(1, now);
+        assert!(state.finish(1, &Ok::<_, ScanError>(()), now));
+        assert_eq!(state, ScanState::Completed { at: now });
+        assert!(state.start(2, now));
+    }
+
+    #[test]
+    fn finish_records_failure() {
+        let now = Instant::now();
+        let mut state = ScanState::Idle;
+        state.start(1, now);
+        state.finish(1, &Err::<(), _>(ScanError::RadioDisabled), now);
+        assert_eq!(state, ScanState::Failed { error: ScanError::RadioDisabled, at: now });
+    }
+
+    #[test]
+    fn stale_results_are_ignored() {
+        let now = Instant::now();
+        let mut state = ScanState::Idle;
+        state.start(1, now);
+        state.cancel();
+        state.start(2, now);
+        assert!(!state.finish(1, &Ok::<_, ScanError>(()), now));
+        assert!(state.is_scanning());
+    }
+
+    #[test]
+    fn cancel_restores_previous_state() {
+        let now = Instant::now();
+        let mut state = ScanState::Completed { at: now };
+        state.start(1, now);
+        state.cancel();
+        assert_eq!(state, ScanState::Completed { at: now });
+    }
+
+    #[test]
+    fn formats_ages() {
+        assert_eq!(format_age(Duration::from_millis(400)), "just now");
+        assert_eq!(format_age(Duration::from_secs(1)), "1 second ago");
+        assert_eq!(format_age(Duration::from_secs(42)), "42 seconds ago");
+        assert_eq!(format_age(Duration::from_secs(150)), "2 minutes ago");
+        assert_eq!(format_age(Duration::from_secs(4000)), "1 hour ago");
+    }
+}
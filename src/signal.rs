@@ -0,0 +1,147 @@
+//! Signal quality levels and the thresholds that separate them.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// How usable a signal is, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SignalLevel {
+    Excellent,
+    Good,
+    Fair,
+    Weak,
+    Poor,
+}
+
+impl SignalLevel {
+    pub const ALL: [SignalLevel; 5] = [
+        SignalLevel::Excellent,
+        SignalLevel::Good,
+        SignalLevel::Fair,
+        SignalLevel::Weak,
+        SignalLevel::Poor,
+    ];
+}
+
+impl fmt::Display for SignalLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SignalLevel::Excellent => "Excellent",
+            SignalLevel::Good => "Good",
+            SignalLevel::Fair => "Fair",
+            SignalLevel::Weak => "Weak",
+            SignalLevel::Poor => "Poor",
+        })
+    }
+}
+
+/// Lowest signal, in dBm, for each level above [`SignalLevel::Poor`].
+///
+/// The defaults are the usual targets for voice-grade coverage surveys:
+/// -67 dBm for VoIP, -70 dBm for reliable data and -80 dBm as the edge of
+/// basic connectivity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalThresholds {
+    pub excellent: i32,
+    pub good: i32,
+    pub fair: i32,
+    pub weak: i32,
+}
+
+impl Default for SignalThresholds {
+    fn default() -> Self {
+        Self { excellent: -50, good: -67, fair: -70, weak: -80 }
+    }
+}
+
+impl SignalThresholds {
+    pub fn level(&self, dbm: i32) -> SignalLevel {
+        if dbm >= self.excellent {
+            SignalLevel::Excellent
+        } else if dbm >= self.good {
+            SignalLevel::Good
+        } else if dbm >= self.fair {
+            SignalLevel::Fair
+        } else if dbm >= self.weak {
+            SignalLevel::Weak
+        } else {
+            SignalLevel::Poor
+        }
+    }
+
+    /// The range a level covers, for the legend, e.g. `-67 to -51 dBm`.
+    pub fn describe(&self, level: SignalLevel) -> String {
+        match level {
+            SignalLevel::Excellent => format!("≥ {} dBm", self.excellent),
+            SignalLevel::Good => format!("{} to {} dBm", self.good, self.excellent - 1),
+            SignalLevel::Fair => format!("{} to {} dBm", self.fair, self.good - 1),
+            SignalLevel::Weak => format!("{} to {} dBm", self.weak, self.fair - 1),
+            SignalLevel::Poor => format!("< {} dBm", self.weak),
+        }
+    }
+}
+
+impl FromStr for SignalThresholds {
+    type Err = String;
+
+    /// Parses four descending dBm values, e.g. `-50,-67,-70,-80`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid signal thresholds {:?}, expected four descending dBm values such as -50,-67,-70,-80", s);
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| err())?;
+        let [excellent, good, fair, weak] = values[..] else {
+            return Err(err());
+        };
+        if !(excellent > good && good > fair && fair > weak) {
+            return Err(err());
+        }
+        Ok(Self { excellent, good, fair, weak })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{ dbm_to_percent, percent_to_dbm };
+
+    #[test]
+    fn classifies_with_default_thresholds() {
+        let thresholds = SignalThresholds::default();
+        let levels: Vec<_> = [-40, -50, -51, -67, -69, -70, -80, -81, -120]
+            .into_iter()
+            .map(|dbm| thresholds.level(dbm))
+            .collect();
+        assert_eq!(levels, [
+            SignalLevel::Excellent,
+            SignalLevel::Excellent,
+            SignalLevel::Good,
+            SignalLevel::Good,
+            SignalLevel::Fair,
+            SignalLevel::Fair,
+            SignalLevel::Weak,
+            SignalLevel::Poor,
+            SignalLevel::Poor,
+        ]);
+        assert_eq!(thresholds.describe(SignalLevel::Good), "-67 to -51 dBm");
+    }
+
+    #[test]
+    fn parses_thresholds() {
+        assert_eq!("-45, -60,-72,-85".parse(), Ok(SignalThresholds { excellent: -45, good: -60, fair: -72, weak: -85 }));
+        assert!("-50,-67,-70".parse::<SignalThresholds>().is_err());
+        assert!("-50,-70,-67,-80".parse::<SignalThresholds>().is_err());
+        assert!("-50,-67,x,-80".parse::<SignalThresholds>().is_err());
+    }
+
+    #[test]
+    fn converts_between_percent_and_dbm() {
+        assert_eq!(percent_to_dbm(100), -40);
+        assert_eq!(percent_to_dbm(0), -100);
+        for dbm in -100..=-40 {
+            assert!(percent_to_dbm(dbm_to_percent(dbm)).abs_diff(dbm) <= 1, "{} dBm", dbm);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Safe rendering of SSID bytes, and detection of lookalike SSIDs.
+//!
+//! An SSID is up to 32 arbitrary bytes. For display, invalid UTF-8, control
+//! characters and invisible formatting characters are escaped, so two SSIDs
+//! that differ in their bytes never render the same. Characters that look
+//! like ASCII ones but are not, such as Cyrillic `о`, are left as they are
+//! but reported, so the view can flag an SSID imitating another.
+
+use std::collections::HashMap;
+use std::fmt::{ self, Write };
+
+use crate::network::Ssid;
+
+/// Renders SSID bytes with `\xNN` for invalid UTF-8 and ASCII control
+/// characters, `\u{NNNN}` for other control and invisible characters, and
+/// `\\` for a backslash.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if c.is_ascii_control() => {
+                    let _ = write!(escaped, "\\x{:02x}", c as u32);
+                }
+                c if c.is_control() || is_invisible(c) => {
+                    let _ = write!(escaped, "\\u{{{:x}}}", c as u32);
+                }
+                c => escaped.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "\\x{:02x}", byte);
+        }
+    }
+    escaped
+}
+
+/// Characters that render as nothing, or only change how their neighbours
+/// render: zero-width spaces and joiners, bidirectional overrides and the
+/// like.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00ad}' | '\u{034f}' | '\u{115f}' | '\u{1160}' | '\u{180e}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{202a}'..='\u{202e}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{3164}' | '\u{feff}' | '\u{ffa0}'
+    )
+}
+
+/// Non-ASCII letters commonly used to imitate ASCII ones, and what they
+/// imitate. Fullwidth forms are handled apart.
+const CONFUSABLES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'), ('в', 'b'), ('е', 'e'), ('һ', 'h'), ('і', 'i'), ('ј', 'j'), ('к', 'k'), ('м', 'm'),
+    ('н', 'h'), ('о', 'o'), ('р', 'p'), ('с', 'c'), ('т', 't'), ('у', 'y'), ('х', 'x'), ('ѕ', 's'),
+    ('ԁ', 'd'), ('ԛ', 'q'), ('ԝ', 'w'), ('А', 'A'), ('В', 'B'), ('Е', 'E'), ('І', 'I'), ('Ј', 'J'),
+    ('К', 'K'), ('М', 'M'), ('Н', 'H'), ('О', 'O'), ('Р', 'P'), ('С', 'C'), ('Т', 'T'), ('Х', 'X'),
+    ('Ѕ', 'S'), ('Ү', 'Y'),
+    // Greek
+    ('α', 'a'), ('ι', 'i'), ('κ', 'k'), ('ν', 'v'), ('ο', 'o'), ('ρ', 'p'), ('υ', 'u'), ('Α', 'A'),
+    ('Β', 'B'), ('Ε', 'E'), ('Ζ', 'Z'), ('Η', 'H'), ('Ι', 'I'), ('Κ', 'K'), ('Μ', 'M'), ('Ν', 'N'),
+    ('Ο', 'O'), ('Ρ', 'P'), ('Τ', 'T'), ('Υ', 'Y'), ('Χ', 'X'),
+    // Latin lookalikes and spaces
+    ('ı', 'i'), ('ǀ', 'l'), ('ℓ', 'l'), ('\u{00a0}', ' '), ('\u{2007}', ' '), ('\u{202f}', ' '),
+];
+
+/// The ASCII character `c` imitates, if it is a known lookalike.
+fn confusable(c: char) -> Option<char> {
+    if let '\u{ff01}'..='\u{ff5e}' = c {
+        return char::from_u32(c as u32 - 0xfee0);
+    }
+    CONFUSABLES
+        .iter()
+        .find(|(lookalike, _)| *lookalike == c)
+        .map(|(_, ascii)| *ascii)
+}
+
+/// A character that makes an SSID look like something it is not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookalike {
+    /// A character imitating the given ASCII one.
+    Homoglyph(char, char),
+    /// A character that renders as nothing.
+    Invisible(char),
+}
+
+impl fmt::Display for Lookalike {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lookalike::Homoglyph(c, ascii) => write!(f, "U+{:04X} looks like '{}'", *c as u32, ascii),
+            Lookalike::Invisible(c) => write!(f, "U+{:04X} is invisible", *c as u32),
+        }
+    }
+}
+
+impl Ssid {
+    /// The bytes as space-separated hex, e.g. `48 6f 6d 65`.
+    pub fn to_hex(&self) -> String {
+        self.as_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Homoglyphs and invisible characters in the SSID, in order.
+    pub fn lookalikes(&self) -> Vec<Lookalike> {
+        String::from_utf8_lossy(self.as_bytes())
+            .chars()
+            .filter_map(|c| {
+                if is_invisible(c) {
+                    Some(Lookalike::Invisible(c))
+                } else {
+                    confusable(c).map(|ascii| Lookalike::Homoglyph(c, ascii))
+                }
+            })
+            .collect()
+    }
+
+    /// What the SSID looks like: homoglyphs replaced by the ASCII they
+    /// imitate and invisible characters dropped. Two SSIDs with the same
+    /// skeleton are hard to tell apart on screen.
+    pub fn skeleton(&self) -> String {
+        String::from_utf8_lossy(self.as_bytes())
+            .chars()
+            .filter(|c| !is_invisible(*c))
+            .map(|c| confusable(c).unwrap_or(c))
+            .collect()
+    }
+}
+
+/// Pairs each SSID in `ssids` that has lookalike characters with an SSID
+/// free of them that it looks the same as, if there is one.
+pub fn imitations<'a>(ssids: impl IntoIterator<Item = &'a Ssid> + Clone) -> HashMap<&'a Ssid, &'a Ssid> {
+    let genuine: HashMap<String, &Ssid> = ssids
+        .clone()
+        .into_iter()
+        .filter(|ssid| ssid.hidden().is_none() && ssid.lookalikes().is_empty())
+        .map(|ssid| (ssid.skeleton(), ssid))
+        .collect();
+    ssids
+        .into_iter()
+        .filter(|ssid| !ssid.lookalikes().is_empty())
+        .filter_map(|ssid| Some((ssid, *genuine.get(&ssid.skeleton())?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_unprintable_bytes() {
+        assert_eq!(escape(b"HomeNet"), "HomeNet");
+        assert_eq!(escape("café".as_bytes()), "café");
+        assert_eq!(escape(b"a\x00b\x1b\tc\n"), r"a\x00b\x1b\tc\n");
+        assert_eq!(escape(b"caf\xe9 \xff"), r"caf\xe9 \xff");
+        assert_eq!(escape(br"back\slash"), r"back\\slash");
+        assert_eq!(escape("Home\u{200b}Net\u{202e}".as_bytes()), r"Home\u{200b}Net\u{202e}");
+        assert_eq!(escape("\u{85}".as_bytes()), r"\u{85}");
+    }
+
+    #[test]
+    fn shows_hex() {
+        assert_eq!(Ssid::from_bytes(&b"Hi\xff"[..]).to_hex(), "48 69 ff");
+        assert_eq!(Ssid::from("").to_hex(), "");
+    }
+
+    #[test]
+    fn finds_lookalike_characters() {
+        let cyrillic = Ssid::from("HоmeNеt");
+        assert_eq!(cyrillic.lookalikes(), [Lookalike::Homoglyph('о', 'o'), Lookalike::Homoglyph('е', 'e')]);
+        assert_eq!(cyrillic.skeleton(), "HomeNet");
+        assert_eq!(cyrillic.lookalikes()[0].to_string(), "U+043E looks like 'o'");
+
+        let zero_width = Ssid::from("Home\u{200b}Net");
+        assert_eq!(zero_width.lookalikes(), [Lookalike::Invisible('\u{200b}')]);
+        assert_eq!(zero_width.skeleton(), "HomeNet");
+
+        assert_eq!(Ssid::from("ＨｏｍｅＮｅｔ").skeleton(), "HomeNet");
+        assert!(Ssid::from("Café Zürich").lookalikes().is_empty());
+    }
+
+    #[test]
+    fn pairs_imitations_with_the_ssid_they_imitate() {
+        let ssids = [Ssid::from("HomeNet"), Ssid::from("HоmeNet"), Ssid::from("Cafe\u{200d}"), Ssid::from("Office")];
+        let imitations = imitations(&ssids);
+        assert_eq!(imitations.len(), 1);
+        assert_eq!(imitations[&ssids[1]], &ssids[0]);
+    }
+}
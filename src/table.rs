@@ -0,0 +1,349 @@
+//! Columns, sort order and column widths of the network table.
+//!
+//! The widgets are built in `main.rs`; this module holds the state behind
+//! them, which outlives individual scans so rows keep their place on rescan.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::SystemTime;
+
+use crate::backend::Capabilities;
+use crate::network::{ Network, Security };
+use crate::scan_state::format_age;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    Ssid,
+    Bssid,
+    Vendor,
+    Channel,
+    Band,
+    Width,
+    Signal,
+    Security,
+    LastSeen,
+}
+
+impl Column {
+    pub const ALL: [Column; 9] = [
+        Column::Ssid,
+        Column::Bssid,
+        Column::Vendor,
+        Column::Channel,
+        Column::Band,
+        Column::Width,
+        Column::Signal,
+        Column::Security,
+        Column::LastSeen,
+    ];
+
+    /// The columns a backend with `capabilities` fills in; the others would
+    /// stay blank.
+    pub fn supported(capabilities: Capabilities) -> Vec<Column> {
+        Column::ALL
+            .into_iter()
+            .filter(|column| match column {
+                Column::Channel | Column::Band | Column::Width => capabilities.frequency,
+                Column::Security => capabilities.security,
+                _ => true,
+            })
+            .collect()
+    }
+
+    fn default_width(self) -> f32 {
+        match self {
+            Column::Ssid => 200.0,
+            Column::Bssid => 160.0,
+            Column::Vendor => 140.0,
+            Column::Channel => 80.0,
+            Column::Band => 80.0,
+            Column::Width => 90.0,
+            Column::Signal => 130.0,
+            Column::Security => 300.0,
+            Column::LastSeen => 130.0,
+        }
+    }
+
+    /// The cell contents for `network`. Security cells get an icon and
+    /// colour on top of this in the view.
+    pub fn text(self, network: &Network, now: SystemTime) -> String {
+        let or_blank = |value: Option<String>| value.unwrap_or_default();
+        match self {
+            Column::Ssid => network.ssid_label(),
+            Column::Bssid => network.bssid.to_string(),
+            Column::Vendor => match network.vendor() {
+                Some(vendor) => vendor.to_string(),
+                None if network.bssid.is_locally_administered() => String::from("Locally administered"),
+                None => String::new(),
+            },
+            Column::Channel => or_blank(network.channel.map(|channel| channel.to_string())),
+            Column::Band => or_blank(network.band.map(|band| band.to_string())),
+            Column::Width => or_blank(network.channel_width.map(|width| width.to_string())),
+            Column::Signal => network.signal_summary(),
+            Column::Security => network.security_summary(),
+            Column::LastSeen => format_age(now.duration_since(network.last_seen).unwrap_or_default()),
+        }
+    }
+
+    /// Orders two networks by this column in `order`. Missing values sort
+    /// last either way.
+    fn compare(self, a: &Network, b: &Network, order: SortOrder) -> Ordering {
+        fn present_first<T: Ord>(a: Option<T>, b: Option<T>, order: SortOrder) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => order.apply(a.cmp(&b)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+
+        match self {
+            Column::Ssid => order.apply(a.ssid.display().to_lowercase().cmp(&b.ssid.display().to_lowercase())),
+            Column::Bssid => order.apply(a.bssid.cmp(&b.bssid)),
+            Column::Vendor => present_first(a.vendor().map(str::to_lowercase), b.vendor().map(str::to_lowercase), order),
+            Column::Channel => present_first(a.channel, b.channel, order),
+            Column::Band => present_first(a.band, b.band, order),
+            Column::Width => present_first(a.channel_width, b.channel_width, order),
+            Column::Signal => order.apply(a.effective_signal_dbm().cmp(&b.effective_signal_dbm())),
+            Column::Security => order.apply(
+                security_rank(a.security)
+                    .cmp(&security_rank(b.security))
+                    .then_with(|| a.has_weak_security().cmp(&b.has_weak_security()).reverse()),
+            ),
+            // Ascending by age, so the most recently seen come first.
+            Column::LastSeen => order.apply(b.last_seen.cmp(&a.last_seen)),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Column::Ssid => "SSID",
+            Column::Bssid => "BSSID",
+            Column::Vendor => "Vendor",
+            Column::Channel => "Channel",
+            Column::Band => "Band",
+            Column::Width => "Width",
+            Column::Signal => "Signal",
+            Column::Security => "Security",
+            Column::LastSeen => "Last seen",
+        })
+    }
+}
+
+/// Orders security modes from least to most protective.
+fn security_rank(security: Security) -> u8 {
+    match security {
+        Security::Unknown => 0,
+        Security::Open => 1,
+        Security::Wep => 2,
+        Security::WpaPsk => 3,
+        Security::Owe => 4,
+        Security::Wpa2Psk => 5,
+        Security::Wpa2Wpa3 => 6,
+        Security::Wpa3Sae => 7,
+        Security::Enterprise => 8,
+        Security::EnterpriseSuiteB => 9,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    /// Turns an ascending ordering into one in this order.
+    fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub column: Column,
+    pub order: SortOrder,
+}
+
+impl Default for Sort {
+    /// Strongest signal first.
+    fn default() -> Self {
+        Self { column: Column::Signal, order: SortOrder::Descending }
+    }
+}
+
+impl Sort {
+    /// Clicking the sorted column flips the order; clicking another one
+    /// sorts by it ascending.
+    pub fn toggle(&mut self, column: Column) {
+        if self.column == column {
+            self.order = match self.order {
+                SortOrder::Ascending => SortOrder::Descending,
+                SortOrder::Descending => SortOrder::Ascending,
+            };
+        } else {
+            *self = Self { column, order: SortOrder::Ascending };
+        }
+    }
+
+    /// Header label, with an arrow on the sorted column.
+    pub fn header(&self, column: Column) -> String {
+        match self.order {
+            _ if self.column != column => column.to_string(),
+            SortOrder::Ascending => format!("{} ▲", column),
+            SortOrder::Descending => format!("{} ▼", column),
+        }
+    }
+
+    /// Sorts `networks` by the column, then by BSSID so networks that tie
+    /// keep a fixed order from one scan to the next.
+    pub fn apply<'a>(&self, networks: impl IntoIterator<Item = &'a Network>) -> Vec<&'a Network> {
+        let mut sorted: Vec<_> = networks.into_iter().collect();
+        sorted.sort_by(|a, b| self.column.compare(a, b, self.order).then_with(|| a.bssid.cmp(&b.bssid)));
+        sorted
+    }
+}
+
+/// Narrowest a column can be dragged to.
+pub const MIN_COLUMN_WIDTH: f32 = 40.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnWidths([f32; Column::ALL.len()]);
+
+impl Default for ColumnWidths {
+    fn default() -> Self {
+        Self(Column::ALL.map(Column::default_width))
+    }
+}
+
+impl ColumnWidths {
+    pub fn get(&self, column: Column) -> f32 {
+        self.0[column as usize]
+    }
+
+    pub fn resize(&mut self, column: Column, delta: f32) {
+        let width = &mut self.0[column as usize];
+        *width = (*width + delta).max(MIN_COLUMN_WIDTH);
+    }
+}
+
+/// A column border being dragged. The cursor position is only known from
+/// the first move after the press, so that move sets the origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resize {
+    pub column: Column,
+    last_x: Option<f32>,
+}
+
+impl Resize {
+    pub fn new(column: Column) -> Self {
+        Self { column, last_x: None }
+    }
+
+    pub fn drag(&mut self, x: f32, widths: &mut ColumnWidths) {
+        if let Some(last_x) = self.last_x {
+            widths.resize(self.column, x - last_x);
+        }
+        self.last_x = Some(x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn network(ssid: &str, last_octet: u8, dbm: i32) -> Network {
+        Network::with_signal(ssid, &format!("AA:BB:CC:DD:EE:{:02X}", last_octet), dbm)
+    }
+
+    fn ssids(networks: &[&Network]) -> Vec<String> {
+        networks.iter().map(|network| network.ssid.to_string()).collect()
+    }
+
+    #[test]
+    fn sorts_by_signal_then_bssid() {
+        let networks = [network("b", 2, -60), network("a", 3, -40), network("c", 1, -60)];
+        let mut sort = Sort::default();
+        assert_eq!(ssids(&sort.apply(&networks)), ["a", "c", "b"]);
+
+        sort.toggle(Column::Signal);
+        assert_eq!(sort.order, SortOrder::Ascending);
+        assert_eq!(ssids(&sort.apply(&networks)), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn sorts_missing_values_last() {
+        let mut networks = [network("a", 1, -50), network("b", 2, -50), network("c", 3, -50)];
+        networks[0].channel = Some(36);
+        networks[2].channel = Some(1);
+        let mut sort = Sort::default();
+        sort.toggle(Column::Channel);
+        assert_eq!(ssids(&sort.apply(&networks)), ["c", "a", "b"]);
+        assert_eq!(sort.header(Column::Channel), "Channel ▲");
+        assert_eq!(sort.header(Column::Ssid), "SSID");
+
+        sort.toggle(Column::Channel);
+        assert_eq!(ssids(&sort.apply(&networks)), ["a", "c", "b"]);
+        assert_eq!(sort.header(Column::Channel), "Channel ▼");
+    }
+
+    #[test]
+    fn sorts_ssids_case_insensitively() {
+        let networks = [network("beta", 1, -50), network("Alpha", 2, -50), network("alpha", 3, -50)];
+        let mut sort = Sort::default();
+        sort.toggle(Column::Ssid);
+        assert_eq!(ssids(&sort.apply(&networks)), ["Alpha", "alpha", "beta"]);
+    }
+
+    #[test]
+    fn leaves_out_columns_the_backend_cannot_fill() {
+        let capabilities = Capabilities { security: true, ..Capabilities::default() };
+        assert_eq!(Column::supported(capabilities), [
+            Column::Ssid,
+            Column::Bssid,
+            Column::Vendor,
+            Column::Signal,
+            Column::Security,
+            Column::LastSeen,
+        ]);
+        let capabilities = Capabilities { frequency: true, security: true, ..capabilities };
+        assert_eq!(Column::supported(capabilities), Column::ALL);
+    }
+
+    #[test]
+    fn shows_age_in_last_seen_column() {
+        let network = network("a", 1, -50);
+        let now = network.last_seen + Duration::from_secs(90);
+        assert_eq!(Column::LastSeen.text(&network, now), "1 minute ago");
+        assert_eq!(Column::Channel.text(&network, now), "");
+    }
+
+    #[test]
+    fn labels_locally_administered_bssids_without_vendor() {
+        let mut network = network("a", 1, -50);
+        let now = network.last_seen;
+        assert_eq!(Column::Vendor.text(&network, now), "Locally administered");
+        network.bssid = "00:11:22:33:44:55".parse().unwrap();
+        assert_eq!(Column::Vendor.text(&network, now), "");
+        network.registered_vendor = Some(String::from("Acme Networks"));
+        assert_eq!(Column::Vendor.text(&network, now), "Acme Networks");
+    }
+
+    #[test]
+    fn resizes_from_first_cursor_position() {
+        let mut widths = ColumnWidths::default();
+        let mut resize = Resize::new(Column::Bssid);
+        resize.drag(500.0, &mut widths);
+        assert_eq!(widths.get(Column::Bssid), 160.0);
+        resize.drag(530.0, &mut widths);
+        assert_eq!(widths.get(Column::Bssid), 190.0);
+        resize.drag(0.0, &mut widths);
+        assert_eq!(widths.get(Column::Bssid), MIN_COLUMN_WIDTH);
+    }
+}